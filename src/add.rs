@@ -6,7 +6,7 @@ use toml_edit::{Array, DocumentMut, Formatted, InlineTable, Value};
 #[cfg(feature = "cli")]
 use clap::Parser;
 
-use crate::{Config, config::ConfigLoadError};
+use crate::{Config, VersionRequirement, config::ConfigLoadError};
 
 #[derive(Debug, Clone, PartialEq, Default)]
 #[cfg_attr(feature = "cli", derive(Parser))]
@@ -62,6 +62,30 @@ impl AddOptions {
     }
 }
 
+/// Splits a package given to `rv add` into its name and version requirement if any.
+/// `dplyr@1.1.4` is the same as `dplyr==1.1.4` and any requirements can be written after the name,
+/// eg `dplyr>=1.1, <1.2`.
+pub fn parse_package_spec(spec: &str) -> Result<(&str, Option<String>), String> {
+    let (name, version) = if let Some((name, version)) = spec.split_once('@') {
+        (name, Some(format!("== {}", version.trim())))
+    } else if let Some(idx) = spec.find(['<', '>', '=']) {
+        (&spec[..idx], Some(spec[idx..].trim().to_string()))
+    } else {
+        (spec, None)
+    };
+
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(format!("Missing package name in `{spec}`"));
+    }
+    if let Some(v) = &version {
+        VersionRequirement::parse_constraints(v)
+            .map_err(|e| format!("Invalid version requirement in `{spec}`: {e}"))?;
+    }
+
+    Ok((name, version))
+}
+
 pub fn read_and_verify_config(config_file: impl AsRef<Path>) -> Result<DocumentMut, AddError> {
     let config_file = config_file.as_ref();
     let _ = Config::from_file(config_file).map_err(|e| AddError {
@@ -84,18 +108,30 @@ pub fn add_packages(
     // collect the names of all of the dependencies
     let config_dep_names = config_deps
         .iter()
-        .filter_map(|v| match v {
-            Value::String(s) => Some(s.value().as_str()),
-            Value::InlineTable(t) => t.get("name").and_then(|v| v.as_str()),
-            _ => None,
-        })
+        .filter_map(dependency_name)
         .map(|s| s.to_string()) // Need to allocate so values are not a reference to a mut
         .collect::<Vec<_>>();
 
     // Determine if the dep to add is in the config, if not add it
-    for package_name in packages {
-        if !config_dep_names.contains(&package_name) {
-            let dep_value = create_dependency_value(&package_name, &options)?;
+    for package in packages {
+        // Specs are validated before getting there
+        let (package_name, version) =
+            parse_package_spec(&package).unwrap_or((package.as_str(), None));
+        let package_name = package_name.to_string();
+
+        if config_dep_names.contains(&package_name) {
+            // Setting a version on an existing dependency replaces it
+            if version.is_some()
+                && let Some(existing) = config_deps
+                    .iter_mut()
+                    .find(|v| dependency_name(v) == Some(package_name.as_str()))
+            {
+                let decor = existing.decor().clone();
+                *existing = create_dependency_value(&package_name, version.as_deref(), &options)?;
+                *existing.decor_mut() = decor;
+            }
+        } else {
+            let dep_value = create_dependency_value(&package_name, version.as_deref(), &options)?;
             config_deps.push(dep_value);
             // Couldn't format value before pushing, so adding formatting after its added
             if let Some(last) = config_deps.iter_mut().last() {
@@ -111,8 +147,99 @@ pub fn add_packages(
     Ok(())
 }
 
-fn create_dependency_value(package_name: &str, options: &AddOptions) -> Result<Value, AddError> {
-    if options.is_empty() {
+/// Removes the given packages from the dependencies of the config, keeping the formatting and
+/// comments of the remaining entries.
+/// Returns the names of the packages that were not found in the dependencies, in which case
+/// the document is left untouched.
+pub fn remove_packages(config_doc: &mut DocumentMut, packages: &[String]) -> Vec<String> {
+    let config_deps = get_mut_array(config_doc);
+
+    let config_dep_names = config_deps
+        .iter()
+        .filter_map(dependency_name)
+        .collect::<Vec<_>>();
+    let missing = packages
+        .iter()
+        .filter(|p| !config_dep_names.contains(&p.as_str()))
+        .cloned()
+        .collect::<Vec<_>>();
+
+    if !missing.is_empty() {
+        return missing;
+    }
+
+    // Go in reverse so indices stay valid while removing
+    for idx in (0..config_deps.len()).rev() {
+        let to_remove = config_deps
+            .get(idx)
+            .and_then(dependency_name)
+            .is_some_and(|n| packages.iter().any(|p| p == n));
+        if !to_remove {
+            continue;
+        }
+        config_deps.remove(idx);
+        // A comment on the same line as the removed dependency ends up in the decor of whatever
+        // follows it so we drop it alongside the dependency
+        if let Some(next) = config_deps.get_mut(idx) {
+            let prefix = next
+                .decor()
+                .prefix()
+                .and_then(|p| p.as_str())
+                .unwrap_or_default();
+            let prefix = strip_same_line_comment(prefix);
+            next.decor_mut().set_prefix(prefix);
+        } else {
+            let trailing = config_deps.trailing().as_str().unwrap_or_default();
+            let trailing = strip_same_line_comment(trailing);
+            config_deps.set_trailing(trailing);
+        }
+    }
+
+    config_deps.set_trailing_comma(true);
+
+    missing
+}
+
+/// Sets `project.conda_env`, leaving the rest of the document as written
+pub fn set_conda_env(config_doc: &mut DocumentMut, conda_env: &str) {
+    // the document was verified to be a valid config so it has a project table
+    let project = config_doc
+        .get_mut("project")
+        .and_then(|item| item.as_table_mut())
+        .unwrap();
+    match project.get_mut("conda_env").and_then(|i| i.as_value_mut()) {
+        Some(value) => {
+            let decor = value.decor().clone();
+            *value = Value::from(conda_env);
+            *value.decor_mut() = decor;
+        }
+        None => {
+            project.insert("conda_env", toml_edit::value(conda_env));
+        }
+    }
+}
+
+fn strip_same_line_comment(decor: &str) -> String {
+    match decor.split_once('\n') {
+        Some((line, rest)) if line.contains('#') => format!("\n{rest}"),
+        _ => decor.to_string(),
+    }
+}
+
+fn dependency_name(value: &Value) -> Option<&str> {
+    match value {
+        Value::String(s) => Some(s.value().as_str()),
+        Value::InlineTable(t) => t.get("name").and_then(|v| v.as_str()),
+        _ => None,
+    }
+}
+
+fn create_dependency_value(
+    package_name: &str,
+    version: Option<&str>,
+    options: &AddOptions,
+) -> Result<Value, AddError> {
+    if options.is_empty() && version.is_none() {
         // Simple string dependency
         return Ok(Value::String(Formatted::new(package_name.to_string())));
     }
@@ -149,6 +276,10 @@ fn create_dependency_value(package_name: &str, options: &AddOptions) -> Result<V
             table.insert("repository", Value::from(repository.as_str()));
         }
 
+        if let Some(version) = version {
+            table.insert("version", Value::from(version));
+        }
+
         if options.force_source {
             table.insert("force_source", Value::from(true));
         }
@@ -207,9 +338,12 @@ pub enum AddErrorKind {
 #[cfg(test)]
 mod tests {
     use super::AddOptions;
-    use crate::{add_packages, read_and_verify_config};
+    use crate::{
+        add_packages, parse_package_spec, read_and_verify_config, remove_packages, set_conda_env,
+    };
 
     const BASELINE_CONFIG: &str = "src/tests/valid_config/baseline_for_add.toml";
+    const BASELINE_REMOVE_CONFIG: &str = "src/tests/valid_config/baseline_for_remove.toml";
 
     // Simple tests - one feature at a time
 
@@ -433,4 +567,106 @@ mod tests {
         .unwrap();
         insta::assert_snapshot!(doc.to_string());
     }
+
+    #[test]
+    fn can_parse_package_spec() {
+        assert_eq!(parse_package_spec("dplyr").unwrap(), ("dplyr", None));
+        assert_eq!(
+            parse_package_spec("dplyr@1.1.4").unwrap(),
+            ("dplyr", Some("== 1.1.4".to_string()))
+        );
+        assert_eq!(
+            parse_package_spec("dplyr>=1.1, <1.2").unwrap(),
+            ("dplyr", Some(">=1.1, <1.2".to_string()))
+        );
+        assert!(parse_package_spec("dplyr@").is_err());
+        assert!(parse_package_spec(">=1.1").is_err());
+    }
+
+    #[test]
+    fn add_with_exact_version() {
+        let mut doc = read_and_verify_config(BASELINE_CONFIG).unwrap();
+        add_packages(
+            &mut doc,
+            vec!["dplyr@1.1.4".to_string()],
+            AddOptions::default(),
+        )
+        .unwrap();
+        insta::assert_snapshot!(doc.to_string());
+    }
+
+    #[test]
+    fn add_with_version_requirement_and_repository() {
+        let mut doc = read_and_verify_config(BASELINE_CONFIG).unwrap();
+        add_packages(
+            &mut doc,
+            vec!["dplyr>=1.1".to_string()],
+            AddOptions {
+                repository: Some("ppm".to_string()),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        insta::assert_snapshot!(doc.to_string());
+    }
+
+    #[test]
+    fn add_with_version_replaces_existing() {
+        let mut doc = read_and_verify_config(BASELINE_REMOVE_CONFIG).unwrap();
+        add_packages(
+            &mut doc,
+            vec!["dplyr@1.1.4".to_string(), "tidyr".to_string()],
+            AddOptions::default(),
+        )
+        .unwrap();
+        insta::assert_snapshot!(doc.to_string());
+    }
+
+    #[test]
+    fn remove_simple_package() {
+        let mut doc = read_and_verify_config(BASELINE_REMOVE_CONFIG).unwrap();
+        let missing = remove_packages(&mut doc, &["dplyr".to_string()]);
+        assert!(missing.is_empty());
+        insta::assert_snapshot!(doc.to_string());
+    }
+
+    #[test]
+    fn remove_detailed_packages() {
+        let mut doc = read_and_verify_config(BASELINE_REMOVE_CONFIG).unwrap();
+        let missing = remove_packages(
+            &mut doc,
+            &[
+                "ggplot2".to_string(),
+                "mypkg".to_string(),
+                "localpkg".to_string(),
+                "urlpkg".to_string(),
+            ],
+        );
+        assert!(missing.is_empty());
+        insta::assert_snapshot!(doc.to_string());
+    }
+
+    #[test]
+    fn remove_last_package() {
+        let mut doc = read_and_verify_config(BASELINE_REMOVE_CONFIG).unwrap();
+        let missing = remove_packages(&mut doc, &["tidyr".to_string()]);
+        assert!(missing.is_empty());
+        insta::assert_snapshot!(doc.to_string());
+    }
+
+    #[test]
+    fn remove_unknown_package_does_nothing() {
+        let mut doc = read_and_verify_config(BASELINE_REMOVE_CONFIG).unwrap();
+        let missing = remove_packages(&mut doc, &["dplyr".to_string(), "shiny".to_string()]);
+        assert_eq!(missing, vec!["shiny".to_string()]);
+        assert!(doc.to_string().contains("\"dplyr\""));
+    }
+
+    #[test]
+    fn set_conda_env_keeps_formatting() {
+        let mut doc = read_and_verify_config(BASELINE_REMOVE_CONFIG).unwrap();
+        set_conda_env(&mut doc, "r-env");
+        set_conda_env(&mut doc, "other-env");
+        insta::assert_snapshot!(doc.to_string());
+    }
 }
@@ -12,11 +12,13 @@ use url::Url;
 use crate::cache::utils::{
     get_current_system_path, get_packages_timeout, get_user_cache_dir, hash_string,
 };
-use crate::consts::{BUILD_LOG_FILENAME, BUILT_FROM_SOURCE_FILENAME};
+use crate::consts::{BUILD_LOG_FILENAME, BUILT_FROM_SOURCE_FILENAME, SOURCE_SHA256_FILENAME};
+use crate::http::HttpError;
 use crate::lockfile::Source;
 use crate::package::{BuiltinPackages, Package, get_builtin_versions_from_library};
+use crate::snapshot::is_dated_snapshot;
 use crate::system_req::get_system_requirements;
-use crate::{RCmd, SystemInfo, Version};
+use crate::{Http, RCmd, SystemInfo, Version};
 
 #[derive(Debug, Clone)]
 pub struct PackagePaths {
@@ -223,11 +225,15 @@ impl DiskCache {
     }
 
     /// Search the cache for the related package db file.
-    /// If it's not found or the entry is too old, the bool param will be false
+    /// If it's not found or the entry is too old, the bool param will be false.
+    /// The package db of a dated snapshot never changes so it never gets too old.
     pub fn get_package_db_entry(&self, repo_url: &str) -> (PathBuf, bool) {
         let path = self.get_package_db_path(repo_url);
 
         if path.exists() {
+            if is_dated_snapshot(repo_url) {
+                return (path, true);
+            }
             let metadata = path.metadata().expect("to work");
             let created = FileTime::from_last_modification_time(&metadata).unix_seconds() as u64;
             let now = SystemTime::now()
@@ -277,6 +283,24 @@ impl DiskCache {
         }
     }
 
+    /// The SHA-256 of the source tarball a repository package was extracted from, if we
+    /// downloaded it ourselves
+    pub fn get_source_sha256(
+        &self,
+        pkg_name: &str,
+        version: &str,
+        source: &Source,
+    ) -> Option<String> {
+        if !source.is_repo() {
+            return None;
+        }
+        let paths = self.get_package_paths(source, Some(pkg_name), Some(version));
+        fs::read_to_string(paths.source.join(SOURCE_SHA256_FILENAME))
+            .ok()
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+    }
+
     /// Finds where a package is present in the cache depending on its source.
     /// The version param is only used when the source is a repository
     pub fn get_installation_status(
@@ -330,19 +354,50 @@ impl DiskCache {
         }
     }
 
-    pub fn get_system_requirements(&self) -> HashMap<String, Vec<String>> {
+    /// The builtin packages saved by a previous run with an R version matching that one, if any.
+    /// Used when resolving for an R version that is not installed.
+    pub fn get_cached_builtin_packages(
+        &self,
+        r_version: &Version,
+    ) -> Option<HashMap<String, Package>> {
+        let mut found: Vec<_> = fs::read_dir(&self.root)
+            .ok()?
+            .filter_map(|entry| {
+                let path = entry.ok()?.path();
+                let version = path
+                    .file_name()?
+                    .to_str()?
+                    .strip_prefix("builtin-")?
+                    .strip_suffix(".mp")?
+                    .parse::<Version>()
+                    .ok()?;
+                r_version.hazy_match(&version).then_some((version, path))
+            })
+            .collect();
+        // Most recent patch version first
+        found.sort_by(|a, b| b.0.cmp(&a.0));
+        found
+            .into_iter()
+            .find_map(|(_, path)| BuiltinPackages::load(path))
+            .map(|builtin| builtin.packages)
+    }
+
+    pub fn get_system_requirements(
+        &self,
+        http: &Http,
+    ) -> Result<HashMap<String, Vec<String>>, HttpError> {
         let (distrib, version) = self.system_info.sysreq_data();
         let key = format!("sysreq-{distrib}-{version}.json",);
         let path = self.root.join(&key);
         // TODO: Handle expiration, what would be a reasonable time?
         if path.exists() {
             let content = fs::read_to_string(&path).expect("to work");
-            serde_json::from_str(&content).unwrap()
+            Ok(serde_json::from_str(&content).unwrap())
         } else {
-            let sysreq = get_system_requirements(&self.system_info);
+            let sysreq = get_system_requirements(&self.system_info, http)?;
             let content = serde_json::to_string(&sysreq).unwrap();
             fs::write(&path, content).expect("to work");
-            sysreq
+            Ok(sysreq)
         }
     }
 }
@@ -1,9 +1,9 @@
 use std::path::PathBuf;
 
-use etcetera::BaseStrategy;
 use sha2::{Digest, Sha256};
 
 use crate::SystemInfo;
+use crate::global_config::{default_cache_dir, global_config};
 
 /// Builds the path for binary in the cache and the library based on system info and R version
 /// {R_Version}/{arch}/{library_identifier}/
@@ -22,18 +22,19 @@ pub fn get_current_system_path(system_info: &SystemInfo, r_version: [u32; 2]) ->
     path
 }
 
-/// Look up the env to see if a specific timeout is set, otherwise use the default value
+/// Look up the env and then the global config to see if a specific timeout is set, otherwise use
+/// the default value
 pub fn get_packages_timeout() -> u64 {
-    if let Ok(v) = std::env::var(crate::consts::PACKAGE_TIMEOUT_ENV_VAR_NAME) {
-        if let Ok(v2) = v.parse() {
-            v2
-        } else {
-            // If the variable doesn't parse into a valid number, return the default one
-            crate::consts::PACKAGE_TIMEOUT
-        }
-    } else {
-        crate::consts::PACKAGE_TIMEOUT
+    // If the variable doesn't parse into a valid number, it is ignored
+    if let Ok(v) = std::env::var(crate::consts::PACKAGE_TIMEOUT_ENV_VAR_NAME)
+        && let Ok(v2) = v.parse()
+    {
+        return v2;
     }
+
+    global_config()
+        .packages_timeout()
+        .unwrap_or(crate::consts::PACKAGE_TIMEOUT)
 }
 
 /// Try to get where the rv cache dir should be
@@ -42,9 +43,11 @@ pub fn get_user_cache_dir() -> Option<PathBuf> {
         return Some(PathBuf::from(p));
     }
 
-    etcetera::base_strategy::choose_base_strategy()
-        .ok()
-        .map(|dirs| dirs.cache_dir().join("rv"))
+    if let Some(p) = global_config().cache_dir() {
+        return Some(p.to_path_buf());
+    }
+
+    default_cache_dir()
 }
 
 /// Equivalent to sha256(input)[:10]
@@ -0,0 +1,85 @@
+//! Hashes of the tarballs downloaded from repositories, to make sure they are the ones that
+//! were locked.
+
+use md5::Md5;
+use sha2::{Digest, Sha256};
+
+/// The lowercase hex MD5 of the content, the hash listed in the PACKAGES files of CRAN-like
+/// repositories
+pub(crate) fn md5_hex(content: &[u8]) -> String {
+    let mut hasher = Md5::new();
+    hasher.update(content);
+    format!("{:x}", hasher.finalize())
+}
+
+/// The lowercase hex SHA-256 of the content
+pub(crate) fn sha256_hex(content: &[u8]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(content);
+    format!("{:x}", hasher.finalize())
+}
+
+#[derive(Debug, PartialEq, thiserror::Error)]
+#[error("expected {algorithm} {expected} but got {actual}")]
+pub struct ChecksumMismatch {
+    pub algorithm: &'static str,
+    pub expected: String,
+    pub actual: String,
+}
+
+/// Checks the content against the hashes we know of, returning its SHA-256.
+pub(crate) fn verify_checksums(
+    content: &[u8],
+    md5: Option<&str>,
+    sha256: Option<&str>,
+) -> Result<String, ChecksumMismatch> {
+    let actual_sha256 = sha256_hex(content);
+    if let Some(expected) = sha256
+        && !expected.eq_ignore_ascii_case(&actual_sha256)
+    {
+        return Err(ChecksumMismatch {
+            algorithm: "sha256",
+            expected: expected.to_string(),
+            actual: actual_sha256,
+        });
+    }
+    if let Some(expected) = md5 {
+        let actual = md5_hex(content);
+        if !expected.eq_ignore_ascii_case(&actual) {
+            return Err(ChecksumMismatch {
+                algorithm: "md5",
+                expected: expected.to_string(),
+                actual,
+            });
+        }
+    }
+
+    Ok(actual_sha256)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn verifies_all_known_checksums() {
+        let content = b"abc";
+        let sha256 = "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad";
+        let md5 = "900150983cd24fb0d6963f7d28e17f72";
+
+        assert_eq!(verify_checksums(content, None, None).unwrap(), sha256);
+        assert_eq!(
+            verify_checksums(content, Some(md5), Some(sha256)).unwrap(),
+            sha256
+        );
+        assert_eq!(
+            verify_checksums(content, Some("d41d8cd98f00b204e9800998ecf8427e"), None),
+            Err(ChecksumMismatch {
+                algorithm: "md5",
+                expected: "d41d8cd98f00b204e9800998ecf8427e".to_string(),
+                actual: md5.to_string(),
+            })
+        );
+        assert!(verify_checksums(content, Some(md5), Some(&sha256.replace('b', "c"))).is_err());
+    }
+}
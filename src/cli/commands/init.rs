@@ -7,11 +7,13 @@ use std::{
 use fs_err::write;
 use url::Url;
 
-use crate::{Repository, consts::LIBRARY_ROOT_DIR_NAME};
+use crate::{
+    Repository,
+    consts::{CONFIG_FILENAME, LIBRARY_ROOT_DIR_NAME},
+};
 
 const GITIGNORE_PATH: &str = "rv/.gitignore";
 const LIBRARY_PATH: &str = "rv/library";
-const CONFIG_FILENAME: &str = "rproject.toml";
 
 const INITIAL_CONFIG: &str = r#"[project]
 name = "%project_name%"
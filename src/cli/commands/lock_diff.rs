@@ -0,0 +1,527 @@
+use crate::git::{CommandExecutor, GitExecutor};
+use crate::http::redact_url;
+use crate::lockfile::LockedPackage;
+use crate::{Lockfile, Source, Version};
+use serde::Serialize;
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::Write;
+use std::path::Path;
+use std::process::Command;
+use std::str::FromStr;
+
+/// A change to the top of the lockfile, eg the R version it was resolved for
+#[derive(Debug, PartialEq, Serialize)]
+pub struct SettingChange {
+    name: &'static str,
+    old: Option<String>,
+    new: Option<String>,
+}
+
+/// A package only present on one side of the diff
+#[derive(Debug, PartialEq, Serialize)]
+pub struct PackageEntry {
+    name: String,
+    version: String,
+    source: String,
+    /// The resolution targets of that entry, empty if it is used by all of them
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    targets: Vec<String>,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct VersionChange {
+    old: String,
+    new: String,
+    /// Whether the first component of the version changed
+    major: bool,
+    downgrade: bool,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct SourceChange {
+    old: String,
+    new: String,
+}
+
+/// A package present on both sides of the diff with something else than its hashes changing
+#[derive(Debug, PartialEq, Serialize)]
+pub struct PackageChange {
+    name: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    targets: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    version: Option<VersionChange>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    source: Option<SourceChange>,
+    /// The packages it now depends on, including suggests
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    added_dependencies: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    removed_dependencies: Vec<String>,
+}
+
+#[derive(Debug, Default, PartialEq, Serialize)]
+pub struct LockDiff {
+    settings: Vec<SettingChange>,
+    added: Vec<PackageEntry>,
+    removed: Vec<PackageEntry>,
+    changed: Vec<PackageChange>,
+}
+
+/// How a source is shown in the diff. Unlike its `Display`, the commit is always there since
+/// a branch moving is a change worth reviewing, and credentials are hidden.
+fn describe_source(source: &Source) -> String {
+    let short = |sha: &str| sha.chars().take(7).collect::<String>();
+    match source {
+        Source::Repository { repository } => redact_url(repository.as_str()),
+        Source::Git {
+            git,
+            sha,
+            directory,
+            tag,
+            branch,
+        } => {
+            let mut out = format!("git {}@{}", redact_url(git.url()), short(sha));
+            if let Some(d) = directory {
+                write!(out, " (directory: {d})").unwrap();
+            }
+            if let Some(t) = tag {
+                write!(out, " (tag: {t})").unwrap();
+            }
+            if let Some(b) = branch {
+                write!(out, " (branch: {b})").unwrap();
+            }
+            out
+        }
+        Source::RUniverse {
+            repository,
+            git,
+            sha,
+            ..
+        } => format!(
+            "{} (git {}@{})",
+            redact_url(repository.as_str()),
+            redact_url(git.url()),
+            short(sha)
+        ),
+        Source::Url { url, sha } => format!("{}@{}", redact_url(url.as_str()), short(sha)),
+        Source::Local { path, sha } => match sha {
+            Some(sha) => format!("path {}@{}", path.display(), short(sha)),
+            None => format!("path {}", path.display()),
+        },
+        Source::Builtin { .. } => "builtin".to_string(),
+    }
+}
+
+fn dependency_names(package: &LockedPackage) -> BTreeSet<&str> {
+    package
+        .dependencies
+        .iter()
+        .chain(&package.suggests)
+        .map(|d| d.name())
+        .collect()
+}
+
+fn version_change(old: &str, new: &str) -> Option<VersionChange> {
+    if old == new {
+        return None;
+    }
+    let (major, downgrade) = match (Version::from_str(old), Version::from_str(new)) {
+        (Ok(o), Ok(n)) => (o.major_minor()[0] != n.major_minor()[0], n < o),
+        _ => (false, false),
+    };
+    Some(VersionChange {
+        old: old.to_string(),
+        new: new.to_string(),
+        major,
+        downgrade,
+    })
+}
+
+fn package_entry(package: &LockedPackage) -> PackageEntry {
+    PackageEntry {
+        name: package.name.clone(),
+        version: package.version.clone(),
+        source: describe_source(&package.source),
+        targets: package.targets.clone(),
+    }
+}
+
+/// Compares two lockfiles, `None` standing for a project without lockfile.
+/// Packages are matched by name and targets so an entry of a lockfile resolved for several
+/// targets is only compared to the entry for the same targets.
+pub fn lock_diff(old: Option<&Lockfile>, new: Option<&Lockfile>) -> LockDiff {
+    let mut diff = LockDiff::default();
+
+    let mut setting = |name, old: Option<String>, new: Option<String>| {
+        if old != new {
+            diff.settings.push(SettingChange { name, old, new });
+        }
+    };
+    setting(
+        "r_version",
+        old.map(|l| l.r_version().original),
+        new.map(|l| l.r_version().original),
+    );
+    setting(
+        "targets",
+        old.map(|l| l.targets().join(", ")),
+        new.map(|l| l.targets().join(", ")),
+    );
+    setting(
+        "resolution",
+        old.map(|l| l.resolution_strategy().as_str().to_string()),
+        new.map(|l| l.resolution_strategy().as_str().to_string()),
+    );
+    setting(
+        "exclude_newer",
+        old.and_then(|l| l.exclude_newer()).map(|d| d.to_string()),
+        new.and_then(|l| l.exclude_newer()).map(|d| d.to_string()),
+    );
+
+    let packages = |lockfile: Option<&Lockfile>| -> Vec<LockedPackage> {
+        let mut out = lockfile.map(|l| l.packages().to_vec()).unwrap_or_default();
+        out.sort_by(|a, b| (&a.name, &a.targets).cmp(&(&b.name, &b.targets)));
+        out
+    };
+    let old_packages = packages(old);
+    let new_packages = packages(new);
+    let old_by_key: HashMap<_, _> = old_packages
+        .iter()
+        .map(|p| ((p.name.as_str(), &p.targets), p))
+        .collect();
+    let new_by_key: HashMap<_, _> = new_packages
+        .iter()
+        .map(|p| ((p.name.as_str(), &p.targets), p))
+        .collect();
+
+    for package in &old_packages {
+        if !new_by_key.contains_key(&(package.name.as_str(), &package.targets)) {
+            diff.removed.push(package_entry(package));
+        }
+    }
+
+    for package in &new_packages {
+        let Some(previous) = old_by_key.get(&(package.name.as_str(), &package.targets)) else {
+            diff.added.push(package_entry(package));
+            continue;
+        };
+
+        let source = (previous.source != package.source).then(|| SourceChange {
+            old: describe_source(&previous.source),
+            new: describe_source(&package.source),
+        });
+        let old_deps = dependency_names(previous);
+        let new_deps = dependency_names(package);
+        let change = PackageChange {
+            name: package.name.clone(),
+            targets: package.targets.clone(),
+            version: version_change(&previous.version, &package.version),
+            source,
+            added_dependencies: new_deps
+                .difference(&old_deps)
+                .map(|d| d.to_string())
+                .collect(),
+            removed_dependencies: old_deps
+                .difference(&new_deps)
+                .map(|d| d.to_string())
+                .collect(),
+        };
+        if change.version.is_some()
+            || change.source.is_some()
+            || !change.added_dependencies.is_empty()
+            || !change.removed_dependencies.is_empty()
+        {
+            diff.changed.push(change);
+        }
+    }
+
+    diff
+}
+
+fn with_targets(name: &str, targets: &[String]) -> String {
+    if targets.is_empty() {
+        name.to_string()
+    } else {
+        format!("{name} [{}]", targets.join(", "))
+    }
+}
+
+impl VersionChange {
+    fn label(&self) -> &'static str {
+        if self.downgrade {
+            "downgrade"
+        } else if self.major {
+            "major"
+        } else {
+            ""
+        }
+    }
+}
+
+impl PackageChange {
+    /// What changed besides the version, one item per line
+    fn details(&self) -> Vec<String> {
+        let mut out = Vec::new();
+        if let Some(source) = &self.source {
+            out.push(format!("source: {} → {}", source.old, source.new));
+        }
+        let deps: Vec<_> = self
+            .added_dependencies
+            .iter()
+            .map(|d| format!("+{d}"))
+            .chain(self.removed_dependencies.iter().map(|d| format!("-{d}")))
+            .collect();
+        if !deps.is_empty() {
+            out.push(format!("dependencies: {}", deps.join(", ")));
+        }
+        out
+    }
+}
+
+impl LockDiff {
+    pub fn is_empty(&self) -> bool {
+        self.settings.is_empty()
+            && self.added.is_empty()
+            && self.removed.is_empty()
+            && self.changed.is_empty()
+    }
+
+    pub fn print(&self) {
+        if self.is_empty() {
+            println!("No changes");
+            return;
+        }
+        let or_none = |v: &Option<String>| v.clone().unwrap_or_else(|| "none".to_string());
+        for s in &self.settings {
+            println!("{}: {} → {}", s.name, or_none(&s.old), or_none(&s.new));
+        }
+        for p in &self.added {
+            let name = with_targets(&p.name, &p.targets);
+            println!("+ {name} {} ({})", p.version, p.source);
+        }
+        for p in &self.removed {
+            let name = with_targets(&p.name, &p.targets);
+            println!("- {name} {} ({})", p.version, p.source);
+        }
+        for c in &self.changed {
+            let mut line = format!("~ {}", with_targets(&c.name, &c.targets));
+            if let Some(v) = &c.version {
+                write!(line, " {} → {}", v.old, v.new).unwrap();
+                if !v.label().is_empty() {
+                    write!(line, " ({})", v.label()).unwrap();
+                }
+            }
+            println!("{line}");
+            for detail in c.details() {
+                println!("    {detail}");
+            }
+        }
+    }
+
+    /// A markdown table, to be posted as a comment on a pull request
+    pub fn to_markdown(&self) -> String {
+        let mut out = String::from("### Lockfile changes\n\n");
+        if self.is_empty() {
+            out.push_str("No changes\n");
+            return out;
+        }
+        let code = |s: &str| format!("`{s}`");
+        let or_empty = |v: &Option<String>| v.as_deref().map(code).unwrap_or_default();
+        for s in &self.settings {
+            writeln!(
+                out,
+                "- {}: {} → {}",
+                s.name,
+                or_empty(&s.old),
+                or_empty(&s.new)
+            )
+            .unwrap();
+        }
+        if !self.settings.is_empty() {
+            out.push('\n');
+        }
+
+        out.push_str("| Package | Old | New | Details |\n|---|---|---|---|\n");
+        for p in &self.added {
+            let name = with_targets(&p.name, &p.targets);
+            writeln!(
+                out,
+                "| {name} | | {} | added, {} |",
+                p.version,
+                code(&p.source)
+            )
+            .unwrap();
+        }
+        for p in &self.removed {
+            let name = with_targets(&p.name, &p.targets);
+            writeln!(out, "| {name} | {} | | removed |", p.version).unwrap();
+        }
+        for c in &self.changed {
+            let name = with_targets(&c.name, &c.targets);
+            let (old, new) = match &c.version {
+                Some(v) => (v.old.as_str(), v.new.as_str()),
+                None => ("", ""),
+            };
+            let mut details = Vec::new();
+            if let Some(v) = &c.version
+                && !v.label().is_empty()
+            {
+                details.push(format!("**{}**", v.label()));
+            }
+            details.extend(c.details().into_iter().map(|d| d.replace('|', "\\|")));
+            writeln!(out, "| {name} | {old} | {new} | {} |", details.join("<br>")).unwrap();
+        }
+        out
+    }
+}
+
+/// Reads the lockfile at that path or, if there is no such file, the one of that git revision of
+/// the project, eg `HEAD` or `origin/main`. `None` if the revision doesn't have a lockfile.
+pub fn read_lockfile(
+    project_dir: &Path,
+    lockfile_name: &str,
+    path_or_revision: &str,
+) -> Result<Option<Lockfile>, String> {
+    let path = Path::new(path_or_revision);
+    if path.is_file() {
+        return match Lockfile::load(path) {
+            Ok(Some(l)) => Ok(Some(l)),
+            Ok(None) => Err(format!("{path_or_revision} is an outdated lockfile")),
+            Err(e) => Err(format!("{path_or_revision}: {e}")),
+        };
+    }
+
+    let git = |args: &[&str]| {
+        GitExecutor.execute(Command::new("git").args(args).current_dir(project_dir))
+    };
+    if git(&[
+        "rev-parse",
+        "--verify",
+        &format!("{path_or_revision}^{{commit}}"),
+    ])
+    .is_err()
+    {
+        return Err(format!(
+            "`{path_or_revision}` is neither a lockfile nor a git revision"
+        ));
+    }
+    // The lockfile might not have been committed yet
+    let Ok(content) = git(&["show", &format!("{path_or_revision}:./{lockfile_name}")]) else {
+        return Ok(None);
+    };
+    match Lockfile::load_content(&content) {
+        Ok(Some(l)) => Ok(Some(l)),
+        Ok(None) => Err(format!(
+            "{lockfile_name} at {path_or_revision} is an outdated lockfile"
+        )),
+        Err(e) => Err(format!("{lockfile_name} at {path_or_revision}: {e}")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lockfile(packages: &str) -> Lockfile {
+        Lockfile::from_str(&format!("version = 2\nr_version = \"4.4\"\n{packages}")).unwrap()
+    }
+
+    const OLD: &str = r#"
+[[packages]]
+name = "cli"
+version = "3.6.3"
+source = { repository = "https://cran.r-project.org/" }
+force_source = false
+dependencies = []
+
+[[packages]]
+name = "gsm"
+version = "1.9.2"
+source = { git = "https://github.com/Gilead-BioStats/gsm", sha = "2b5c7b5c4a", tag = "v1.9.2" }
+force_source = true
+dependencies = ["cli", "dplyr"]
+
+[[packages]]
+name = "rlang"
+version = "1.1.4"
+source = { repository = "https://cran.r-project.org/" }
+force_source = false
+dependencies = []
+
+[[packages]]
+name = "dplyr"
+version = "1.1.4"
+source = { repository = "https://cran.r-project.org/" }
+force_source = false
+dependencies = []
+"#;
+
+    const NEW: &str = r#"
+[[packages]]
+name = "cli"
+version = "3.6.3"
+source = { repository = "https://cran.r-project.org/" }
+sha256 = "8d92bd29c2ed7bf15f2778618ffe4a95556193d21d8431a7f75e7e5fc102bf48"
+force_source = false
+dependencies = []
+
+[[packages]]
+name = "gsm"
+version = "2.0.0"
+source = { git = "https://github.com/Gilead-BioStats/gsm", sha = "9f8e7d6c5b", tag = "v2.0.0" }
+force_source = true
+dependencies = ["cli", "rlang"]
+
+[[packages]]
+name = "rlang"
+version = "1.1.5"
+source = { git = "https://github.com/r-lib/rlang", sha = "0a1b2c3d4e", branch = "main" }
+force_source = true
+dependencies = []
+
+[[packages]]
+name = "R6"
+version = "2.5.1"
+source = { repository = "https://cran.r-project.org/" }
+force_source = false
+dependencies = []
+"#;
+
+    #[test]
+    fn reports_all_package_changes() {
+        let old = lockfile(OLD);
+        let new = lockfile(NEW);
+        let diff = lock_diff(Some(&old), Some(&new));
+
+        assert!(diff.settings.is_empty());
+        assert_eq!(
+            diff.added
+                .iter()
+                .map(|p| p.name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["R6"]
+        );
+        assert_eq!(
+            diff.removed
+                .iter()
+                .map(|p| p.name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["dplyr"]
+        );
+        // Only the hash of cli changed
+        assert_eq!(
+            diff.changed
+                .iter()
+                .map(|p| p.name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["gsm", "rlang"]
+        );
+        insta::assert_snapshot!("lock_diff_markdown", diff.to_markdown());
+
+        assert!(lock_diff(Some(&old), Some(&old)).is_empty());
+        let from_nothing = lock_diff(None, Some(&old));
+        assert_eq!(from_nothing.added.len(), 4);
+        assert_eq!(from_nothing.settings.len(), 3);
+    }
+}
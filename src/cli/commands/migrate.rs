@@ -7,7 +7,7 @@ use std::{
 use anyhow::{Result, anyhow};
 
 use crate::{
-    DiskCache, RenvLock, Repository, SystemInfo, Context,
+    DiskCache, Http, RenvLock, Repository, SystemInfo, Context,
     context::load_databases,
     renv::{ResolvedRenv, UnresolvedRenv},
 };
@@ -45,8 +45,8 @@ pub fn migrate_renv(
         Ok(c) => c,
         Err(e) => return Err(anyhow!(e)),
     };
-    let databases =
-        load_databases(&renv_lock.config_repositories(), &cache).map_err(|e| anyhow!("{e}"))?;
+    let databases = load_databases(&renv_lock.config_repositories(), &cache, &Http::default())
+        .map_err(|e| anyhow!("{e}"))?;
 
     // resolve the renv.lock file to determine the true source of packages
     let (resolved, unresolved) = renv_lock.resolve(&databases);
@@ -1,7 +1,11 @@
 mod init;
+mod lock_diff;
 mod migrate;
 mod tree;
+mod why;
 
 pub use init::{find_r_repositories, init, init_structure};
+pub use lock_diff::{LockDiff, lock_diff, read_lockfile};
 pub use migrate::migrate_renv;
 pub use tree::tree;
+pub use why::why;
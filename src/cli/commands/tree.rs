@@ -1,9 +1,9 @@
-use crate::Context;
 use crate::lockfile::Source;
 use crate::package::PackageType;
+use crate::{ConfigDependency, Context};
 use crate::{ResolvedDependency, UnresolvedDependency, Version};
 use serde::Serialize;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 #[derive(Debug, PartialEq, Copy, Clone)]
 enum NodeKind {
@@ -32,6 +32,7 @@ pub struct TreeNode<'a> {
     version_req: Option<String>,
     children: Vec<TreeNode<'a>>,
     ignored: bool,
+    overridden: bool,
 }
 
 impl TreeNode<'_> {
@@ -64,6 +65,9 @@ impl TreeNode<'_> {
             if !sys_deps.is_empty() {
                 elems.push(format!("system deps: {sys_deps}"));
             }
+            if self.overridden {
+                elems.push(String::from("overridden"));
+            }
             elems.join(", ")
         } else {
             let mut elems = Vec::new();
@@ -155,6 +159,7 @@ fn recursive_finder<'d>(
             sys_deps,
             children,
             ignored: resolved.ignored,
+            overridden: resolved.overridden,
         }
     } else {
         let unresolved = unresolved_deps_by_name[name];
@@ -172,6 +177,7 @@ fn recursive_finder<'d>(
             resolved: false,
             children: vec![],
             ignored: false,
+            overridden: false,
         }
     }
 }
@@ -203,8 +209,10 @@ impl Tree<'_> {
     }
 }
 
+/// Builds the tree of the given top level dependencies, usually the project dependencies
 pub fn tree<'a>(
     context: &'a Context,
+    top_level_deps: &[&'a ConfigDependency],
     resolved_deps: &'a [ResolvedDependency],
     unresolved_deps: &'a [UnresolvedDependency],
 ) -> Tree<'a> {
@@ -216,7 +224,12 @@ pub fn tree<'a>(
 
     let mut out = Vec::new();
 
-    for top_level_dep in context.config.dependencies() {
+    let mut seen = HashSet::new();
+    for top_level_dep in top_level_deps {
+        // The same dependency can be listed by several workspace members
+        if !seen.insert(top_level_dep.name()) {
+            continue;
+        }
         if let Some(found) = deps_by_name.get(top_level_dep.name()) {
             out.push(recursive_finder(
                 found.name.as_ref(),
@@ -241,6 +254,7 @@ pub fn tree<'a>(
                 resolved: false,
                 children: vec![],
                 ignored: false,
+                overridden: false,
             })
         }
     }
@@ -0,0 +1,285 @@
+use crate::package::{Dependency, Package, parse_description_file_in_folder};
+use crate::{ConfigDependency, Context, ResolvedDependency, Version};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt;
+
+#[derive(Debug, PartialEq, Copy, Clone, Serialize)]
+pub enum DependencyKind {
+    Depends,
+    Imports,
+    LinkingTo,
+    Suggests,
+}
+
+impl fmt::Display for DependencyKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DependencyKind::Depends => write!(f, "Depends"),
+            DependencyKind::Imports => write!(f, "Imports"),
+            DependencyKind::LinkingTo => write!(f, "LinkingTo"),
+            DependencyKind::Suggests => write!(f, "Suggests"),
+        }
+    }
+}
+
+/// A package in a path, along with how the previous package of the path depends on it.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct WhyStep<'a> {
+    name: &'a str,
+    version: &'a Version,
+    /// `None` for the top level dependency, or if we can't find the DESCRIPTION file of the
+    /// previous package to know how it depends on this one
+    kind: Option<DependencyKind>,
+    /// The version requirement of the previous package on this one
+    version_req: Option<String>,
+}
+
+impl fmt::Display for WhyStep<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)?;
+        match (&self.kind, &self.version_req) {
+            (Some(kind), Some(req)) => write!(f, " [{kind} {req}]"),
+            (Some(kind), None) => write!(f, " [{kind}]"),
+            (None, Some(req)) => write!(f, " [{req}]"),
+            (None, None) => Ok(()),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct Why<'a> {
+    name: &'a str,
+    version: &'a Version,
+    /// Whether the package is listed in the rproject.toml itself
+    top_level: bool,
+    /// A shortest path from each top level dependency requiring the package to the package. A
+    /// path stops at the first top level dependency found going up, even if that one is itself
+    /// required by another package.
+    paths: Vec<Vec<WhyStep<'a>>>,
+}
+
+impl Why<'_> {
+    pub fn print(&self) {
+        if self.top_level {
+            println!(
+                "{} {} is a dependency of the project",
+                self.name, self.version
+            );
+        }
+        let paths: Vec<_> = self.paths.iter().filter(|p| p.len() > 1).collect();
+        if paths.is_empty() {
+            return;
+        }
+        println!(
+            "{} {} is required by {} path{}:",
+            self.name,
+            self.version,
+            paths.len(),
+            if paths.len() > 1 { "s" } else { "" }
+        );
+        for path in paths {
+            let steps: Vec<_> = path.iter().map(|s| s.to_string()).collect();
+            println!("  {}", steps.join(" → "));
+        }
+    }
+}
+
+/// How `package` depends on `dep_name`, according to its DESCRIPTION file
+fn dependency_kind(package: &Package, dep_name: &str) -> Option<DependencyKind> {
+    let has = |deps: &[Dependency]| deps.iter().any(|d| d.name() == dep_name);
+    if has(&package.depends) {
+        Some(DependencyKind::Depends)
+    } else if has(&package.imports) {
+        Some(DependencyKind::Imports)
+    } else if has(&package.linking_to) {
+        Some(DependencyKind::LinkingTo)
+    } else if has(&package.suggests) {
+        Some(DependencyKind::Suggests)
+    } else {
+        None
+    }
+}
+
+/// The lockfile doesn't keep the kind of each dependency so we read it from the package installed
+/// in the library or from the repositories, if their databases were loaded
+fn find_package(context: &Context, dep: &ResolvedDependency) -> Option<Package> {
+    let installed = context.library.packages.get(dep.name.as_ref()) == Some(dep.version.as_ref());
+    if installed
+        && let Ok(package) =
+            parse_description_file_in_folder(context.library_path().join(dep.name.as_ref()))
+    {
+        return Some(package);
+    }
+
+    context
+        .databases
+        .iter()
+        .flat_map(|(db, _)| std::iter::once(&db.source_packages).chain(db.binary_packages.values()))
+        .filter_map(|packages| packages.get(dep.name.as_ref()))
+        .flatten()
+        .find(|p| &p.version == dep.version.as_ref())
+        .cloned()
+}
+
+/// Walks the reverse edges from `target` up to the top level dependencies and returns one of the
+/// shortest paths to each of them, starting from the top level dependency and ending with
+/// `target`. Listing every path would explode on graphs with many shared dependencies.
+fn find_paths<'a>(
+    target: &'a str,
+    parents: &HashMap<&'a str, Vec<&'a str>>,
+    top_level: &HashSet<&'a str>,
+) -> Vec<Vec<&'a str>> {
+    // Breadth first so the first time we reach a package is through a shortest path.
+    // package -> the package we came from, towards `target`
+    let mut previous: HashMap<&str, Option<&str>> = HashMap::from([(target, None)]);
+    let mut queue = VecDeque::from([target]);
+    let mut out = Vec::new();
+    while let Some(name) = queue.pop_front() {
+        if top_level.contains(name) {
+            let mut path = vec![name];
+            let mut current = name;
+            while let Some(Some(next)) = previous.get(current) {
+                path.push(next);
+                current = next;
+            }
+            out.push(path);
+            continue;
+        }
+        for parent in parents.get(name).into_iter().flatten() {
+            // Suggests can introduce cycles
+            if !previous.contains_key(parent) {
+                previous.insert(parent, Some(name));
+                queue.push_back(parent);
+            }
+        }
+    }
+    out.sort();
+    out
+}
+
+/// Explains why a package is part of the resolution, returning `None` if it is not.
+pub fn why<'a>(
+    context: &'a Context,
+    top_level_deps: &[&'a ConfigDependency],
+    resolved_deps: &'a [ResolvedDependency],
+    name: &str,
+) -> Option<Why<'a>> {
+    let deps_by_name: HashMap<_, _> = resolved_deps.iter().map(|d| (d.name.as_ref(), d)).collect();
+    let target = deps_by_name.get(name)?;
+
+    // The edges as (parent, child) -> (kind, version requirement)
+    let mut parents: HashMap<&str, Vec<&str>> = HashMap::new();
+    let mut edges = HashMap::new();
+    for dep in resolved_deps {
+        let suggests = if dep.install_suggests {
+            dep.suggests.as_slice()
+        } else {
+            &[]
+        };
+        let children = dep
+            .dependencies
+            .iter()
+            .map(|d| (d, None))
+            .chain(suggests.iter().map(|d| (d, Some(DependencyKind::Suggests))))
+            .filter(|(d, _)| deps_by_name.contains_key(d.name()));
+
+        for (child, kind) in children {
+            let child_parents = parents.entry(child.name()).or_default();
+            if child_parents.contains(&dep.name.as_ref()) {
+                continue;
+            }
+            child_parents.push(dep.name.as_ref());
+            edges.insert(
+                (dep.name.as_ref(), child.name()),
+                (kind, child.version_requirement().map(|r| r.to_string())),
+            );
+        }
+    }
+
+    let top_level: HashSet<_> = top_level_deps.iter().map(|d| d.name()).collect();
+    let paths = find_paths(target.name.as_ref(), &parents, &top_level);
+
+    // Only look for the DESCRIPTION files of the packages we need
+    let mut packages = HashMap::new();
+    let mut path_steps = Vec::with_capacity(paths.len());
+    for path in paths {
+        let mut steps = Vec::with_capacity(path.len());
+        for (i, name) in path.iter().enumerate() {
+            let dep = deps_by_name[name];
+            let (kind, version_req) = if i == 0 {
+                (None, None)
+            } else {
+                let parent = deps_by_name[path[i - 1]];
+                let (kind, version_req) = edges[&(path[i - 1], *name)].clone();
+                let kind = kind.or_else(|| {
+                    packages
+                        .entry(parent.name.as_ref())
+                        .or_insert_with(|| find_package(context, parent))
+                        .as_ref()
+                        .and_then(|p| dependency_kind(p, name))
+                });
+                (kind, version_req)
+            };
+            steps.push(WhyStep {
+                name: dep.name.as_ref(),
+                version: dep.version.as_ref(),
+                kind,
+                version_req,
+            });
+        }
+        path_steps.push(steps);
+    }
+
+    Some(Why {
+        name: target.name.as_ref(),
+        version: target.version.as_ref(),
+        top_level: top_level.contains(target.name.as_ref()),
+        paths: path_steps,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_shortest_paths_up_to_top_level_deps() {
+        let parents = HashMap::from([
+            ("V8", vec!["reactable", "juicyjuice"]),
+            ("reactable", vec!["gsm.app"]),
+            ("juicyjuice", vec!["gt"]),
+            ("gt", vec!["gsm.app", "gsm"]),
+            ("gsm", vec!["gsm.app"]),
+        ]);
+        let top_level = HashSet::from(["gsm.app", "gsm"]);
+
+        assert_eq!(
+            find_paths("V8", &parents, &top_level),
+            vec![
+                vec!["gsm", "gt", "juicyjuice", "V8"],
+                vec!["gsm.app", "reactable", "V8"],
+            ]
+        );
+        // A top level dependency is its own explanation
+        assert_eq!(find_paths("gsm", &parents, &top_level), vec![vec!["gsm"]]);
+        assert!(find_paths("unknown", &parents, &top_level).is_empty());
+
+        // Diamonds stacked on top of each other have 2^n paths
+        let mut parents = HashMap::new();
+        for i in 0..64 {
+            let below = format!("p{i}");
+            let above = format!("p{}", i + 1);
+            parents.insert(below, vec![format!("{above}a"), format!("{above}b")]);
+            parents.insert(format!("{above}a"), vec![above.clone()]);
+            parents.insert(format!("{above}b"), vec![above]);
+        }
+        let parents: HashMap<&str, Vec<&str>> = parents
+            .iter()
+            .map(|(k, v)| (k.as_str(), v.iter().map(|s| s.as_str()).collect()))
+            .collect();
+        let paths = find_paths("p0", &parents, &HashSet::from(["p64"]));
+        assert_eq!(paths.len(), 1);
+        assert_eq!(paths[0].len(), 129);
+    }
+}
@@ -4,7 +4,10 @@ mod sync;
 pub mod utils;
 
 pub use crate::{Context, RCommandLookup, ResolveMode};
-pub use commands::{find_r_repositories, init, init_structure, migrate_renv, tree};
-pub use resolution::resolve_dependencies;
-pub use sync::SyncHelper;
+pub use commands::{
+    LockDiff, find_r_repositories, init, init_structure, lock_diff, migrate_renv, read_lockfile,
+    tree, why,
+};
+pub use resolution::{resolve_dependencies, resolve_other_targets};
+pub use sync::{GroupSelection, MemberSelection, SyncHelper, update_lockfile};
 pub use utils::OutputFormat;
@@ -1,28 +1,100 @@
+use crate::cli::OutputFormat;
 use crate::{Context, Resolution, ResolveMode};
+use serde_json::json;
 
 /// Resolve dependencies for the project. If there are any unmet dependencies, they will be printed
-/// to stderr and the cli will exit.
-pub fn resolve_dependencies(
-    context: &Context,
+/// to stderr and the cli will exit, along with an explanation of each version conflict.
+/// In JSON mode, the failure is printed to stdout instead.
+/// Exclusions removing a hard dependency and patches not satisfying a version requirement are
+/// printed as warnings.
+pub fn resolve_dependencies<'a>(
+    context: &'a Context,
     resolve_mode: ResolveMode,
     exit_on_failure: bool,
-) -> Resolution<'_> {
+    output_format: &OutputFormat,
+) -> Resolution<'a> {
     let resolution = context.resolve(resolve_mode);
 
+    for (excluded, required_by) in &resolution.excluded_requirements {
+        eprintln!(
+            "Warning: `{excluded}` is excluded but `{required_by}` requires it (Depends/Imports/LinkingTo), `{required_by}` might not work."
+        );
+    }
+
+    for failure in &resolution.patch_failures {
+        eprintln!(
+            "Warning: {failure}, `{}` might not work.",
+            failure.required_by
+        );
+    }
+
     if !resolution.is_success() && exit_on_failure {
-        eprintln!("Failed to resolve all dependencies");
-        let req_error_messages = resolution.req_error_messages();
+        exit_with_failures(&resolution, None, output_format);
+    }
 
-        for d in &resolution.failed {
-            eprintln!("    {d}");
-        }
+    resolution
+}
 
-        if !req_error_messages.is_empty() {
-            eprintln!("{}", req_error_messages.join("\n"));
+/// Resolve dependencies for the other resolution targets of the config, exiting like
+/// `resolve_dependencies` if any of them fails. The warnings are only shown for the current system.
+pub fn resolve_other_targets<'a>(
+    context: &'a Context,
+    resolve_mode: ResolveMode,
+    exit_on_failure: bool,
+    output_format: &OutputFormat,
+) -> Vec<(&'a str, Resolution<'a>)> {
+    let mut out = Vec::with_capacity(context.other_targets.len());
+    for target in &context.other_targets {
+        let resolution = context.resolve_target(target, resolve_mode);
+        if !resolution.is_success() && exit_on_failure {
+            exit_with_failures(&resolution, Some(&target.id), output_format);
         }
+        out.push((target.id.as_str(), resolution));
+    }
+    out
+}
+
+fn exit_with_failures(
+    resolution: &Resolution,
+    target: Option<&str>,
+    output_format: &OutputFormat,
+) -> ! {
+    let error = match target {
+        Some(id) => format!("Failed to resolve all dependencies for target {id}"),
+        None => "Failed to resolve all dependencies".to_string(),
+    };
 
+    if output_format.is_json() {
+        let unresolved: Vec<_> = resolution.failed.iter().map(|d| d.to_string()).collect();
+        let mut out = json!({
+            "error": error,
+            "unresolved": unresolved,
+            "conflicts": resolution.conflicts,
+        });
+        if let Some(id) = target {
+            out["target"] = json!(id);
+        }
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&out).expect("valid json")
+        );
         ::std::process::exit(1)
     }
 
-    resolution
+    eprintln!("{error}");
+    let req_error_messages = resolution.req_error_messages();
+
+    for d in &resolution.failed {
+        eprintln!("    {d}");
+    }
+
+    if !req_error_messages.is_empty() {
+        eprintln!("{}", req_error_messages.join("\n"));
+    }
+
+    for conflict in &resolution.conflicts {
+        eprintln!("\n{conflict}");
+    }
+
+    ::std::process::exit(1)
 }
@@ -1,12 +1,125 @@
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::path::PathBuf;
 
-use anyhow::Result;
+use anyhow::{Result, anyhow};
+use clap::Parser;
 use fs_err::{self as fs};
 use serde::Serialize;
 
-use crate::cli::{Context, OutputFormat, ResolveMode, resolve_dependencies};
-use crate::{Lockfile, Resolution, SyncChange, SyncHandler, system_req, timeit};
+use crate::cli::{Context, OutputFormat, ResolveMode, resolve_dependencies, resolve_other_targets};
+use crate::config::{DEV_GROUP, MAIN_GROUP};
+use crate::consts::STAGING_DIR_NAME;
+use crate::{
+    Config, ConfigDependency, Lockfile, Resolution, ResolvedDependency, SyncChange, SyncHandler,
+    WorkspaceMember, system_req, timeit,
+};
+
+/// Which dependency groups to install. By default, the main dependencies and the `dev` group
+/// are installed.
+#[derive(Debug, Clone, PartialEq, Default, Parser)]
+pub struct GroupSelection {
+    /// Also install the dependencies from that group. Can be repeated
+    #[clap(long = "group", conflicts_with = "only_group")]
+    pub groups: Vec<String>,
+    /// Do not install the dependencies from the `dev` group
+    #[clap(long, conflicts_with = "only_group")]
+    pub no_dev: bool,
+    /// Only install the dependencies from that group, not the main ones. Can be repeated
+    #[clap(long)]
+    pub only_group: Vec<String>,
+}
+
+impl GroupSelection {
+    /// Errors if a group is not defined in the config or in any of the workspace members
+    pub fn validate(&self, context: &Context) -> Result<()> {
+        let known: Vec<_> = context
+            .dependency_groups()
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect();
+        for group in self.groups.iter().chain(&self.only_group) {
+            if !known.contains(&group.as_str()) {
+                return Err(anyhow!(
+                    "Unknown dependency group `{group}`. Available groups: {}",
+                    known.join(", ")
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    fn selected(&self) -> Vec<&str> {
+        if !self.only_group.is_empty() {
+            return self.only_group.iter().map(|g| g.as_str()).collect();
+        }
+
+        let mut out = vec![MAIN_GROUP];
+        if !self.no_dev {
+            out.push(DEV_GROUP);
+        }
+        out.extend(self.groups.iter().map(|g| g.as_str()));
+        out
+    }
+
+    /// The dependencies of the selected groups in that config
+    pub fn selected_dependencies<'c>(&self, config: &'c Config) -> Vec<&'c ConfigDependency> {
+        let selected = self.selected();
+        config
+            .dependency_groups()
+            .into_iter()
+            .filter(|(name, _)| selected.contains(name))
+            .flat_map(|(_, deps)| deps)
+            .collect()
+    }
+}
+
+/// Which projects of a workspace to act on. By default, only the project of the config file.
+#[derive(Debug, Clone, PartialEq, Default, Parser)]
+pub struct MemberSelection {
+    /// Only act on that workspace member, selected by its directory or project name.
+    /// Can be repeated
+    #[clap(long = "member", conflicts_with = "all_members")]
+    pub members: Vec<String>,
+    /// Act on the workspace root and all of its members
+    #[clap(long)]
+    pub all_members: bool,
+}
+
+impl MemberSelection {
+    /// The selected projects, `None` being the workspace root itself
+    pub fn select<'c>(&self, context: &'c Context) -> Result<Vec<Option<&'c WorkspaceMember>>> {
+        if self.members.is_empty() && !self.all_members {
+            return Ok(vec![None]);
+        }
+        if context.workspace_members.is_empty() {
+            return Err(anyhow!(
+                "The project is not a workspace root: it has no `[workspace]` members"
+            ));
+        }
+        if self.all_members {
+            return Ok(std::iter::once(None)
+                .chain(context.workspace_members.iter().map(Some))
+                .collect());
+        }
+
+        let mut out = Vec::with_capacity(self.members.len());
+        for name in &self.members {
+            let Some(member) = context.workspace_members.iter().find(|m| m.matches(name)) else {
+                return Err(anyhow!(
+                    "Unknown workspace member `{name}`. Available members: {}",
+                    context
+                        .workspace_members
+                        .iter()
+                        .map(|m| m.path.as_str())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ));
+            };
+            out.push(Some(member));
+        }
+        Ok(out)
+    }
+}
 
 #[derive(Debug, Default, Serialize)]
 struct SyncChanges {
@@ -35,6 +148,10 @@ pub struct SyncHelper {
     pub output_format: Option<OutputFormat>,
     pub save_install_logs_in: Option<PathBuf>,
     pub exit_on_failure: bool,
+    pub groups: GroupSelection,
+    pub members: MemberSelection,
+    /// Errors instead of syncing if the lockfile would change, which is then never written
+    pub locked: bool,
 }
 
 impl Default for SyncHelper {
@@ -44,6 +161,9 @@ impl Default for SyncHelper {
             output_format: None,
             save_install_logs_in: None,
             exit_on_failure: true,
+            groups: GroupSelection::default(),
+            members: MemberSelection::default(),
+            locked: false,
         }
     }
 }
@@ -57,100 +177,357 @@ impl SyncHelper {
         let sync_start = std::time::Instant::now();
         // TODO: exit on failure without println? and move that to main.rs
         // otherwise callers will think everything is fine
-        let resolution = resolve_dependencies(context, resolve_mode, self.exit_on_failure);
+        let output_format = self.output_format.as_ref().unwrap_or(&OutputFormat::Plain);
+        let resolution =
+            resolve_dependencies(context, resolve_mode, self.exit_on_failure, output_format);
+        // The other resolution targets are only needed to write or check the lockfile
+        let write_lockfile = !self.dry_run
+            && !self.locked
+            && resolve_mode != ResolveMode::Frozen
+            && context.config.use_lockfile();
+        let other_resolutions = if write_lockfile || self.locked {
+            resolve_other_targets(context, resolve_mode, self.exit_on_failure, output_format)
+        } else {
+            Vec::new()
+        };
+        if let Some((id, _)) = other_resolutions.iter().find(|(_, r)| !r.is_success()) {
+            return Err(anyhow!(
+                "Failed to resolve all dependencies for target {id}"
+            ));
+        }
+        if self.locked {
+            check_lockfile(context, &resolution, &other_resolutions)?;
+        }
+        let targets = self.members.select(context)?;
+
+        let mut changes_by_target = Vec::with_capacity(targets.len());
+        for member in targets {
+            let (config, member_library) = match member {
+                Some(m) => {
+                    let mut library =
+                        m.library(&context.cache.system_info, context.r_version.major_minor());
+                    library.find_content();
+                    (&m.config, Some(library))
+                }
+                None => (&context.config, None),
+            };
+            let library = member_library.as_ref().unwrap_or(&context.library);
 
-        match timeit!(
-            if self.dry_run {
-                "Planned dependencies"
+            // The lockfile has all the groups and workspace members but we only install what
+            // the selected groups of that project require
+            let to_install: Vec<_> = if resolve_mode == ResolveMode::Frozen {
+                // The config is not looked at so we rely on the groups written in the lockfile
+                let selected = self.groups.selected();
+                resolution
+                    .found
+                    .iter()
+                    .filter(|d| d.groups.iter().any(|g| selected.contains(&g.as_str())))
+                    .cloned()
+                    .collect()
             } else {
-                "Synced dependencies"
-            },
-            {
-                let mut handler = SyncHandler::new(context, self.save_install_logs_in.clone());
+                let closure = resolution.closure(self.groups.selected_dependencies(config));
+                resolution
+                    .found
+                    .iter()
+                    .enumerate()
+                    .filter(|(i, _)| closure.contains(i))
+                    .map(|(_, d)| d.clone())
+                    .collect()
+            };
+
+            match timeit!(
                 if self.dry_run {
-                    handler.dry_run();
-                }
-                if context.show_progress_bar {
-                    handler.show_progress_bar();
+                    "Planned dependencies"
+                } else {
+                    "Synced dependencies"
+                },
+                {
+                    let mut handler = SyncHandler::new(context, self.save_install_logs_in.clone());
+                    if self.dry_run {
+                        handler.dry_run();
+                    }
+                    if context.show_progress_bar {
+                        handler.show_progress_bar();
+                    }
+                    handler.set_library(library);
+                    handler.set_uses_lockfile(context.config.use_lockfile());
+                    handler.handle(&to_install, &context.r_cmd)
                 }
-                handler.set_uses_lockfile(context.config.use_lockfile());
-                handler.handle(&resolution.found, &context.r_cmd)
-            }
-        ) {
-            Ok(mut changes) => {
-                if !self.dry_run && context.config.use_lockfile() {
-                    if resolution.found.is_empty() {
-                        // delete the lockfiles if there are no dependencies
-                        let lockfile_path = context.lockfile_path();
-                        if lockfile_path.exists() {
-                            fs::remove_file(lockfile_path)?;
-                        }
-                    } else {
-                        let lockfile = Lockfile::from_resolved(
-                            &context.r_version.major_minor(),
-                            resolution.found.clone(),
-                        );
-                        if let Some(existing_lockfile) = &context.lockfile {
-                            if existing_lockfile != &lockfile {
-                                lockfile.save(context.lockfile_path())?;
-                                log::debug!("Lockfile changed, saving it.");
-                            }
-                        } else {
-                            lockfile.save(context.lockfile_path())?;
-                        }
+            ) {
+                Ok(changes) => changes_by_target.push((member, changes)),
+                Err(e) => {
+                    let staging_path = library.path().join(STAGING_DIR_NAME);
+                    if staging_path.is_dir() {
+                        fs::remove_dir_all(staging_path)?;
                     }
+                    return Err(e.into());
                 }
-                let all_sys_deps: HashSet<_> = changes
-                    .iter()
-                    .flat_map(|x| x.sys_deps.iter().map(|x| x.name.as_str()))
-                    .collect();
-                let sysdeps_status = system_req::check_installation_status(
-                    &context.cache.system_info,
-                    &all_sys_deps,
-                );
-
-                for change in changes.iter_mut() {
-                    change.update_sys_deps_status(&sysdeps_status);
+            }
+        }
+
+        if write_lockfile {
+            update_lockfile(context, &resolution, &other_resolutions)?;
+        }
+        let all_sys_deps: HashSet<_> = changes_by_target
+            .iter()
+            .flat_map(|(_, changes)| changes)
+            .flat_map(|x| x.sys_deps.iter().map(|x| x.name.as_str()))
+            .collect();
+        let sysdeps_status =
+            system_req::check_installation_status(&context.cache.system_info, &all_sys_deps);
+
+        for change in changes_by_target
+            .iter_mut()
+            .flat_map(|(_, changes)| changes.iter_mut())
+        {
+            change.update_sys_deps_status(&sysdeps_status);
+        }
+
+        if let Some(log_folder) = &self.save_install_logs_in {
+            fs::create_dir_all(log_folder)?;
+            for change in changes_by_target
+                .iter()
+                .flat_map(|(_, changes)| changes)
+                .filter(|x| x.installed)
+            {
+                let log_path = change.log_path(&context.cache);
+                if log_path.exists() {
+                    fs::copy(log_path, log_folder.join(format!("{}.log", change.name)))?;
                 }
+            }
+        }
 
-                if let Some(log_folder) = &self.save_install_logs_in {
-                    fs::create_dir_all(log_folder)?;
-                    for change in changes.iter().filter(|x| x.installed) {
-                        let log_path = change.log_path(&context.cache);
-                        if log_path.exists() {
-                            fs::copy(log_path, log_folder.join(format!("{}.log", change.name)))?;
+        if let Some(format) = &self.output_format {
+            // Only the workspace root: we keep the output of a regular project
+            let only_root = changes_by_target.len() == 1 && changes_by_target[0].0.is_none();
+            if format.is_json() {
+                let out = if only_root {
+                    let (_, changes) = changes_by_target.pop().unwrap();
+                    serde_json::to_string_pretty(&SyncChanges::from_changes(changes))
+                } else {
+                    let by_member: BTreeMap<_, _> = changes_by_target
+                        .into_iter()
+                        .map(|(member, changes)| {
+                            (target_label(member), SyncChanges::from_changes(changes))
+                        })
+                        .collect();
+                    serde_json::to_string_pretty(&by_member)
+                };
+                println!("{}", out.expect("valid json"));
+            } else {
+                for (i, (member, changes)) in changes_by_target.into_iter().enumerate() {
+                    if !only_root {
+                        if i > 0 {
+                            println!();
                         }
+                        println!("{}:", target_label(member));
                     }
-                }
-
-                if let Some(format) = &self.output_format {
-                    if format.is_json() {
-                        println!(
-                            "{}",
-                            serde_json::to_string_pretty(&SyncChanges::from_changes(changes))
-                                .expect("valid json")
-                        );
-                    } else if changes.is_empty() {
+                    if changes.is_empty() {
                         println!("Nothing to do");
                     } else {
                         for c in changes {
                             println!("{}", c.print(!self.dry_run, !sysdeps_status.is_empty()));
                         }
                     }
-
-                    if !self.dry_run && !format.is_json() {
-                        println!("sync completed in {} ms", sync_start.elapsed().as_millis());
-                    }
                 }
-
-                Ok(resolution)
             }
-            Err(e) => {
-                if context.staging_path().is_dir() {
-                    fs::remove_dir_all(context.staging_path())?;
-                }
-                Err(e.into())
+
+            if !self.dry_run && !format.is_json() {
+                println!("sync completed in {} ms", sync_start.elapsed().as_millis());
             }
         }
+
+        Ok(resolution)
+    }
+}
+
+/// The resolved packages with the SHA-256 of the source tarballs downloaded after the resolution,
+/// during the sync
+fn with_source_hashes<'d>(
+    context: &Context,
+    resolution: &Resolution<'d>,
+) -> Vec<ResolvedDependency<'d>> {
+    let mut found = resolution.found.clone();
+    for dep in found.iter_mut().filter(|d| d.sha256.is_none()) {
+        dep.sha256 = context
+            .cache
+            .get_source_sha256(&dep.name, &dep.version.original, &dep.source);
+    }
+    found
+}
+
+/// The lockfile of the resolution, merged with the ones of the other resolution targets.
+/// `None` if there are no dependencies, in which case there should be no lockfile.
+fn build_lockfile(
+    context: &Context,
+    resolution: &Resolution,
+    other_resolutions: &[(&str, Resolution)],
+) -> Option<Lockfile> {
+    let no_dependencies =
+        resolution.found.is_empty() && other_resolutions.iter().all(|(_, r)| r.found.is_empty());
+    if no_dependencies {
+        return None;
+    }
+
+    let lockfile = if let Some(current) = &context.current_target {
+        let mut found_by_target: HashMap<_, _> = other_resolutions
+            .iter()
+            .map(|(id, r)| (*id, with_source_hashes(context, r)))
+            .collect();
+        found_by_target.insert(current.as_str(), with_source_hashes(context, resolution));
+        let resolved = context
+            .config
+            .targets()
+            .iter()
+            .map(|t| {
+                let id = t.id();
+                let found = found_by_target.remove(id.as_str()).unwrap_or_default();
+                (id, found)
+            })
+            .collect();
+        Lockfile::from_resolved_targets(&context.config.r_version().major_minor(), resolved)
+    } else {
+        Lockfile::from_resolved(
+            &context.r_version.major_minor(),
+            with_source_hashes(context, resolution),
+        )
+    }
+    .with_resolution_strategy(context.resolution_strategy)
+    .with_exclude_newer(context.exclude_newer.clone())
+    .with_url_templates(context.url_templates());
+    Some(lockfile)
+}
+
+/// Errors with the difference between the lockfile and what would be written if they don't
+/// match, for `--locked`
+fn check_lockfile(
+    context: &Context,
+    resolution: &Resolution,
+    other_resolutions: &[(&str, Resolution)],
+) -> Result<()> {
+    if !context.config.use_lockfile() {
+        return Err(anyhow!(
+            "`--locked` cannot be used with `use_lockfile = false` in the config file"
+        ));
+    }
+    let lockfile_path = context.lockfile_path();
+    let current = if lockfile_path.exists() {
+        Lockfile::load(&lockfile_path)?
+    } else {
+        None
+    };
+    // The hashes added from the local cache are not a change of the lockfile
+    let expected = build_lockfile(context, resolution, other_resolutions)
+        .map(|mut l| {
+            l.forget_hashes_missing_from(current.as_ref());
+            l.as_toml_string()
+        })
+        .unwrap_or_default();
+    let current = current.map(|l| l.as_toml_string()).unwrap_or_default();
+    if current == expected {
+        return Ok(());
+    }
+
+    let lockfile_name = context.config.lockfile_name();
+    let diff = similar::TextDiff::from_lines(&current, &expected)
+        .unified_diff()
+        .header(lockfile_name, &format!("{lockfile_name} (resolved)"))
+        .to_string();
+    Err(anyhow!(
+        "The lockfile {lockfile_name} needs to be updated but `--locked` was used:\n{diff}"
+    ))
+}
+
+/// Writes the lockfile of the resolution, merged with the ones of the other resolution targets,
+/// or deletes it if there are no dependencies. Returns whether the file changed.
+pub fn update_lockfile(
+    context: &Context,
+    resolution: &Resolution,
+    other_resolutions: &[(&str, Resolution)],
+) -> Result<bool> {
+    let lockfile_path = context.lockfile_path();
+    let Some(lockfile) = build_lockfile(context, resolution, other_resolutions) else {
+        // delete the lockfiles if there are no dependencies
+        if lockfile_path.exists() {
+            fs::remove_file(lockfile_path)?;
+            return Ok(true);
+        }
+        return Ok(false);
+    };
+    let existing_lockfile = context
+        .universal_lockfile
+        .as_ref()
+        .or(context.lockfile.as_ref());
+    // Compare what would be written since the env vars are expanded in memory
+    if existing_lockfile.is_some_and(|l| l.as_toml_string() == lockfile.as_toml_string()) {
+        return Ok(false);
+    }
+    lockfile.save(lockfile_path)?;
+    log::debug!("Lockfile changed, saving it.");
+    Ok(true)
+}
+
+/// How a workspace member is named in the output, `.` being the workspace root
+fn target_label(member: Option<&WorkspaceMember>) -> &str {
+    member.map(|m| m.path.as_str()).unwrap_or(".")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::RCommandLookup;
+    use std::path::Path;
+
+    /// Writes a project depending on local packages, all at version 1.0.0
+    fn write_project(dir: &Path, packages: &[&str]) -> PathBuf {
+        let mut dependencies = Vec::new();
+        for name in packages {
+            fs::create_dir_all(dir.join(name)).unwrap();
+            fs::write(
+                dir.join(name).join("DESCRIPTION"),
+                format!("Package: {name}\nVersion: 1.0.0\n"),
+            )
+            .unwrap();
+            dependencies.push(format!(r#"{{ name = "{name}", path = "{name}" }}"#));
+        }
+        let config_path = dir.join("rproject.toml");
+        fs::write(
+            &config_path,
+            format!(
+                "[project]\nname = \"test\"\nr_version = \"4.4\"\nrepositories = []\ndependencies = [{}]\n",
+                dependencies.join(", ")
+            ),
+        )
+        .unwrap();
+        config_path
+    }
+
+    #[test]
+    fn check_lockfile_errors_with_diff_on_new_dependency() {
+        let project = tempfile::tempdir().unwrap();
+        let cache = tempfile::tempdir().unwrap();
+        let config_path = write_project(project.path(), &["a"]);
+        let context =
+            Context::new_with_cache_dir(&config_path, RCommandLookup::Skip, Some(cache.path()))
+                .unwrap();
+        let resolution = context.resolve(ResolveMode::Default);
+        assert!(update_lockfile(&context, &resolution, &[]).unwrap());
+        assert!(check_lockfile(&context, &resolution, &[]).is_ok());
+
+        // A hash only known from the local cache doesn't make the lockfile outdated
+        let mut cached = resolution.clone();
+        cached.found[0].sha256 = Some("abc".to_string());
+        assert!(check_lockfile(&context, &cached, &[]).is_ok());
+
+        let config_path = write_project(project.path(), &["a", "b"]);
+        let context =
+            Context::new_with_cache_dir(&config_path, RCommandLookup::Skip, Some(cache.path()))
+                .unwrap();
+        let resolution = context.resolve(ResolveMode::Default);
+        let err = check_lockfile(&context, &resolution, &[])
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("needs to be updated but `--locked` was used"));
+        assert!(err.contains("+name = \"b\""));
     }
 }
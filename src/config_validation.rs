@@ -0,0 +1,1435 @@
+//! Validation of a rproject.toml reporting every problem with its location in the file, used by
+//! `rv config validate`, and the JSON Schema of that file for editors.
+//! Both are generated from the description of the fields below, which needs to be kept in sync
+//! with the types in `config.rs`. The tests check that the fields match the ones serde accepts.
+
+use std::collections::HashSet;
+use std::ops::Range;
+use std::path::Path;
+use std::str::FromStr;
+
+use serde::Serialize;
+use serde_json::json;
+use toml_edit::{Document, Item, TableLike, Value};
+use url::Url;
+
+use crate::config::{DEV_GROUP, MAIN_GROUP, SUGGESTS_GROUP};
+use crate::git::url::GitUrl;
+use crate::snapshot::is_valid_snapshot_date;
+use crate::system_info::LINUX_DISTRIBUTIONS;
+use crate::template::{expand_env_vars, is_template};
+use crate::{Config, Version, VersionRequirement};
+
+#[derive(Debug, Clone, Copy)]
+enum Kind {
+    String,
+    Bool,
+    /// A http(s) URL, which can use env vars
+    Url,
+    /// A http(s) or ssh URL, which can use env vars
+    GitUrl,
+    Version,
+    VersionRequirement,
+    /// A `YYYY-MM-DD` date
+    Date,
+    OneOf(&'static [&'static str]),
+    StringArray,
+    /// A table of strings
+    StringMap,
+    /// A table of strings, which can use env vars
+    TemplateMap,
+    Table(&'static [Field]),
+    TableArray(&'static [Field]),
+    Dependencies,
+    /// A table of dependency lists
+    Groups,
+    /// A table of tables of strings, which can use env vars
+    EnvVars,
+    /// A table of lists of configure args rules
+    ConfigureArgs,
+    /// One of the forms of `REPOSITORY_AUTH_FORMS`
+    RepositoryAuth,
+    /// A table of version requirements or dependencies without names
+    Overrides,
+    /// A table of git, local or url dependencies without names
+    Patches,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Field {
+    name: &'static str,
+    kind: Kind,
+    required: bool,
+    doc: &'static str,
+}
+
+const fn field(name: &'static str, kind: Kind, doc: &'static str) -> Field {
+    Field {
+        name,
+        kind,
+        required: false,
+        doc,
+    }
+}
+
+const fn required(name: &'static str, kind: Kind, doc: &'static str) -> Field {
+    Field {
+        name,
+        kind,
+        required: true,
+        doc,
+    }
+}
+
+const CONFIG_FIELDS: &[Field] = &[
+    field(
+        "library",
+        Kind::String,
+        "Installs the packages directly in that folder instead of the project library",
+    ),
+    field(
+        "use_lockfile",
+        Kind::Bool,
+        "Whether to use the lockfile for resolution. Defaults to true",
+    ),
+    field(
+        "lockfile_name",
+        Kind::String,
+        "The lockfile filename. Defaults to rv.lock",
+    ),
+    required("project", Kind::Table(PROJECT_FIELDS), "The project"),
+    field(
+        "workspace",
+        Kind::Table(WORKSPACE_FIELDS),
+        "Makes the project the root of a workspace",
+    ),
+    field(
+        "overrides",
+        Kind::Overrides,
+        "Forces the version or source of packages wherever they are required",
+    ),
+    field(
+        "patch",
+        Kind::Patches,
+        "Redirects packages to a git, local or url source wherever they are required",
+    ),
+];
+
+const PROJECT_FIELDS: &[Field] = &[
+    required("name", Kind::String, "The project name"),
+    required("r_version", Kind::Version, "The R version required, eg 4.4"),
+    field("description", Kind::String, "The project description"),
+    field("license", Kind::String, "The project license"),
+    field(
+        "authors",
+        Kind::TableArray(AUTHOR_FIELDS),
+        "The project authors",
+    ),
+    field("keywords", Kind::StringArray, "The project keywords"),
+    field(
+        "repositories",
+        Kind::TableArray(REPOSITORY_FIELDS),
+        "The repositories to fetch packages from, in order of priority",
+    ),
+    field(
+        "snapshot",
+        Kind::Date,
+        "The default snapshot date of the Posit Package Manager repositories, eg 2024-06-01",
+    ),
+    field(
+        "exclude_newer",
+        Kind::Date,
+        "Ignore the package versions published after that day, eg 2025-03-01",
+    ),
+    field(
+        "dependencies",
+        Kind::Dependencies,
+        "The dependencies of the project",
+    ),
+    field(
+        "dev_dependencies",
+        Kind::Dependencies,
+        "The `dev` group, installed unless `rv sync --no-dev` is used",
+    ),
+    field("suggests", Kind::Dependencies, "The `suggests` group"),
+    field(
+        "groups",
+        Kind::Groups,
+        "Additional dependency groups, only installed with `rv sync --group <name>`",
+    ),
+    field("urls", Kind::StringMap, "The project URLs"),
+    field(
+        "prefer_repositories_for",
+        Kind::StringArray,
+        "Packages to take from the repositories rather than from the remotes of a DESCRIPTION file",
+    ),
+    field(
+        "exclude",
+        Kind::StringArray,
+        "Packages never installed even if a dependency requires them",
+    ),
+    field(
+        "packages_env_vars",
+        Kind::EnvVars,
+        "Environment variables set when compiling each package",
+    ),
+    field(
+        "configure_args",
+        Kind::ConfigureArgs,
+        "The configure.args of each package, by OS and architecture",
+    ),
+    field(
+        "conda_env",
+        Kind::String,
+        "The conda environment to install packages in",
+    ),
+    field(
+        "pak_fallback",
+        Kind::Bool,
+        "Whether to try installing with pak when an installation fails",
+    ),
+    field(
+        "targets",
+        Kind::TableArray(TARGET_FIELDS),
+        "The platforms and R versions to resolve the lockfile for, the current system being one of them",
+    ),
+];
+
+const AUTHOR_FIELDS: &[Field] = &[
+    required("name", Kind::String, "The author name"),
+    required("email", Kind::String, "The author email"),
+    field(
+        "maintainer",
+        Kind::Bool,
+        "Whether the author is a maintainer",
+    ),
+];
+
+const REPOSITORY_FIELDS: &[Field] = &[
+    required(
+        "alias",
+        Kind::String,
+        "The name used to refer to the repository in dependencies",
+    ),
+    required("url", Kind::Url, "The repository URL"),
+    field(
+        "force_source",
+        Kind::Bool,
+        "Whether to always build packages from source",
+    ),
+    field(
+        "auth",
+        Kind::RepositoryAuth,
+        "How to authenticate to the repository",
+    ),
+    field(
+        "headers",
+        Kind::TemplateMap,
+        "Extra headers sent with every request to the repository",
+    ),
+    field(
+        "ca_bundle",
+        Kind::String,
+        "A PEM file with the certificate authorities to trust for the repository",
+    ),
+    field(
+        "proxy",
+        Kind::String,
+        "The proxy to go through for the repository",
+    ),
+    field(
+        "snapshot",
+        Kind::Date,
+        "The snapshot date of the repository if it is a Posit Package Manager one, eg 2024-06-01",
+    ),
+];
+
+const REPOSITORY_AUTH_FIELDS: &[Field] = &[
+    field(
+        "token_env",
+        Kind::String,
+        "The env var containing a bearer token",
+    ),
+    field("username", Kind::String, "The username for basic auth"),
+    field(
+        "password_env",
+        Kind::String,
+        "The env var containing the password for basic auth",
+    ),
+    field(
+        "netrc",
+        Kind::Bool,
+        "Whether to use the credentials of the repository host in the .netrc file",
+    ),
+];
+
+const WORKSPACE_FIELDS: &[Field] = &[required(
+    "members",
+    Kind::StringArray,
+    "The member directories, relative to this file",
+)];
+
+const INSTALL_SUGGESTIONS: Field = field(
+    "install_suggestions",
+    Kind::Bool,
+    "Whether to also install the suggested packages",
+);
+const DEPENDENCIES_ONLY: Field = field(
+    "dependencies_only",
+    Kind::Bool,
+    "Whether to only install the dependencies of the package",
+);
+const DEPENDENCY_NAME: Field = required("name", Kind::String, "The package name");
+const EXCLUDE: Field = field(
+    "exclude",
+    Kind::StringArray,
+    "Packages never installed even if the dependency requires them, for the whole project",
+);
+const OS_MARKER: Field = field(
+    "os",
+    Kind::OneOf(OS_TARGETS),
+    "Only use the dependency on that OS",
+);
+const ARCH_MARKER: Field = field(
+    "arch",
+    Kind::OneOf(ARCH_TARGETS),
+    "Only use the dependency on that architecture",
+);
+const R_VERSION_MARKER: Field = field(
+    "r_version",
+    Kind::VersionRequirement,
+    "Only use the dependency with R versions matching that requirement, eg `>= 4.3`",
+);
+
+const REPOSITORY_DEPENDENCY_FIELDS: &[Field] = &[
+    DEPENDENCY_NAME,
+    field(
+        "repository",
+        Kind::String,
+        "The alias of the repository to get the package from",
+    ),
+    field(
+        "version",
+        Kind::VersionRequirement,
+        "Comma separated version requirements, eg `>= 1.1.0, < 1.2`",
+    ),
+    INSTALL_SUGGESTIONS,
+    field(
+        "force_source",
+        Kind::Bool,
+        "Whether to build the package from source",
+    ),
+    DEPENDENCIES_ONLY,
+    EXCLUDE,
+    OS_MARKER,
+    ARCH_MARKER,
+    R_VERSION_MARKER,
+];
+
+const GIT_DEPENDENCY_FIELDS: &[Field] = &[
+    DEPENDENCY_NAME,
+    required(
+        "git",
+        Kind::GitUrl,
+        "The git repository URL, http(s) or ssh",
+    ),
+    field("commit", Kind::String, "The commit to use"),
+    field("tag", Kind::String, "The tag to use"),
+    field("branch", Kind::String, "The branch to use"),
+    field(
+        "directory",
+        Kind::String,
+        "The directory of the package in the repository",
+    ),
+    INSTALL_SUGGESTIONS,
+    DEPENDENCIES_ONLY,
+    EXCLUDE,
+    OS_MARKER,
+    ARCH_MARKER,
+    R_VERSION_MARKER,
+];
+
+const LOCAL_DEPENDENCY_FIELDS: &[Field] = &[
+    DEPENDENCY_NAME,
+    required(
+        "path",
+        Kind::String,
+        "The path to the package folder or tarball",
+    ),
+    INSTALL_SUGGESTIONS,
+    DEPENDENCIES_ONLY,
+    EXCLUDE,
+    OS_MARKER,
+    ARCH_MARKER,
+    R_VERSION_MARKER,
+];
+
+const URL_DEPENDENCY_FIELDS: &[Field] = &[
+    DEPENDENCY_NAME,
+    required("url", Kind::Url, "The URL of the package tarball"),
+    INSTALL_SUGGESTIONS,
+    DEPENDENCIES_ONLY,
+    EXCLUDE,
+    OS_MARKER,
+    ARCH_MARKER,
+    R_VERSION_MARKER,
+];
+
+/// The kinds of dependencies, found by the key giving their source
+const DEPENDENCY_KINDS: &[(&str, &str, &[Field])] = &[
+    ("git", "git", GIT_DEPENDENCY_FIELDS),
+    ("path", "local", LOCAL_DEPENDENCY_FIELDS),
+    ("url", "url", URL_DEPENDENCY_FIELDS),
+];
+
+/// The fields of each way to authenticate to a repository, only one of them can be used
+const REPOSITORY_AUTH_FORMS: &[&[&str]] =
+    &[&["token_env"], &["username", "password_env"], &["netrc"]];
+
+const OS_TARGETS: &[&str] = &["linux", "macos", "windows"];
+const ARCH_TARGETS: &[&str] = &["x86_64", "amd64", "arm64", "aarch64", "x86", "arm"];
+
+const TARGET_FIELDS: &[Field] = &[
+    required("os", Kind::OneOf(OS_TARGETS), "The OS of the target"),
+    required(
+        "arch",
+        Kind::OneOf(ARCH_TARGETS),
+        "The architecture of the target",
+    ),
+    field(
+        "distribution",
+        Kind::OneOf(LINUX_DISTRIBUTIONS),
+        "The Linux distribution of the target",
+    ),
+    field(
+        "version",
+        Kind::String,
+        "The version of the distribution, eg `9` for RHEL 9",
+    ),
+    field(
+        "codename",
+        Kind::String,
+        "The codename of the distribution, eg `jammy`, needed for Ubuntu and Debian binaries",
+    ),
+    required(
+        "r_version",
+        Kind::Version,
+        "The R version of the target, eg 4.4",
+    ),
+];
+
+const CONFIGURE_ARGS_RULE_FIELDS: &[Field] = &[
+    field(
+        "os",
+        Kind::OneOf(OS_TARGETS),
+        "Only use those args on that OS",
+    ),
+    field(
+        "arch",
+        Kind::OneOf(ARCH_TARGETS),
+        "Only use those args on that architecture",
+    ),
+    required(
+        "args",
+        Kind::StringArray,
+        "The arguments passed to configure",
+    ),
+];
+
+/// A problem found in a config file
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigDiagnostic {
+    pub message: String,
+    /// The bytes of the file the problem is about
+    pub span: Range<usize>,
+}
+
+#[derive(Debug, Serialize)]
+struct DiagnosticLocation<'a> {
+    message: &'a str,
+    line: usize,
+    column: usize,
+}
+
+impl ConfigDiagnostic {
+    fn new(message: impl Into<String>, span: Range<usize>) -> Self {
+        Self {
+            message: message.into(),
+            span,
+        }
+    }
+
+    /// The 1-indexed line and column of the start of the problem
+    pub fn location(&self, content: &str) -> (usize, usize) {
+        let start = self.span.start.min(content.len());
+        let before = &content[..start];
+        let line = before.matches('\n').count() + 1;
+        let line_start = before.rfind('\n').map(|i| i + 1).unwrap_or(0);
+        let column = content[line_start..start].chars().count() + 1;
+        (line, column)
+    }
+
+    /// The diagnostic with its location, for JSON output
+    pub fn to_json(&self, content: &str) -> serde_json::Value {
+        let (line, column) = self.location(content);
+        serde_json::to_value(DiagnosticLocation {
+            message: &self.message,
+            line,
+            column,
+        })
+        .expect("valid json")
+    }
+
+    /// Renders the diagnostic with its location and the offending line, eg
+    ///
+    /// ```text
+    /// error: Unknown field `tags` in a git dependency
+    ///  --> rproject.toml:8:52
+    ///   |
+    /// 8 |     { name = "mypkg", git = "https://github.com/a/b", tags = "v1" },
+    ///   |                                                       ^^^^
+    /// ```
+    pub fn render(&self, path: &Path, content: &str) -> String {
+        let (line, column) = self.location(content);
+        let line_content = content
+            .lines()
+            .nth(line - 1)
+            .unwrap_or_default()
+            .trim_end_matches('\r');
+        let remaining = line_content.chars().count().saturating_sub(column - 1);
+        let span_len = content
+            .get(self.span.clone())
+            .map(|s| s.chars().count())
+            .unwrap_or_default();
+        let carets = "^".repeat(span_len.min(remaining).max(1));
+        let gutter = " ".repeat(line.to_string().len());
+
+        format!(
+            "error: {}\n{gutter}--> {}:{line}:{column}\n{gutter} |\n{line} | {line_content}\n{gutter} | {}{carets}\n",
+            self.message,
+            path.display(),
+            " ".repeat(column - 1),
+        )
+    }
+}
+
+/// The dependency fields an override or a patch can't have: its name is the key and it only
+/// sets what to install
+const NOT_IN_KEYED_DEPENDENCIES: &[&str] = &["name", "dependencies_only", "exclude"];
+
+fn keyed_dependency_fields(fields: &[Field]) -> Vec<Field> {
+    fields
+        .iter()
+        .filter(|f| !NOT_IN_KEYED_DEPENDENCIES.contains(&f.name))
+        .copied()
+        .collect()
+}
+
+fn field_names(fields: &[Field]) -> String {
+    fields
+        .iter()
+        .map(|f| format!("`{}`", f.name))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// A table and its location in the file
+type SpannedTable<'a> = (&'a dyn TableLike, Option<Range<usize>>);
+
+/// The tables found in an array of tables, either written as `[[...]]` or as inline tables
+fn tables_of(item: &Item) -> Option<Vec<SpannedTable<'_>>> {
+    if let Some(array) = item.as_array_of_tables() {
+        return Some(
+            array
+                .iter()
+                .map(|t| (t as &dyn TableLike, t.span()))
+                .collect(),
+        );
+    }
+
+    let array = item.as_array()?;
+    let mut out = Vec::new();
+    for value in array.iter() {
+        out.push((value.as_inline_table()? as &dyn TableLike, value.span()));
+    }
+    Some(out)
+}
+
+#[derive(Debug, Default)]
+struct Validator {
+    diagnostics: Vec<ConfigDiagnostic>,
+    repository_aliases: HashSet<String>,
+}
+
+impl Validator {
+    fn error(&mut self, message: impl Into<String>, span: Range<usize>) {
+        self.diagnostics.push(ConfigDiagnostic::new(message, span));
+    }
+
+    fn table(
+        &mut self,
+        table: &dyn TableLike,
+        fields: &[Field],
+        path: &str,
+        span: Range<usize>,
+        unknown_field: &dyn Fn(&str) -> String,
+    ) {
+        for (key, item) in table.iter() {
+            let key_span = table
+                .key(key)
+                .and_then(|k| k.span())
+                .unwrap_or_else(|| span.clone());
+            let item_path = if path.is_empty() {
+                key.to_string()
+            } else {
+                format!("{path}.{key}")
+            };
+            match fields.iter().find(|f| f.name == key) {
+                Some(f) => {
+                    let item_span = item.span().unwrap_or_else(|| key_span.clone());
+                    self.item(item, f.kind, &item_path, item_span);
+                }
+                None => self.error(unknown_field(key), key_span),
+            }
+        }
+
+        for f in fields.iter().filter(|f| f.required) {
+            if !table.contains_key(f.name) {
+                let location = if path.is_empty() {
+                    "the config".to_string()
+                } else {
+                    format!("`{path}`")
+                };
+                self.error(
+                    format!("Missing field `{}` in {location}", f.name),
+                    span.clone(),
+                );
+            }
+        }
+    }
+
+    fn simple_table(
+        &mut self,
+        table: &dyn TableLike,
+        fields: &[Field],
+        path: &str,
+        span: Range<usize>,
+    ) {
+        let location = if path.is_empty() {
+            "the config".to_string()
+        } else {
+            format!("`{path}`")
+        };
+        self.table(table, fields, path, span, &|key| {
+            format!(
+                "Unknown field `{key}` in {location}, expected one of {}",
+                field_names(fields)
+            )
+        });
+    }
+
+    fn string<'a>(&mut self, item: &'a Item, path: &str, span: &Range<usize>) -> Option<&'a str> {
+        let s = item.as_str();
+        if s.is_none() {
+            self.error(format!("`{path}` should be a string"), span.clone());
+        }
+        s
+    }
+
+    /// Expands the env vars if there are any, reporting the ones that aren't set
+    fn expand(&mut self, s: &str, span: &Range<usize>) -> Option<String> {
+        if !is_template(s) {
+            return Some(s.to_string());
+        }
+        match expand_env_vars(s) {
+            Ok(expanded) => Some(expanded),
+            Err(e) => {
+                self.error(e.to_string(), span.clone());
+                None
+            }
+        }
+    }
+
+    fn item(&mut self, item: &Item, kind: Kind, path: &str, span: Range<usize>) {
+        match kind {
+            Kind::String => {
+                self.string(item, path, &span);
+            }
+            Kind::Bool => {
+                if item.as_bool().is_none() {
+                    self.error(format!("`{path}` should be true or false"), span);
+                }
+            }
+            Kind::Url => {
+                if let Some(s) = self.string(item, path, &span)
+                    && let Some(url) = self.expand(s, &span)
+                    && !((url.starts_with("http://") || url.starts_with("https://"))
+                        && Url::parse(&url).is_ok())
+                {
+                    self.error(format!("`{path}` should be a http(s) URL, got `{s}`"), span);
+                }
+            }
+            Kind::GitUrl => {
+                if let Some(s) = self.string(item, path, &span)
+                    && let Some(url) = self.expand(s, &span)
+                    && let Err(e) = GitUrl::try_from(url.as_str())
+                {
+                    self.error(format!("`{path}`: {e}"), span);
+                }
+            }
+            Kind::Version => {
+                if let Some(s) = self.string(item, path, &span)
+                    && Version::from_str(s).is_err()
+                {
+                    self.error(
+                        format!("`{path}` should be a version like `4.4`, got `{s}`"),
+                        span,
+                    );
+                }
+            }
+            Kind::VersionRequirement => {
+                if let Some(s) = self.string(item, path, &span)
+                    && let Err(e) = VersionRequirement::parse_constraints(s)
+                {
+                    self.error(
+                        format!("`{path}` is not a valid version requirement: {e}"),
+                        span,
+                    );
+                }
+            }
+            Kind::Date => {
+                if let Some(s) = self.string(item, path, &span)
+                    && !is_valid_snapshot_date(s)
+                {
+                    self.error(
+                        format!("`{path}` should be a date like `2024-06-01`, got `{s}`"),
+                        span,
+                    );
+                }
+            }
+            Kind::OneOf(values) => {
+                if let Some(s) = self.string(item, path, &span)
+                    && !values.contains(&s)
+                {
+                    self.error(
+                        format!(
+                            "`{path}` should be one of {}, got `{s}`",
+                            values
+                                .iter()
+                                .map(|v| format!("`{v}`"))
+                                .collect::<Vec<_>>()
+                                .join(", ")
+                        ),
+                        span,
+                    );
+                }
+            }
+            Kind::StringArray => {
+                let Some(array) = item.as_array() else {
+                    self.error(format!("`{path}` should be an array of strings"), span);
+                    return;
+                };
+                for value in array.iter() {
+                    if value.as_str().is_none() {
+                        self.error(
+                            format!("`{path}` should only contain strings"),
+                            value.span().unwrap_or_else(|| span.clone()),
+                        );
+                    }
+                }
+            }
+            Kind::StringMap | Kind::TemplateMap => {
+                let Some(table) = item.as_table_like() else {
+                    self.error(format!("`{path}` should be a table"), span);
+                    return;
+                };
+                for (key, value) in table.iter() {
+                    let value_span = value.span().unwrap_or_else(|| span.clone());
+                    if let Some(s) = self.string(value, &format!("{path}.{key}"), &value_span)
+                        && matches!(kind, Kind::TemplateMap)
+                    {
+                        self.expand(s, &value_span);
+                    }
+                }
+            }
+            Kind::Table(fields) => {
+                let Some(table) = item.as_table_like() else {
+                    self.error(format!("`{path}` should be a table"), span);
+                    return;
+                };
+                self.simple_table(table, fields, path, span);
+            }
+            Kind::RepositoryAuth => {
+                let Some(table) = item.as_table_like() else {
+                    self.error(format!("`{path}` should be a table"), span);
+                    return;
+                };
+                self.simple_table(table, REPOSITORY_AUTH_FIELDS, path, span.clone());
+                let mut keys: Vec<_> = table.iter().map(|(k, _)| k).collect();
+                keys.sort_unstable();
+                let valid = REPOSITORY_AUTH_FORMS.iter().any(|form| {
+                    let mut form = form.to_vec();
+                    form.sort_unstable();
+                    form == keys
+                });
+                if !valid {
+                    self.error(
+                        format!(
+                            "`{path}` should be one of `{{ token_env = \"...\" }}`, `{{ username = \"...\", password_env = \"...\" }}` or `{{ netrc = true }}`"
+                        ),
+                        span,
+                    );
+                }
+            }
+            Kind::TableArray(fields) => {
+                let Some(tables) = tables_of(item) else {
+                    self.error(format!("`{path}` should be an array of tables"), span);
+                    return;
+                };
+                for (i, (table, table_span)) in tables.into_iter().enumerate() {
+                    let table_span = table_span.unwrap_or_else(|| span.clone());
+                    self.simple_table(table, fields, &format!("{path}[{i}]"), table_span);
+                }
+            }
+            Kind::Dependencies => {
+                let Some(array) = item.as_array() else {
+                    self.error(format!("`{path}` should be an array of dependencies"), span);
+                    return;
+                };
+                for (i, value) in array.iter().enumerate() {
+                    let value_span = value.span().unwrap_or_else(|| span.clone());
+                    self.dependency(value, &format!("{path}[{i}]"), value_span);
+                }
+            }
+            Kind::Groups => {
+                let Some(table) = item.as_table_like() else {
+                    self.error(format!("`{path}` should be a table"), span);
+                    return;
+                };
+                for (name, deps) in table.iter() {
+                    let key_span = table
+                        .key(name)
+                        .and_then(|k| k.span())
+                        .unwrap_or_else(|| span.clone());
+                    if [MAIN_GROUP, DEV_GROUP, SUGGESTS_GROUP].contains(&name) {
+                        self.error(
+                            format!(
+                                "The dependency group name `{name}` is reserved, use `dependencies`, `dev_dependencies` or `suggests` instead"
+                            ),
+                            key_span.clone(),
+                        );
+                    }
+                    let deps_span = deps.span().unwrap_or(key_span);
+                    self.item(
+                        deps,
+                        Kind::Dependencies,
+                        &format!("{path}.{name}"),
+                        deps_span,
+                    );
+                }
+            }
+            Kind::EnvVars => {
+                let Some(table) = item.as_table_like() else {
+                    self.error(format!("`{path}` should be a table"), span);
+                    return;
+                };
+                for (package, vars) in table.iter() {
+                    let vars_span = vars.span().unwrap_or_else(|| span.clone());
+                    self.item(
+                        vars,
+                        Kind::TemplateMap,
+                        &format!("{path}.{package}"),
+                        vars_span,
+                    );
+                }
+            }
+            Kind::Overrides => {
+                let Some(table) = item.as_table_like() else {
+                    self.error(format!("`{path}` should be a table"), span);
+                    return;
+                };
+                for (name, value) in table.iter() {
+                    let value_path = format!("{path}.{name}");
+                    let value_span = value
+                        .span()
+                        .or_else(|| table.key(name).and_then(|k| k.span()))
+                        .unwrap_or_else(|| span.clone());
+                    if value.is_str() {
+                        self.item(value, Kind::VersionRequirement, &value_path, value_span);
+                    } else if let Some(t) = value.as_table_like() {
+                        self.dependency_table(t, Some(name), &value_path, value_span);
+                    } else {
+                        self.error(
+                            format!("`{value_path}` should be a version requirement or a table like `{{ repository = \"cran\" }}`"),
+                            value_span,
+                        );
+                    }
+                }
+            }
+            Kind::Patches => {
+                let Some(table) = item.as_table_like() else {
+                    self.error(format!("`{path}` should be a table"), span);
+                    return;
+                };
+                for (name, value) in table.iter() {
+                    let value_path = format!("{path}.{name}");
+                    let value_span = value
+                        .span()
+                        .or_else(|| table.key(name).and_then(|k| k.span()))
+                        .unwrap_or_else(|| span.clone());
+                    let Some(t) = value.as_table_like() else {
+                        self.error(
+                            format!("`{value_path}` should be a table like `{{ git = \"...\", branch = \"...\" }}`"),
+                            value_span,
+                        );
+                        continue;
+                    };
+                    if !DEPENDENCY_KINDS
+                        .iter()
+                        .any(|(key, _, _)| t.contains_key(key))
+                    {
+                        self.error(
+                            format!("`{value_path}` needs a `git`, `path` or `url` source"),
+                            value_span,
+                        );
+                        continue;
+                    }
+                    self.dependency_table(t, Some(name), &value_path, value_span);
+                }
+            }
+            Kind::ConfigureArgs => {
+                let Some(table) = item.as_table_like() else {
+                    self.error(format!("`{path}` should be a table"), span);
+                    return;
+                };
+                for (package, rules) in table.iter() {
+                    let rules_span = rules.span().unwrap_or_else(|| span.clone());
+                    self.item(
+                        rules,
+                        Kind::TableArray(CONFIGURE_ARGS_RULE_FIELDS),
+                        &format!("{path}.{package}"),
+                        rules_span,
+                    );
+                }
+            }
+        }
+    }
+
+    fn dependency(&mut self, value: &Value, path: &str, span: Range<usize>) {
+        if let Some(name) = value.as_str() {
+            if name.trim().is_empty() {
+                self.error(format!("`{path}` is missing a package name"), span);
+            }
+            return;
+        }
+        let Some(table) = value.as_inline_table() else {
+            self.error(
+                format!("`{path}` should be a package name or a table like `{{ name = \"dplyr\", repository = \"cran\" }}`"),
+                span,
+            );
+            return;
+        };
+        self.dependency_table(table, None, path, span);
+    }
+
+    /// Validates a dependency written as a table. Overrides and patches have their name set,
+    /// which is the key of the table in `[overrides]` or `[patch]`.
+    fn dependency_table(
+        &mut self,
+        table: &dyn TableLike,
+        keyed_name: Option<&str>,
+        path: &str,
+        span: Range<usize>,
+    ) {
+        let sources: Vec<_> = DEPENDENCY_KINDS
+            .iter()
+            .filter(|(key, _, _)| table.contains_key(key))
+            .collect();
+        if sources.len() > 1 {
+            self.error(
+                format!("`{path}` can only have one of `git`, `path` or `url`"),
+                span,
+            );
+            return;
+        }
+        let (kind, fields) = sources
+            .first()
+            .map(|(_, kind, fields)| (*kind, *fields))
+            .unwrap_or(("repository", REPOSITORY_DEPENDENCY_FIELDS));
+        let fields = match keyed_name {
+            Some(_) => keyed_dependency_fields(fields),
+            None => fields.to_vec(),
+        };
+
+        self.table(table, &fields, path, span.clone(), &|key| {
+            if keyed_name.is_some() && NOT_IN_KEYED_DEPENDENCIES.contains(&key) {
+                return format!("`{key}` can't be used in `{path}`, it only sets the version or source of the package");
+            }
+            let other_kind = DEPENDENCY_KINDS
+                .iter()
+                .map(|(_, kind, fields)| (*kind, *fields))
+                .chain(std::iter::once(("repository", REPOSITORY_DEPENDENCY_FIELDS)))
+                .find(|(_, fields)| fields.iter().any(|f| f.name == key));
+            match other_kind {
+                Some((other, _)) => format!(
+                    "`{key}` can only be used by {other} dependencies, `{path}` is a {kind} dependency"
+                ),
+                None => format!(
+                    "Unknown field `{key}` for a {kind} dependency, expected one of {}",
+                    field_names(&fields)
+                ),
+            }
+        });
+
+        let name = keyed_name
+            .or_else(|| table.get("name").and_then(|n| n.as_str()))
+            .unwrap_or_default();
+        if keyed_name.is_none() && table.contains_key("name") && name.trim().is_empty() {
+            self.error(format!("`{path}` is missing a package name"), span.clone());
+        }
+
+        match kind {
+            "git" => {
+                let refs = ["tag", "branch", "commit"]
+                    .iter()
+                    .filter(|k| table.contains_key(k))
+                    .count();
+                if refs != 1 {
+                    self.error(
+                        format!("The git dependency `{name}` needs exactly one of `tag`, `branch` or `commit`"),
+                        span,
+                    );
+                }
+            }
+            "repository" => {
+                if let Some((key, alias)) = table.get_key_value("repository")
+                    && let Some(alias) = alias.as_str()
+                    && !self.repository_aliases.contains(alias)
+                {
+                    self.error(
+                        format!("Dependency `{name}` uses the repository `{alias}` which is not an alias of `project.repositories`"),
+                        key.span().unwrap_or(span),
+                    );
+                }
+            }
+            _ => (),
+        }
+    }
+
+    fn workspace_members(&mut self, members: &Item) {
+        let Some(members) = members.as_array() else {
+            return;
+        };
+        let mut seen = HashSet::new();
+        for value in members.iter() {
+            let (Some(member), Some(span)) = (value.as_str(), value.span()) else {
+                continue;
+            };
+            let path = Path::new(member);
+            if member.trim().is_empty() || path.is_absolute() {
+                self.error(
+                    format!("Workspace member `{member}` needs to be a path relative to the workspace root"),
+                    span,
+                );
+            } else if !seen.insert(path.components().collect::<std::path::PathBuf>()) {
+                self.error(format!("Workspace member `{member}` is listed twice"), span);
+            }
+        }
+    }
+}
+
+/// Finds all the problems in the content of a rproject.toml
+pub fn validate_config(content: &str) -> Vec<ConfigDiagnostic> {
+    let document = match Document::parse(content) {
+        Ok(d) => d,
+        Err(e) => {
+            return vec![ConfigDiagnostic::new(
+                e.message().trim(),
+                e.span().unwrap_or_default(),
+            )];
+        }
+    };
+
+    let mut validator = Validator::default();
+    let root = document.as_table();
+    if let Some(repositories) = root.get("project").and_then(|p| p.get("repositories"))
+        && let Some(tables) = tables_of(repositories)
+    {
+        validator.repository_aliases = tables
+            .iter()
+            .filter_map(|(t, _)| t.get("alias").and_then(|a| a.as_str()))
+            .map(|a| a.to_string())
+            .collect();
+    }
+    validator.simple_table(root, CONFIG_FIELDS, "", 0..0);
+    if let Some(members) = root.get("workspace").and_then(|w| w.get("members")) {
+        validator.workspace_members(members);
+    }
+
+    let mut diagnostics = validator.diagnostics;
+    // In case the description of the fields above is missing something the config types check
+    if diagnostics.is_empty()
+        && let Err(e) = Config::from_str(content)
+    {
+        diagnostics.push(ConfigDiagnostic::new(e.source.to_string(), 0..0));
+    }
+    diagnostics.sort_by_key(|d| d.span.start);
+    diagnostics
+}
+
+fn kind_schema(kind: Kind) -> serde_json::Value {
+    match kind {
+        Kind::String => json!({"type": "string"}),
+        Kind::Bool => json!({"type": "boolean"}),
+        Kind::Url => json!({"type": "string", "pattern": "^(https?://|.*\\$\\{)"}),
+        Kind::GitUrl => json!({"type": "string"}),
+        Kind::Version => json!({"type": "string", "pattern": "^[0-9]+(\\.[0-9]+)*$"}),
+        Kind::VersionRequirement => json!({"type": "string"}),
+        Kind::Date => json!({"type": "string", "pattern": "^[0-9]{4}-[0-9]{2}-[0-9]{2}$"}),
+        Kind::OneOf(values) => json!({"type": "string", "enum": values}),
+        Kind::StringArray => json!({"type": "array", "items": {"type": "string"}}),
+        Kind::StringMap | Kind::TemplateMap => {
+            json!({"type": "object", "additionalProperties": {"type": "string"}})
+        }
+        Kind::Table(fields) => object_schema(fields),
+        Kind::RepositoryAuth => {
+            let forms: Vec<_> = REPOSITORY_AUTH_FORMS
+                .iter()
+                .map(|form| {
+                    let fields: Vec<_> = REPOSITORY_AUTH_FIELDS
+                        .iter()
+                        .filter(|f| form.contains(&f.name))
+                        .map(|f| Field {
+                            required: true,
+                            ..*f
+                        })
+                        .collect();
+                    object_schema(&fields)
+                })
+                .collect();
+            json!({"oneOf": forms})
+        }
+        Kind::TableArray(fields) => json!({"type": "array", "items": object_schema(fields)}),
+        Kind::Dependencies => {
+            json!({"type": "array", "items": {"$ref": "#/definitions/dependency"}})
+        }
+        Kind::Groups => json!({
+            "type": "object",
+            "additionalProperties": kind_schema(Kind::Dependencies),
+        }),
+        Kind::EnvVars => json!({
+            "type": "object",
+            "additionalProperties": kind_schema(Kind::StringMap),
+        }),
+        Kind::ConfigureArgs => json!({
+            "type": "object",
+            "additionalProperties": kind_schema(Kind::TableArray(CONFIGURE_ARGS_RULE_FIELDS)),
+        }),
+        Kind::Overrides => json!({
+            "type": "object",
+            "additionalProperties": {"$ref": "#/definitions/override"},
+        }),
+        Kind::Patches => json!({
+            "type": "object",
+            "additionalProperties": {"$ref": "#/definitions/patch"},
+        }),
+    }
+}
+
+fn object_schema(fields: &[Field]) -> serde_json::Value {
+    let properties: serde_json::Map<_, _> = fields
+        .iter()
+        .map(|f| {
+            let mut schema = kind_schema(f.kind);
+            schema["description"] = json!(f.doc);
+            (f.name.to_string(), schema)
+        })
+        .collect();
+    let required: Vec<_> = fields
+        .iter()
+        .filter(|f| f.required)
+        .map(|f| f.name)
+        .collect();
+
+    json!({
+        "type": "object",
+        "properties": properties,
+        "required": required,
+        "additionalProperties": false,
+    })
+}
+
+/// The schemas of the dependency tables, the repository one being last. Overrides and patches
+/// are keyed by their name so they don't have one.
+fn dependency_table_schemas(is_keyed: bool) -> Vec<serde_json::Value> {
+    let fields_schema = |fields: &[Field]| {
+        if is_keyed {
+            object_schema(&keyed_dependency_fields(fields))
+        } else {
+            object_schema(fields)
+        }
+    };
+    let mut schemas: Vec<_> = DEPENDENCY_KINDS
+        .iter()
+        .map(|(_, _, fields)| fields_schema(fields))
+        .collect();
+    let mut repository_dependency = fields_schema(REPOSITORY_DEPENDENCY_FIELDS);
+    // The other kinds are found by their source key so a repository dependency can't have them
+    repository_dependency["not"] = json!({
+        "anyOf": DEPENDENCY_KINDS
+            .iter()
+            .map(|(key, _, _)| json!({"required": [key]}))
+            .collect::<Vec<_>>(),
+    });
+    schemas.push(repository_dependency);
+    schemas
+}
+
+/// The JSON Schema of rproject.toml
+pub fn config_json_schema() -> serde_json::Value {
+    let mut dependency_kinds = vec![json!({"type": "string", "description": "A package name"})];
+    dependency_kinds.extend(dependency_table_schemas(false));
+    let mut override_kinds = vec![json!({
+        "type": "string",
+        "description": "A version requirement, eg `>= 1.1.0`",
+    })];
+    override_kinds.extend(dependency_table_schemas(true));
+    let mut patch_kinds = dependency_table_schemas(true);
+    // A patch can't be a repository dependency
+    patch_kinds.pop();
+
+    let mut schema = object_schema(CONFIG_FIELDS);
+    schema["$schema"] = json!("http://json-schema.org/draft-07/schema#");
+    schema["title"] = json!("rproject.toml");
+    schema["definitions"] = json!({
+        "dependency": {"anyOf": dependency_kinds},
+        "override": {"anyOf": override_kinds},
+        "patch": {"anyOf": patch_kinds},
+    });
+    schema
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn valid_config_files_have_no_diagnostics() {
+        for path in std::fs::read_dir("src/tests/valid_config/").unwrap() {
+            let path = path.unwrap().path();
+            let content = std::fs::read_to_string(&path).unwrap();
+            assert_eq!(validate_config(&content), vec![], "{}", path.display());
+        }
+    }
+
+    #[test]
+    fn reports_all_problems_with_location() {
+        let path = Path::new("src/tests/config_validation/many_errors.toml");
+        let content = std::fs::read_to_string(path).unwrap();
+        let out: String = validate_config(&content)
+            .iter()
+            .map(|d| d.render(path, &content))
+            .collect::<Vec<_>>()
+            .join("\n");
+        insta::assert_snapshot!("validate-many-errors", out);
+    }
+
+    /// Gets the fields serde accepts for a struct, which are the ones not skipped
+    fn serde_fields<T: serde::de::DeserializeOwned>() -> Vec<&'static str> {
+        use serde::de::{Error, Visitor, value};
+
+        struct FieldsRecorder<'a>(&'a mut Vec<&'static str>);
+
+        impl<'de> serde::Deserializer<'de> for FieldsRecorder<'_> {
+            type Error = value::Error;
+
+            fn deserialize_any<V: Visitor<'de>>(self, _: V) -> Result<V::Value, Self::Error> {
+                Err(value::Error::custom("not a struct"))
+            }
+
+            fn deserialize_struct<V: Visitor<'de>>(
+                self,
+                _name: &'static str,
+                fields: &'static [&'static str],
+                _visitor: V,
+            ) -> Result<V::Value, Self::Error> {
+                self.0.extend(fields);
+                Err(value::Error::custom("recorded"))
+            }
+
+            serde::forward_to_deserialize_any! {
+                bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes
+                byte_buf option unit unit_struct newtype_struct seq tuple tuple_struct map enum
+                identifier ignored_any
+            }
+        }
+
+        let mut fields = Vec::new();
+        let _ = T::deserialize(FieldsRecorder(&mut fields));
+        fields.sort();
+        fields
+    }
+
+    fn table_fields(fields: &[Field]) -> Vec<&'static str> {
+        let mut names: Vec<_> = fields.iter().map(|f| f.name).collect();
+        names.sort();
+        names
+    }
+
+    /// The untagged enums don't expose their fields, so we serialize a value of each variant
+    /// with every field set. Adding a field to a variant will fail to compile here.
+    fn serialized_fields<T: Serialize>(value: T) -> Vec<String> {
+        let serde_json::Value::Object(map) = serde_json::to_value(value).unwrap() else {
+            panic!("Not serialized as a table");
+        };
+        let mut names: Vec<_> = map.keys().cloned().collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn fields_match_the_config_types() {
+        use crate::config::{
+            ArchTarget, Author, ConfigDependency, ConfigureArgsRule, OsTarget, Project, Repository,
+            RepositoryAuth, ResolutionTarget, WorkspaceConfig,
+        };
+
+        assert_eq!(serde_fields::<Config>(), table_fields(CONFIG_FIELDS));
+        assert_eq!(serde_fields::<Project>(), table_fields(PROJECT_FIELDS));
+        assert_eq!(serde_fields::<Author>(), table_fields(AUTHOR_FIELDS));
+        assert_eq!(
+            serde_fields::<Repository>(),
+            table_fields(REPOSITORY_FIELDS)
+        );
+        assert_eq!(
+            serde_fields::<WorkspaceConfig>(),
+            table_fields(WORKSPACE_FIELDS)
+        );
+        assert_eq!(
+            serde_fields::<ResolutionTarget>(),
+            table_fields(TARGET_FIELDS)
+        );
+
+        let url = serde_json::from_value(json!("https://example.com/pkg.tar.gz")).unwrap();
+        let git = GitUrl::try_from("https://github.com/A2-ai/rv").unwrap();
+        let dependencies = [
+            (
+                ConfigDependency::Detailed {
+                    name: "pkg".to_string(),
+                    repository: Some("cran".to_string()),
+                    version: Some(">= 1.0".to_string()),
+                    install_suggestions: true,
+                    force_source: Some(true),
+                    dependencies_only: true,
+                    exclude: vec![],
+                    os: Some(OsTarget::Linux),
+                    arch: Some(ArchTarget::X86_64),
+                    r_version: Some(">= 4.3".to_string()),
+                },
+                REPOSITORY_DEPENDENCY_FIELDS,
+            ),
+            (
+                ConfigDependency::Git {
+                    git,
+                    commit: Some("abc".to_string()),
+                    tag: Some("v1".to_string()),
+                    branch: Some("main".to_string()),
+                    directory: Some("pkg".to_string()),
+                    name: "pkg".to_string(),
+                    install_suggestions: true,
+                    dependencies_only: true,
+                    exclude: vec![],
+                    os: Some(OsTarget::Linux),
+                    arch: Some(ArchTarget::X86_64),
+                    r_version: Some(">= 4.3".to_string()),
+                },
+                GIT_DEPENDENCY_FIELDS,
+            ),
+            (
+                ConfigDependency::Local {
+                    path: "pkg".into(),
+                    name: "pkg".to_string(),
+                    install_suggestions: true,
+                    dependencies_only: true,
+                    exclude: vec![],
+                    os: Some(OsTarget::Linux),
+                    arch: Some(ArchTarget::X86_64),
+                    r_version: Some(">= 4.3".to_string()),
+                },
+                LOCAL_DEPENDENCY_FIELDS,
+            ),
+            (
+                ConfigDependency::Url {
+                    url,
+                    name: "pkg".to_string(),
+                    install_suggestions: true,
+                    dependencies_only: true,
+                    exclude: vec![],
+                    os: Some(OsTarget::Linux),
+                    arch: Some(ArchTarget::X86_64),
+                    r_version: Some(">= 4.3".to_string()),
+                },
+                URL_DEPENDENCY_FIELDS,
+            ),
+        ];
+        for (dep, fields) in dependencies {
+            assert_eq!(serialized_fields(&dep), table_fields(fields), "{dep:?}");
+        }
+
+        let auths = [
+            RepositoryAuth::Token {
+                token_env: "TOKEN".to_string(),
+            },
+            RepositoryAuth::Basic {
+                username: "me".to_string(),
+                password_env: "PASSWORD".to_string(),
+            },
+            RepositoryAuth::Netrc { netrc: true },
+        ];
+        let mut auth_fields = Vec::new();
+        for (auth, form) in auths.iter().zip(REPOSITORY_AUTH_FORMS) {
+            let fields = serialized_fields(auth);
+            let mut form = form.to_vec();
+            form.sort();
+            assert_eq!(fields, form);
+            auth_fields.extend(fields);
+        }
+        assert_eq!(auths.len(), REPOSITORY_AUTH_FORMS.len());
+        auth_fields.sort();
+        assert_eq!(auth_fields, table_fields(REPOSITORY_AUTH_FIELDS));
+
+        let args = vec!["--with-foo".to_string()];
+        let rules = [
+            ConfigureArgsRule::OsArch {
+                os: OsTarget::Linux,
+                arch: ArchTarget::X86_64,
+                args: args.clone(),
+            },
+            ConfigureArgsRule::Os {
+                os: OsTarget::Linux,
+                args: args.clone(),
+            },
+            ConfigureArgsRule::Arch {
+                arch: ArchTarget::X86_64,
+                args: args.clone(),
+            },
+            ConfigureArgsRule::Default { args },
+        ];
+        let mut rule_fields: Vec<_> = rules.iter().flat_map(serialized_fields).collect();
+        rule_fields.sort();
+        rule_fields.dedup();
+        assert_eq!(rule_fields, table_fields(CONFIGURE_ARGS_RULE_FIELDS));
+    }
+
+    #[test]
+    fn json_schema_is_up_to_date() {
+        let schema = serde_json::to_string_pretty(&config_json_schema()).unwrap() + "\n";
+        let saved = std::fs::read_to_string("docs/rproject.schema.json")
+            .unwrap()
+            .replace("\r\n", "\n");
+        assert_eq!(
+            saved, schema,
+            "Run `rv config schema > docs/rproject.schema.json` to update it"
+        );
+    }
+}
@@ -3,6 +3,7 @@ pub const DESCRIPTION_FILENAME: &str = "DESCRIPTION";
 pub const SOURCE_PACKAGES_PATH: &str = "/src/contrib/PACKAGES";
 pub const RUNIVERSE_PACKAGES_API_PATH: &str = "api/packages";
 pub const LOCKFILE_NAME: &str = "rv.lock";
+pub const CONFIG_FILENAME: &str = "rproject.toml";
 
 pub const RV_DIR_NAME: &str = "rv";
 pub const LIBRARY_ROOT_DIR_NAME: &str = "library";
@@ -10,6 +11,8 @@ pub const STAGING_DIR_NAME: &str = "__rv__staging";
 pub(crate) const LIBRARY_METADATA_FILENAME: &str = ".rv.metadata";
 pub const BUILD_LOG_FILENAME: &str = "__rv_build.log";
 pub const BUILT_FROM_SOURCE_FILENAME: &str = ".__rv_source";
+/// Where the SHA-256 of a downloaded source tarball is kept, next to what was extracted from it
+pub const SOURCE_SHA256_FILENAME: &str = ".__rv_sha256";
 
 /// How long are the package databases cached for
 /// Same default value as PKGCACHE_TIMEOUT:
@@ -25,6 +28,7 @@ pub const SYS_DEPS_CHECK_IN_PATH_ENV_VAR_NAME: &str = "RV_SYS_DEPS_CHECK_IN_PATH
 pub const SUBMODULE_UPDATE_DISABLE_ENV_VAR_NAME: &str = "RV_SUBMODULE_UPDATE_DISABLE";
 pub const CACHE_DIR_ENV_VAR_NAME: &str = "RV_CACHE_DIR";
 pub const COPY_THREADS_ENV_VAR_NAME: &str = "RV_COPY_THREADS";
+pub const LINK_MODE_ENV_VAR_NAME: &str = "RV_LINK_MODE";
 
 // List obtained from the REPL: `rownames(installed.packages(priority="base"))`
 // Those will have the same version as R
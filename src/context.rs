@@ -1,6 +1,6 @@
 //! Project context for rv library usage
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::env;
 use std::error::Error;
 use std::path::{Path, PathBuf};
@@ -23,13 +23,18 @@ struct CondaEnvInfo {
     name: Option<String>,
     prefix: PathBuf,
 }
-use crate::lockfile::Lockfile;
-use crate::package::Package;
+use crate::global_config::global_config;
+use crate::http::HttpError;
+use crate::lockfile::{Lockfile, Source};
+use crate::package::{Package, get_bundled_builtin_packages};
+use crate::repository_urls::{get_archive_index_url, get_current_index_url};
+use crate::snapshot::is_valid_snapshot_date;
+use crate::template::UrlTemplates;
 use crate::utils::create_spinner;
 use crate::{
-    Config, CondaManager, DiskCache, GitExecutor, Http, Library, RCommandLine, RCmd, Repository, RepositoryDatabase,
-    Resolution, Resolver, SystemInfo, Version, find_r_version_command, get_package_file_urls, http,
-    system_req,
+    Config, ConfigDependency, CondaManager, DiskCache, GitExecutor, Http, Library, RCommandLine, RCmd, Repository, RepositoryDatabase,
+    Resolution, ResolvedDependency, ResolutionStrategy, ResolutionTarget, Resolver, SystemInfo, Version, WorkspaceMember, find_r_version_command,
+    get_package_file_urls, http, load_workspace_members, system_req,
 };
 
 /// Try to find the conda executable using multiple methods
@@ -124,6 +129,26 @@ pub enum ResolveMode {
     Default,
     /// Ignore lockfile and resolve all dependencies fresh
     FullUpgrade,
+    /// Use lockfile for all dependencies but the ones set with `Context::set_upgrade_packages`,
+    /// which are resolved fresh
+    PartialUpgrade,
+    /// Use the lockfile as is, without resolving anything or checking it against the config
+    Frozen,
+}
+
+/// What is needed to resolve the dependencies for one of the resolution targets of the config,
+/// other than the current system
+#[derive(Debug)]
+pub struct TargetContext {
+    pub target: ResolutionTarget,
+    pub id: String,
+    pub cache: DiskCache,
+    pub databases: Vec<(RepositoryDatabase, bool)>,
+    /// From a previous run with that R version or from the table bundled in rv, see
+    /// `find_builtin_packages`
+    pub builtin_packages: HashMap<String, Package>,
+    /// The entries of the lockfile for that target
+    pub lockfile: Option<Lockfile>,
 }
 
 /// Project context containing all state needed for rv operations
@@ -141,10 +166,30 @@ pub struct Context {
     /// Taken from posit API. Only for some linux distrib, it will remain empty
     /// on mac/windows/arch etc
     pub system_dependencies: HashMap<String, Vec<String>>,
+    /// Downloads with the HTTP settings of the repositories of the config
+    pub http: Http,
     /// Whether to show progress bars/spinners
     pub show_progress_bar: bool,
     /// Conda environment information (if using conda)
     pub conda_env: Option<PathBuf>,
+    /// The members of the workspace if the config is a workspace root, resolved together with
+    /// the root project
+    pub workspace_members: Vec<WorkspaceMember>,
+    /// The id of the resolution target matching the current system, if the config has targets
+    pub current_target: Option<String>,
+    /// The other resolution targets of the config, resolved along the current system into a
+    /// single lockfile
+    pub other_targets: Vec<TargetContext>,
+    /// The whole lockfile if it was resolved for several targets, `lockfile` only having the
+    /// entries of the current one
+    pub universal_lockfile: Option<Lockfile>,
+    /// How to pick the versions, the one the lockfile was resolved with by default
+    pub resolution_strategy: ResolutionStrategy,
+    /// Versions published after that day are ignored. The one of the config, unless overridden
+    /// on the command line
+    pub exclude_newer: Option<String>,
+    /// The packages to resolve fresh in `ResolveMode::PartialUpgrade`
+    pub upgrade_packages: HashSet<String>,
 }
 
 impl Context {
@@ -159,6 +204,25 @@ impl Context {
         config_file: &Path,
         r_command_lookup: RCommandLookup,
         cache_dir: Option<&Path>,
+    ) -> Result<Self, Box<dyn Error + Send + Sync>> {
+        Self::load(config_file, r_command_lookup, cache_dir, None)
+    }
+
+    /// Uses that lockfile instead of the one on disk, eg the one merged from both sides of a git
+    /// conflict that is only written once resolved again
+    pub fn new_with_lockfile(
+        config_file: &Path,
+        r_command_lookup: RCommandLookup,
+        lockfile: Lockfile,
+    ) -> Result<Self, Box<dyn Error + Send + Sync>> {
+        Self::load(config_file, r_command_lookup, None, Some(lockfile))
+    }
+
+    fn load(
+        config_file: &Path,
+        r_command_lookup: RCommandLookup,
+        cache_dir: Option<&Path>,
+        lockfile: Option<Lockfile>,
     ) -> Result<Self, Box<dyn Error + Send + Sync>> {
         let config = Config::from_file(config_file)?;
 
@@ -228,24 +292,84 @@ impl Context {
         };
 
         let project_dir = config_file.parent().unwrap().to_path_buf();
+        let workspace_members = load_workspace_members(&project_dir, &config)?;
+        let http =
+            Http::new(config.repositories(), &project_dir).with_mirrors(&global_config().mirrors());
+        let target_ids: Vec<_> = config.targets().iter().map(|t| t.id()).collect();
+        let current_target = config
+            .targets()
+            .iter()
+            .find(|t| t.matches(&cache.system_info, &r_version))
+            .map(|t| t.id());
         let lockfile_path = project_dir.join(config.lockfile_name());
-        let lockfile = if lockfile_path.exists() && config.use_lockfile() {
-            if let Some(lockfile) = Lockfile::load(&lockfile_path)? {
-                if !lockfile.r_version().hazy_match(&r_version) {
-                    log::debug!(
-                        "R version in config file and lockfile are not compatible. Ignoring lockfile."
-                    );
-                    None
-                } else {
-                    Some(lockfile)
-                }
-            } else {
+        let lockfile = match lockfile {
+            Some(lockfile) => Some(lockfile),
+            None if lockfile_path.exists() && config.use_lockfile() => {
+                Lockfile::load(&lockfile_path)?
+            }
+            None => None,
+        };
+        let lockfile = if let Some(lockfile) = lockfile {
+            if lockfile.targets() != target_ids {
+                log::debug!(
+                    "Resolution targets in config file and lockfile are different. Ignoring lockfile."
+                );
                 None
+            } else if target_ids.is_empty() && !lockfile.r_version().hazy_match(&r_version) {
+                log::debug!(
+                    "R version in config file and lockfile are not compatible. Ignoring lockfile."
+                );
+                None
+            } else if config.exclude_newer() != lockfile.exclude_newer() {
+                log::debug!(
+                    "Exclude newer date in config file and lockfile are different. Ignoring lockfile."
+                );
+                None
+            } else {
+                Some(lockfile)
             }
         } else {
             None
         };
 
+        let resolution_strategy = lockfile
+            .as_ref()
+            .map(|l| l.resolution_strategy())
+            .unwrap_or_default();
+        let exclude_newer = config.exclude_newer().map(|d| d.to_string());
+        // The current system only uses its own entries of a lockfile resolved for several targets
+        let (lockfile, universal_lockfile) = match lockfile {
+            Some(l) if !target_ids.is_empty() => {
+                (current_target.as_ref().map(|id| l.for_target(id)), Some(l))
+            }
+            l => (l, None),
+        };
+        let other_targets = config
+            .targets()
+            .iter()
+            .filter(|t| current_target.as_ref() != Some(&t.id()))
+            .map(|target| {
+                let id = target.id();
+                let builtin_packages = find_builtin_packages(&cache, &target.r_version)
+                    .unwrap_or_else(|| {
+                        log::debug!("No builtin packages found for target {id}");
+                        HashMap::new()
+                    });
+                Ok(TargetContext {
+                    target: target.clone(),
+                    cache: DiskCache::new_in_dir(
+                        &target.r_version,
+                        target.system_info(),
+                        &cache.root,
+                    )?,
+                    databases: Vec::new(),
+                    builtin_packages,
+                    lockfile: universal_lockfile.as_ref().map(|l| l.for_target(&id)),
+                    id,
+                })
+            })
+            .collect::<Result<Vec<_>, Box<dyn Error + Send + Sync>>>()?;
+
         // Determine the library path based on config or conda environment
         let library_path = if let Some(p) = config.library() {
             Some(p.clone())
@@ -295,9 +419,12 @@ impl Context {
         log::debug!("Library path: {}", library.path.display());
         library.find_content();
 
-        // We can only fetch the builtin packages if we have the right R
+        // We can only fetch the builtin packages if we have the right R, otherwise we use the ones
+        // of a previous run or the table bundled in rv
         let builtin_packages = if r_version_found {
             cache.get_builtin_packages_versions(&r_cmd)?
+        } else if let Some(builtin) = find_builtin_packages(&cache, &r_version) {
+            builtin
         } else {
             log::warn!(
                 "R version not found: there may be issues with resolution regarding recommended packages"
@@ -305,7 +432,7 @@ impl Context {
             HashMap::new()
         };
 
-        Ok(Self {
+        let mut context = Self {
             config,
             cache,
             r_version,
@@ -316,9 +443,38 @@ impl Context {
             r_cmd,
             builtin_packages,
             system_dependencies: HashMap::new(),
+            http,
             show_progress_bar: false,
             conda_env: conda_env_path,
-        })
+            workspace_members,
+            current_target,
+            other_targets,
+            universal_lockfile,
+            resolution_strategy,
+            exclude_newer,
+            upgrade_packages: HashSet::new(),
+        };
+        let url_templates = context.url_templates();
+        let lockfiles = context.lockfile.iter_mut().chain(
+            context
+                .other_targets
+                .iter_mut()
+                .flat_map(|t| &mut t.lockfile),
+        );
+        for lockfile in lockfiles {
+            lockfile.set_config_url_templates(url_templates.clone());
+        }
+
+        Ok(context)
+    }
+
+    /// The URLs written with env vars in the config of the root and of the workspace members
+    pub fn url_templates(&self) -> UrlTemplates {
+        let mut templates = self.config.url_templates().clone();
+        for member in &self.workspace_members {
+            templates.extend(member.config.url_templates());
+        }
+        templates
     }
 
     /// Enable progress bar display for long-running operations
@@ -326,36 +482,241 @@ impl Context {
         self.show_progress_bar = true;
     }
 
-    /// Load package databases from repositories
+    /// Resolves with that strategy instead of the one of the lockfile, ignoring the lockfile if
+    /// they differ. Needs to be called before loading the databases.
+    pub fn set_resolution_strategy(&mut self, strategy: ResolutionStrategy) {
+        if strategy == self.resolution_strategy {
+            return;
+        }
+        if self.lockfile.is_some() {
+            log::debug!(
+                "Lockfile was resolved with the {} strategy. Ignoring lockfile.",
+                self.resolution_strategy.as_str()
+            );
+        }
+        self.resolution_strategy = strategy;
+        self.lockfile = None;
+        self.universal_lockfile = None;
+        for target in &mut self.other_targets {
+            target.lockfile = None;
+        }
+    }
+
+    /// Ignores the versions published after that day instead of the cutoff of the config,
+    /// ignoring the lockfile if it was resolved with another one. Needs to be called before loading the
+    /// databases.
+    pub fn set_exclude_newer(&mut self, date: String) -> Result<(), String> {
+        if !is_valid_snapshot_date(&date) {
+            return Err(format!(
+                "The exclude newer date `{date}` is not a date like `2025-03-01`."
+            ));
+        }
+        if self.exclude_newer.as_ref() == Some(&date) {
+            return Ok(());
+        }
+        if self.lockfile.is_some() {
+            log::debug!(
+                "Lockfile was resolved with another exclude newer date. Ignoring lockfile."
+            );
+        }
+        self.exclude_newer = Some(date);
+        self.lockfile = None;
+        self.universal_lockfile = None;
+        for target in &mut self.other_targets {
+            target.lockfile = None;
+        }
+        Ok(())
+    }
+
+    /// Sets the packages to upgrade in `ResolveMode::PartialUpgrade`, along with the packages
+    /// they depend on in the lockfile if `with_deps` is set. Errors if one of them is neither in
+    /// the lockfile nor a dependency of the project.
+    pub fn set_upgrade_packages(
+        &mut self,
+        packages: &[String],
+        with_deps: bool,
+    ) -> Result<(), String> {
+        let lockfiles: Vec<_> = self
+            .lockfile
+            .iter()
+            .chain(self.other_targets.iter().flat_map(|t| &t.lockfile))
+            .collect();
+        let dependencies = self.all_dependencies();
+        let mut upgrade_packages = HashSet::new();
+
+        for name in packages {
+            let in_lockfile = lockfiles
+                .iter()
+                .any(|l| l.get_package(name, None).is_some());
+            if !lockfiles.is_empty()
+                && !in_lockfile
+                && !dependencies.iter().any(|d| d.name() == name)
+            {
+                return Err(format!(
+                    "Package `{name}` is not a dependency of the project."
+                ));
+            }
+            upgrade_packages.insert(name.to_string());
+            if with_deps {
+                for lockfile in &lockfiles {
+                    upgrade_packages.extend(
+                        lockfile
+                            .get_package_tree(name, None)
+                            .into_iter()
+                            .map(|n| n.to_string()),
+                    );
+                }
+            }
+        }
+
+        self.upgrade_packages = upgrade_packages;
+        Ok(())
+    }
+
+    /// Errors if the config has resolution targets and the current system is not one of them
+    fn check_current_target(&self) -> Result<(), Box<dyn Error + Send + Sync>> {
+        if self.config.targets().is_empty() || self.current_target.is_some() {
+            return Ok(());
+        }
+        let targets: Vec<_> = self.config.targets().iter().map(|t| t.id()).collect();
+        Err(format!(
+            "The current system ({} {}, {}, R {}) is not one of the resolution targets of the config: {}",
+            self.cache.system_info.os_family(),
+            self.cache.system_info.version,
+            self.cache.system_info.arch().unwrap_or("unknown arch"),
+            self.r_version.original,
+            targets.join(", ")
+        )
+        .into())
+    }
+
+    /// Load package databases from repositories, for the current system and for each of the
+    /// other resolution targets
     pub fn load_databases(&mut self) -> Result<(), Box<dyn Error + Send + Sync>> {
+        self.check_current_target()?;
         let pb = create_spinner(self.show_progress_bar, "Loading databases...");
-        self.databases = load_databases(self.config.repositories(), &self.cache)?;
+        self.databases = load_databases(self.config.repositories(), &self.cache, &self.http)?;
+        for target in &mut self.other_targets {
+            target.databases =
+                load_databases(self.config.repositories(), &target.cache, &self.http)?;
+        }
         pb.finish_and_clear();
         Ok(())
     }
 
-    /// Load databases only if the lockfile cannot fully resolve dependencies
+    /// Whether the lockfile is enough to resolve the dependencies on that system and R version
+    fn lockfile_can_resolve(
+        &self,
+        lockfile: Option<&Lockfile>,
+        system_info: &SystemInfo,
+        r_version: &Version,
+    ) -> bool {
+        // Overrides and patches can apply to packages that are not top level dependencies, they
+        // need to match the lockfile as well
+        let on_target = |d: &&ConfigDependency| d.matches_target(system_info, r_version);
+        let overrides: Vec<_> = self.config.overrides().iter().filter(on_target).collect();
+        let patches: Vec<_> = self.config.patches().iter().filter(on_target).collect();
+        let mut dependencies = self.dependencies_for(system_info, r_version);
+        dependencies.extend(&overrides);
+        dependencies.extend(&patches);
+        lockfile
+            .map(|l| {
+                l.can_resolve(&dependencies, self.config.repositories())
+                    && l.overridden_packages()
+                        .all(|name| overrides.iter().any(|o| o.name() == name))
+                    && l.patched_packages()
+                        .all(|name| patches.iter().any(|p| p.name() == name))
+            })
+            .unwrap_or(false)
+    }
+
+    /// Load databases only if the lockfile cannot fully resolve dependencies, for the current
+    /// system and for each of the other resolution targets
     pub fn load_databases_if_needed(&mut self) -> Result<(), Box<dyn Error + Send + Sync>> {
-        let can_resolve = self
-            .lockfile
-            .as_ref()
-            .map(|l| l.can_resolve(self.config.dependencies(), self.config.repositories()))
-            .unwrap_or(false);
+        self.check_current_target()?;
+        if !self.lockfile_can_resolve(
+            self.lockfile.as_ref(),
+            &self.cache.system_info,
+            &self.r_version,
+        ) {
+            return self.load_databases();
+        }
 
-        if !can_resolve {
-            self.load_databases()?;
+        let outdated: Vec<_> = self
+            .other_targets
+            .iter()
+            .map(|t| {
+                !self.lockfile_can_resolve(
+                    t.lockfile.as_ref(),
+                    &t.cache.system_info,
+                    &t.target.r_version,
+                )
+            })
+            .collect();
+        for (target, outdated) in self.other_targets.iter_mut().zip(outdated) {
+            if outdated {
+                target.databases =
+                    load_databases(self.config.repositories(), &target.cache, &self.http)?;
+            }
         }
         Ok(())
     }
 
+    /// Loads the lockfile for `ResolveMode::Frozen`, even if the config changed since it was
+    /// written. Errors if there is no lockfile or if it was not resolved for the current system.
+    fn load_frozen_lockfile(&mut self) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let lockfile_path = self.lockfile_path();
+        let lockfile = if self.config.use_lockfile() && lockfile_path.exists() {
+            Lockfile::load(&lockfile_path)?
+        } else {
+            None
+        };
+        let Some(lockfile) = lockfile else {
+            return Err(format!(
+                "No usable lockfile found at {}, it is required to sync with `--frozen`",
+                lockfile_path.display()
+            )
+            .into());
+        };
+
+        let mut lockfile = if lockfile.targets().is_empty() {
+            if !lockfile.r_version().hazy_match(&self.r_version) {
+                return Err(format!(
+                    "The lockfile was resolved for R {} but R {} is used",
+                    lockfile.r_version().original,
+                    self.r_version.original
+                )
+                .into());
+            }
+            lockfile
+        } else if let Some(id) = self
+            .current_target
+            .as_ref()
+            .filter(|id| lockfile.targets().contains(id))
+        {
+            lockfile.for_target(id)
+        } else {
+            return Err(format!(
+                "The lockfile was not resolved for the current system, only for: {}",
+                lockfile.targets().join(", ")
+            )
+            .into());
+        };
+        lockfile.set_config_url_templates(self.url_templates());
+        self.lockfile = Some(lockfile);
+        Ok(())
+    }
+
     /// Load system requirements from posit API (only supported on some Linux distros)
-    pub fn load_system_requirements(&mut self) {
+    pub fn load_system_requirements(&mut self) -> Result<(), HttpError> {
         if !system_req::is_supported(&self.cache.system_info) {
-            return;
+            return Ok(());
         }
         let pb = create_spinner(self.show_progress_bar, "Loading system requirements...");
-        self.system_dependencies = self.cache.get_system_requirements();
+        let system_dependencies = self.cache.get_system_requirements(&self.http);
         pb.finish_and_clear();
+        self.system_dependencies = system_dependencies?;
+        Ok(())
     }
 
     /// Load databases and system requirements based on resolve mode
@@ -367,9 +728,10 @@ impl Context {
         // are contained in the lockfile because we ignore the lockfile during initial resolution
         match resolve_mode {
             ResolveMode::Default => self.load_databases_if_needed()?,
-            ResolveMode::FullUpgrade => self.load_databases()?,
+            ResolveMode::FullUpgrade | ResolveMode::PartialUpgrade => self.load_databases()?,
+            ResolveMode::Frozen => self.load_frozen_lockfile()?,
         }
-        self.load_system_requirements();
+        self.load_system_requirements()?;
         Ok(())
     }
 
@@ -385,42 +747,135 @@ impl Context {
         self.library.path.join(STAGING_DIR_NAME)
     }
 
+    /// The dependencies of the project and of all the workspace members
+    pub fn all_dependencies(&self) -> Vec<&ConfigDependency> {
+        let mut out = self.config.all_dependencies();
+        for member in &self.workspace_members {
+            out.extend(member.all_dependencies());
+        }
+        out
+    }
+
+    /// The dependencies matching the current system and the R version used, see
+    /// `ConfigDependency::matches_target`
+    pub fn target_dependencies(&self) -> Vec<&ConfigDependency> {
+        self.dependencies_for(&self.cache.system_info, &self.r_version)
+    }
+
+    /// The dependencies matching that system and R version
+    fn dependencies_for(
+        &self,
+        system_info: &SystemInfo,
+        r_version: &Version,
+    ) -> Vec<&ConfigDependency> {
+        self.all_dependencies()
+            .into_iter()
+            .filter(|d| d.matches_target(system_info, r_version))
+            .collect()
+    }
+
+    /// The dependency groups of the project, merged by name with the groups of the workspace
+    /// members
+    pub fn dependency_groups(&self) -> Vec<(&str, Vec<&ConfigDependency>)> {
+        let mut out: Vec<(&str, Vec<&ConfigDependency>)> = Vec::new();
+        let configs =
+            std::iter::once(&self.config).chain(self.workspace_members.iter().map(|m| &m.config));
+        for config in configs {
+            for (name, deps) in config.dependency_groups() {
+                if let Some((_, existing)) = out.iter_mut().find(|(n, _)| *n == name) {
+                    existing.extend(deps);
+                } else {
+                    out.push((name, deps.iter().collect()));
+                }
+            }
+        }
+        out
+    }
+
     pub fn resolve(&self, resolve_mode: ResolveMode) -> Resolution<'_> {
-        let lockfile = match resolve_mode {
-            ResolveMode::Default => &self.lockfile,
-            ResolveMode::FullUpgrade => &None,
+        self.resolve_for(
+            &self.databases,
+            &self.r_version,
+            &self.cache,
+            &self.builtin_packages,
+            self.lockfile.as_ref(),
+            resolve_mode,
+        )
+    }
+
+    /// Resolves the dependencies for one of the other resolution targets of the config
+    pub fn resolve_target<'a>(
+        &'a self,
+        target: &'a TargetContext,
+        resolve_mode: ResolveMode,
+    ) -> Resolution<'a> {
+        self.resolve_for(
+            &target.databases,
+            &target.target.r_version,
+            &target.cache,
+            &target.builtin_packages,
+            target.lockfile.as_ref(),
+            resolve_mode,
+        )
+    }
+
+    fn resolve_for<'a>(
+        &'a self,
+        databases: &'a [(RepositoryDatabase, bool)],
+        r_version: &'a Version,
+        cache: &'a DiskCache,
+        builtin_packages: &'a HashMap<String, Package>,
+        lockfile: Option<&'a Lockfile>,
+        resolve_mode: ResolveMode,
+    ) -> Resolution<'a> {
+        let resolver_lockfile = match resolve_mode {
+            ResolveMode::Default | ResolveMode::PartialUpgrade => lockfile,
+            ResolveMode::FullUpgrade => None,
+            ResolveMode::Frozen => return self.frozen_resolution(lockfile, cache),
         };
 
         let mut resolver = Resolver::new(
             &self.project_dir,
-            &self.databases,
+            databases,
             self.config.repositories().iter().map(|x| x.url()).collect(),
-            &self.r_version,
-            &self.builtin_packages,
-            lockfile.as_ref(),
+            r_version,
+            &cache.system_info,
+            builtin_packages,
+            resolver_lockfile,
             self.config.packages_env_vars(),
         );
 
         if self.show_progress_bar {
             resolver.show_progress_bar();
         }
+        resolver.set_resolution_strategy(self.resolution_strategy);
+        resolver.set_exclude_newer(self.exclude_newer.as_deref());
+        if resolve_mode == ResolveMode::PartialUpgrade {
+            resolver.set_upgrade_packages(&self.upgrade_packages);
+        }
 
         let mut resolution = resolver.resolve(
-            self.config.dependencies(),
+            &self.all_dependencies(),
             self.config.prefer_repositories_for(),
-            &self.cache,
+            self.config.exclude(),
+            self.config.overrides(),
+            self.config.patches(),
+            cache,
             &GitExecutor {},
-            &Http {},
+            &self.http,
         );
+        resolution.assign_groups(self.dependency_groups());
 
         // If upgrade mode and there is a lockfile, adjust from_lockfile flags
         // to indicate which resolved deps match what was in the lockfile
-        if resolve_mode == ResolveMode::FullUpgrade && self.lockfile.is_some() {
+        if resolve_mode != ResolveMode::Default
+            && let Some(lockfile) = lockfile
+        {
             resolution.found = resolution
                 .found
                 .into_iter()
                 .map(|mut dep| {
-                    dep.from_lockfile = self.lockfile.as_ref().unwrap().contains_resolved_dep(&dep);
+                    dep.from_lockfile = lockfile.contains_resolved_dep(&dep);
                     dep
                 })
                 .collect::<Vec<_>>();
@@ -428,6 +883,41 @@ impl Context {
 
         resolution
     }
+
+    /// The packages of the lockfile as they are, for `ResolveMode::Frozen`
+    fn frozen_resolution<'a>(
+        &'a self,
+        lockfile: Option<&'a Lockfile>,
+        cache: &'a DiskCache,
+    ) -> Resolution<'a> {
+        let packages_env_vars = self.config.packages_env_vars();
+        let mut resolution = Resolution::default();
+        for package in lockfile.into_iter().flat_map(|l| l.packages()) {
+            let installation_status =
+                cache.get_installation_status(&package.name, &package.version, &package.source);
+            let mut dep = ResolvedDependency::from_locked_package(package, installation_status);
+            dep.groups = package.groups.clone();
+            if let Some(vars) = packages_env_vars.get(&package.name) {
+                dep.env_vars = vars.iter().map(|(k, v)| (k.as_str(), v.as_str())).collect();
+            }
+            if let Source::Local { path, .. } = &package.source {
+                dep.local_resolved_path = fs::canonicalize(self.project_dir.join(path)).ok();
+            }
+            resolution.found.push(dep);
+        }
+        resolution
+    }
+}
+
+/// The builtin packages of a R version that is not installed, see
+/// `DiskCache::get_cached_builtin_packages` and `get_bundled_builtin_packages`
+fn find_builtin_packages(
+    cache: &DiskCache,
+    r_version: &Version,
+) -> Option<HashMap<String, Package>> {
+    cache
+        .get_cached_builtin_packages(r_version)
+        .or_else(|| get_bundled_builtin_packages(r_version).map(|b| b.packages))
 }
 
 /// Load package databases from repositories
@@ -435,6 +925,7 @@ impl Context {
 pub fn load_databases(
     repositories: &[Repository],
     cache: &DiskCache,
+    http: &Http,
 ) -> Result<Vec<(RepositoryDatabase, bool)>, Box<dyn Error + Send + Sync>> {
     #[cfg(feature = "cli")]
     let iter = repositories.par_iter();
@@ -443,7 +934,7 @@ pub fn load_databases(
 
     let results: Vec<Result<_, Box<dyn Error + Send + Sync>>> = iter
         .map(|r| {
-            let db = load_single_database(r, cache)?;
+            let db = load_single_database(r, cache, http)?;
             Ok((db, r.force_source))
         })
         .collect();
@@ -459,6 +950,7 @@ pub fn load_databases(
 fn load_single_database(
     r: &Repository,
     cache: &DiskCache,
+    http: &Http,
 ) -> Result<RepositoryDatabase, Box<dyn Error + Send + Sync>> {
     // 1. Generate path to add to URL to get the src PACKAGE and binary PACKAGE for current OS
     let (path, exists) = cache.get_package_db_entry(r.url());
@@ -479,17 +971,24 @@ fn load_single_database(
         if path.exists() {
             fs::remove_file(&path)?;
         }
-        log::debug!("Need to download R-Universe packages API for {}", r.url());
+        log::debug!(
+            "Need to download R-Universe packages API for {}",
+            http::redact_url(r.url())
+        );
         let mut db = RepositoryDatabase::new(r.url());
         let mut r_universe_api = Vec::new();
         let api_url = format!("{}/{RUNIVERSE_PACKAGES_API_PATH}", r.url())
             .parse::<Url>()
             .map_err(|e| format!("Invalid URL: {e}"))?;
 
-        let bytes_read = http::download(&api_url, &mut r_universe_api, Vec::new())?;
+        let bytes_read = http.get(&api_url, &mut r_universe_api, Vec::new())?;
 
         if bytes_read == 0 {
-            return Err(format!("File at {api_url} was not found").into());
+            return Err(format!(
+                "File at {} was not found",
+                http::redact_url(api_url.as_str())
+            )
+            .into());
         }
 
         db.parse_runiverse_api(&String::from_utf8_lossy(&r_universe_api));
@@ -502,21 +1001,26 @@ fn load_single_database(
         if path.exists() {
             fs::remove_file(&path)?;
         }
-        log::debug!("Need to download PACKAGES file for {}", r.url());
+        log::debug!(
+            "Need to download PACKAGES file for {}",
+            http::redact_url(r.url())
+        );
         let mut db = RepositoryDatabase::new(r.url());
         // download files, parse them and persist to disk
         let mut source_package = Vec::new();
-        let (source_url, binary_url) = get_package_file_urls(
-            &Url::parse(r.url()).map_err(|e| format!("Invalid URL: {e}"))?,
-            &cache.r_version,
-            &cache.system_info,
-        );
+        let repo_url = Url::parse(r.url()).map_err(|e| format!("Invalid URL: {e}"))?;
+        let (source_url, binary_url) =
+            get_package_file_urls(&repo_url, &cache.r_version, &cache.system_info);
 
-        let bytes_read = http::download(&source_url, &mut source_package, Vec::new())?;
+        let bytes_read = http.get(&source_url, &mut source_package, Vec::new())?;
 
         // We should ALWAYS have a PACKAGES file for source
         if bytes_read == 0 {
-            return Err(format!("File at {source_url} was not found").into());
+            return Err(format!(
+                "File at {} was not found",
+                http::redact_url(source_url.as_str())
+            )
+            .into());
         }
         // UNSAFE: we trust the PACKAGES data to be valid UTF-8
         db.parse_source(unsafe { std::str::from_utf8_unchecked(&source_package) });
@@ -525,8 +1029,11 @@ fn load_single_database(
         // we do not know for certain that the Some return of get_binary_path will be a valid url,
         // but we do know that if it returns None there is not a binary PACKAGES file
         if let Some(url) = binary_url {
-            log::debug!("checking for binary packages URL: {url}");
-            let bytes_read = http::download(&url, &mut binary_package, vec![]).unwrap_or(0);
+            log::debug!(
+                "checking for binary packages URL: {}",
+                http::redact_url(url.as_str())
+            );
+            let bytes_read = http.get(&url, &mut binary_package, vec![]).unwrap_or(0);
             // but sometimes we might not have a binary PACKAGES file and that's fine.
             // We only load binary if we found a file
             if bytes_read > 0 {
@@ -540,8 +1047,153 @@ fn load_single_database(
             log::debug!("No binary URL.")
         }
 
+        // Only CRAN-like repositories have an index of archived packages, it's fine not to find it
+        if let Some(archive_index) =
+            get_archive_index_url(&repo_url).and_then(|url| download_index(http, &url))
+        {
+            db.parse_archive(&archive_index);
+        }
+
+        // The current index is only used to know when the current versions were published
+        if let Some(current_index) =
+            get_current_index_url(&repo_url).and_then(|url| download_index(http, &url))
+        {
+            db.parse_current(&current_index);
+        }
+
         db.persist(&path)?;
         log::debug!("Saving packages db at {path:?}");
         Ok(db)
     }
 }
+
+/// Downloads one of the optional `src/contrib/Meta` indexes. Only a 404 means the repository
+/// doesn't have it: other errors are logged since the archived versions will be missing.
+fn download_index(http: &Http, url: &Url) -> Option<Vec<u8>> {
+    let mut content = Vec::new();
+    match http.get(url, &mut content, vec![]) {
+        Ok(bytes_read) if bytes_read > 0 => Some(content),
+        Ok(_) => None,
+        Err(e) if e.is_not_found() => {
+            log::debug!("No index found at {}", e.url);
+            None
+        }
+        Err(e) => {
+            log::warn!("{e}: {}, ignoring it.", e.source);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn write_config(dir: &Path, dependencies: &str) -> PathBuf {
+        let config_path = dir.join("rproject.toml");
+        fs::write(
+            &config_path,
+            format!(
+                "[project]\nname = \"test\"\nr_version = \"4.4\"\nrepositories = []\ndependencies = [{dependencies}]\n"
+            ),
+        )
+        .unwrap();
+        config_path
+    }
+
+    #[test]
+    fn frozen_resolution_uses_lockfile_only() {
+        let project = tempfile::tempdir().unwrap();
+        let cache = tempfile::tempdir().unwrap();
+        let lockfile = r#"version = 2
+r_version = "4.4"
+
+[[packages]]
+name = "cli"
+version = "3.6.3"
+source = { repository = "https://cran.r-project.org/" }
+force_source = false
+dependencies = []
+
+[[packages]]
+name = "R6"
+version = "2.5.1"
+source = { repository = "https://cran.r-project.org/" }
+force_source = false
+dependencies = []
+"#;
+        fs::write(project.path().join("rv.lock"), lockfile).unwrap();
+        // Neither of those can be resolved, the resolver would report them as failed
+        let config_path = write_config(
+            project.path(),
+            r#""unknown", { name = "local", path = "missing" }"#,
+        );
+        let mut context =
+            Context::new_with_cache_dir(&config_path, RCommandLookup::Skip, Some(cache.path()))
+                .unwrap();
+        context.load_frozen_lockfile().unwrap();
+
+        let resolution = context.resolve(ResolveMode::Frozen);
+        let lockfile = Lockfile::from_str(lockfile).unwrap();
+        assert!(resolution.failed.is_empty());
+        let resolved: Vec<_> = resolution
+            .found
+            .iter()
+            .map(|d| (d.name.to_string(), d.version.original.clone()))
+            .collect();
+        let locked: Vec<_> = lockfile
+            .packages()
+            .iter()
+            .map(|p| (p.name.clone(), p.version.clone()))
+            .collect();
+        assert_eq!(resolved, locked);
+    }
+
+    #[test]
+    fn removing_exclude_newer_ignores_lockfile() {
+        let project = tempfile::tempdir().unwrap();
+        let cache = tempfile::tempdir().unwrap();
+        let lockfile = r#"version = 2
+r_version = "4.4"
+exclude_newer = "2025-03-01"
+
+[[packages]]
+name = "R6"
+version = "2.5.1"
+source = { repository = "https://cran.r-project.org/" }
+force_source = false
+dependencies = []
+"#;
+        fs::write(project.path().join("rv.lock"), lockfile).unwrap();
+
+        let config_path = write_config(project.path(), r#""R6""#);
+        let context =
+            Context::new_with_cache_dir(&config_path, RCommandLookup::Skip, Some(cache.path()))
+                .unwrap();
+        assert_eq!(context.exclude_newer, None);
+        assert!(context.lockfile.is_none());
+
+        let config = fs::read_to_string(&config_path)
+            .unwrap()
+            .replace("[project]\n", "[project]\nexclude_newer = \"2025-03-01\"\n");
+        fs::write(&config_path, config).unwrap();
+        let context =
+            Context::new_with_cache_dir(&config_path, RCommandLookup::Skip, Some(cache.path()))
+                .unwrap();
+        assert_eq!(context.exclude_newer.as_deref(), Some("2025-03-01"));
+        assert!(context.lockfile.is_some());
+    }
+
+    #[test]
+    fn frozen_requires_lockfile() {
+        let project = tempfile::tempdir().unwrap();
+        let cache = tempfile::tempdir().unwrap();
+        let config_path = write_config(project.path(), r#""dplyr""#);
+        let mut context =
+            Context::new_with_cache_dir(&config_path, RCommandLookup::Skip, Some(cache.path()))
+                .unwrap();
+        let err = context.load_frozen_lockfile().unwrap_err().to_string();
+        assert!(err.contains("it is required to sync with `--frozen`"));
+    }
+}
@@ -39,7 +39,7 @@ pub(crate) fn copy_folder(
 
 /// Copy the whole content of a folder to another folder using parallel processing
 /// This is optimized for NFS scenarios where parallel I/O can improve performance
-/// Thread count can be configured via the RV_COPY_THREADS environment variable
+/// Thread count can be configured via the RV_COPY_THREADS environment variable or the global config
 #[cfg(feature = "cli")]
 fn copy_folder_parallel(
     from: impl AsRef<Path>,
@@ -51,6 +51,7 @@ fn copy_folder_parallel(
     let num_threads = std::env::var(COPY_THREADS_ENV_VAR_NAME)
         .ok()
         .and_then(|x| x.parse::<usize>().ok())
+        .or_else(|| crate::global_config::global_config().copy_threads())
         .unwrap_or(default_num_threads);
 
     let pool = rayon::ThreadPoolBuilder::new()
@@ -0,0 +1,426 @@
+//! Machine-wide defaults for rv, read from a system-wide `/etc/rv/config.toml` and a user-level
+//! `~/.config/rv/config.toml`.
+//! The precedence is CLI > env vars > project > user > system > built-in defaults.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use etcetera::BaseStrategy;
+use serde::{Deserialize, Serialize};
+
+use crate::config::HttpUrl;
+use crate::consts::{
+    CACHE_DIR_ENV_VAR_NAME, COPY_THREADS_ENV_VAR_NAME, LINK_MODE_ENV_VAR_NAME,
+    NUM_CPUS_ENV_VAR_NAME, PACKAGE_TIMEOUT, PACKAGE_TIMEOUT_ENV_VAR_NAME, SYS_REQ_URL_ENV_VAR_NAME,
+};
+use crate::{Config, LinkMode, Repository};
+
+pub const GLOBAL_CONFIG_FILENAME: &str = "config.toml";
+
+/// The content of one of the global config files
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct GlobalConfigFile {
+    /// The repositories put in the config by `rv init`
+    repositories: Option<Vec<Repository>>,
+    /// One of copy, clone, hardlink or symlink
+    link_mode: Option<String>,
+    /// How many packages can be installed in parallel
+    num_workers: Option<usize>,
+    /// How many threads are used to copy packages on network filesystems
+    copy_threads: Option<usize>,
+    cache_dir: Option<PathBuf>,
+    /// How long the package databases are cached for, in seconds
+    packages_timeout: Option<u64>,
+    sys_req_url: Option<HttpUrl>,
+    /// Repository URL -> mirror URL. The files of the repository are downloaded from the mirror.
+    #[serde(default)]
+    mirrors: HashMap<String, HttpUrl>,
+}
+
+/// Where the effective value of a setting comes from
+#[derive(Debug, Clone, PartialEq)]
+pub enum SettingSource {
+    Default,
+    System(PathBuf),
+    User(PathBuf),
+    Project,
+    Env(&'static str),
+}
+
+impl fmt::Display for SettingSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Default => write!(f, "default"),
+            Self::System(p) => write!(f, "system config ({})", p.display()),
+            Self::User(p) => write!(f, "user config ({})", p.display()),
+            Self::Project => write!(f, "project config"),
+            Self::Env(name) => write!(f, "env var {name}"),
+        }
+    }
+}
+
+impl Serialize for SettingSource {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+/// The effective value of a setting, as displayed by `rv config show`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Setting {
+    pub name: &'static str,
+    pub value: String,
+    pub source: SettingSource,
+}
+
+/// The system and user config files merged, the user one taking precedence
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct GlobalConfig {
+    /// From lowest to highest precedence
+    layers: Vec<(SettingSource, GlobalConfigFile)>,
+}
+
+impl GlobalConfig {
+    /// Loads the system and user config files, if they exist
+    pub fn load() -> Result<Self, GlobalConfigError> {
+        Self::from_files(
+            system_config_path().as_deref(),
+            user_config_path().as_deref(),
+        )
+    }
+
+    pub fn from_files(
+        system: Option<&Path>,
+        user: Option<&Path>,
+    ) -> Result<Self, GlobalConfigError> {
+        let mut layers = Vec::new();
+        if let Some(path) = system.filter(|p| p.exists()) {
+            layers.push((SettingSource::System(path.to_path_buf()), read_file(path)?));
+        }
+        if let Some(path) = user.filter(|p| p.exists()) {
+            layers.push((SettingSource::User(path.to_path_buf()), read_file(path)?));
+        }
+        Ok(Self { layers })
+    }
+
+    /// The value of the highest precedence file setting it
+    fn lookup<'a, T>(
+        &'a self,
+        f: impl Fn(&'a GlobalConfigFile) -> Option<T>,
+    ) -> Option<(T, &'a SettingSource)> {
+        self.layers
+            .iter()
+            .rev()
+            .find_map(|(source, file)| f(file).map(|v| (v, source)))
+    }
+
+    pub fn repositories(&self) -> Option<&[Repository]> {
+        self.lookup(|f| f.repositories.as_deref()).map(|(v, _)| v)
+    }
+
+    pub fn link_mode(&self) -> Option<LinkMode> {
+        self.lookup(|f| f.link_mode.as_deref().and_then(LinkMode::from_name))
+            .map(|(v, _)| v)
+    }
+
+    pub fn num_workers(&self) -> Option<usize> {
+        self.lookup(|f| f.num_workers).map(|(v, _)| v)
+    }
+
+    pub fn copy_threads(&self) -> Option<usize> {
+        self.lookup(|f| f.copy_threads).map(|(v, _)| v)
+    }
+
+    pub fn cache_dir(&self) -> Option<&Path> {
+        self.lookup(|f| f.cache_dir.as_deref()).map(|(v, _)| v)
+    }
+
+    pub fn packages_timeout(&self) -> Option<u64> {
+        self.lookup(|f| f.packages_timeout).map(|(v, _)| v)
+    }
+
+    pub fn sys_req_url(&self) -> Option<&str> {
+        self.lookup(|f| f.sys_req_url.as_ref().map(|u| u.as_str()))
+            .map(|(v, _)| v)
+    }
+
+    /// All the mirrors, a mirror in the user config replacing the system one for the same repository
+    pub(crate) fn mirrors(&self) -> HashMap<&str, &HttpUrl> {
+        let mut out = HashMap::new();
+        for (_, file) in &self.layers {
+            for (url, mirror) in &file.mirrors {
+                out.insert(url.trim_end_matches('/'), mirror);
+            }
+        }
+        out
+    }
+
+    /// The effective value of every setting and where it comes from.
+    /// Only the env vars and the repositories of the project can override the config files.
+    pub fn settings(&self, project: Option<&Config>) -> Vec<Setting> {
+        fn setting(
+            name: &'static str,
+            env: Option<&'static str>,
+            from_file: Option<(String, &SettingSource)>,
+            default: impl FnOnce() -> String,
+        ) -> Setting {
+            if let Some(env) = env
+                && let Ok(value) = std::env::var(env)
+            {
+                return Setting {
+                    name,
+                    value,
+                    source: SettingSource::Env(env),
+                };
+            }
+            match from_file {
+                Some((value, source)) => Setting {
+                    name,
+                    value,
+                    source: source.clone(),
+                },
+                None => Setting {
+                    name,
+                    value: default(),
+                    source: SettingSource::Default,
+                },
+            }
+        }
+
+        let format_repositories = |repos: &[Repository]| {
+            repos
+                .iter()
+                .map(|r| format!("{} ({})", r.alias, r.url()))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+
+        let repositories = if let Some(config) = project {
+            Setting {
+                name: "repositories",
+                value: format_repositories(config.repositories()),
+                source: SettingSource::Project,
+            }
+        } else {
+            setting(
+                "repositories",
+                None,
+                self.lookup(|f| f.repositories.as_deref().map(format_repositories)),
+                || format_repositories(&default_repositories()),
+            )
+        };
+
+        let mut mirrors: Vec<_> = self
+            .mirrors()
+            .into_iter()
+            .map(|(url, mirror)| format!("{url} -> {}", mirror.as_str()))
+            .collect();
+        mirrors.sort();
+        let mirrors_source = self
+            .layers
+            .iter()
+            .rev()
+            .find(|(_, f)| !f.mirrors.is_empty())
+            .map(|(source, _)| source.clone())
+            .unwrap_or(SettingSource::Default);
+
+        vec![
+            repositories,
+            setting(
+                "link_mode",
+                Some(LINK_MODE_ENV_VAR_NAME),
+                self.lookup(|f| f.link_mode.clone()),
+                || LinkMode::default().name().to_string(),
+            ),
+            setting(
+                "num_workers",
+                Some(NUM_CPUS_ENV_VAR_NAME),
+                self.lookup(|f| f.num_workers.map(|v| v.to_string())),
+                || num_cpus::get().to_string(),
+            ),
+            setting(
+                "copy_threads",
+                Some(COPY_THREADS_ENV_VAR_NAME),
+                self.lookup(|f| f.copy_threads.map(|v| v.to_string())),
+                || "between 4 and 16 depending on the package size".to_string(),
+            ),
+            setting(
+                "cache_dir",
+                Some(CACHE_DIR_ENV_VAR_NAME),
+                self.lookup(|f| f.cache_dir.as_ref().map(|p| p.display().to_string())),
+                || {
+                    default_cache_dir()
+                        .map(|p| p.display().to_string())
+                        .unwrap_or_default()
+                },
+            ),
+            setting(
+                "packages_timeout",
+                Some(PACKAGE_TIMEOUT_ENV_VAR_NAME),
+                self.lookup(|f| f.packages_timeout.map(|v| v.to_string())),
+                || PACKAGE_TIMEOUT.to_string(),
+            ),
+            setting(
+                "sys_req_url",
+                Some(SYS_REQ_URL_ENV_VAR_NAME),
+                self.lookup(|f| f.sys_req_url.as_ref().map(|u| u.as_str().to_string())),
+                || crate::system_req::SYSTEM_REQ_API_URL.to_string(),
+            ),
+            Setting {
+                name: "mirrors",
+                value: mirrors.join(", "),
+                source: mirrors_source,
+            },
+        ]
+    }
+}
+
+fn read_file(path: &Path) -> Result<GlobalConfigFile, GlobalConfigError> {
+    let content = std::fs::read_to_string(path).map_err(|e| GlobalConfigError {
+        path: path.into(),
+        source: GlobalConfigErrorKind::Io(e),
+    })?;
+    let file: GlobalConfigFile = toml::from_str(&content).map_err(|e| GlobalConfigError {
+        path: path.into(),
+        source: GlobalConfigErrorKind::Parse(e),
+    })?;
+
+    if let Some(mode) = &file.link_mode
+        && LinkMode::from_name(mode).is_none()
+    {
+        return Err(GlobalConfigError {
+            path: path.into(),
+            source: GlobalConfigErrorKind::InvalidConfig(format!(
+                "Unknown link mode `{mode}`, it needs to be one of copy, clone, hardlink or symlink"
+            )),
+        });
+    }
+
+    Ok(file)
+}
+
+/// `/etc/rv/config.toml`, there is no system-wide config on Windows
+pub fn system_config_path() -> Option<PathBuf> {
+    if cfg!(windows) {
+        None
+    } else {
+        Some(Path::new("/etc/rv").join(GLOBAL_CONFIG_FILENAME))
+    }
+}
+
+/// `~/.config/rv/config.toml` on Linux and macOS
+pub fn user_config_path() -> Option<PathBuf> {
+    etcetera::base_strategy::choose_base_strategy()
+        .ok()
+        .map(|dirs| dirs.config_dir().join("rv").join(GLOBAL_CONFIG_FILENAME))
+}
+
+pub(crate) fn default_cache_dir() -> Option<PathBuf> {
+    etcetera::base_strategy::choose_base_strategy()
+        .ok()
+        .map(|dirs| dirs.cache_dir().join("rv"))
+}
+
+/// The repositories used by `rv init` if none are set in the global config
+pub fn default_repositories() -> Vec<Repository> {
+    vec![
+        Repository::new(
+            "posit".to_string(),
+            url::Url::parse("https://packagemanager.posit.co/cran/latest").unwrap(),
+            false,
+        ),
+        Repository::new(
+            "bioc".to_string(),
+            url::Url::parse("https://packagemanager.posit.co/bioc/latest").unwrap(),
+            false,
+        ),
+    ]
+}
+
+/// The global config, loaded once. An invalid file is ignored with a warning here, `rv config show`
+/// is where the error is reported.
+pub fn global_config() -> &'static GlobalConfig {
+    static GLOBAL_CONFIG: OnceLock<GlobalConfig> = OnceLock::new();
+    GLOBAL_CONFIG.get_or_init(|| match GlobalConfig::load() {
+        Ok(c) => c,
+        Err(e) => {
+            log::warn!("{e}: {}, ignoring it.", e.source);
+            GlobalConfig::default()
+        }
+    })
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("Failed to load global config at `{path}`")]
+#[non_exhaustive]
+pub struct GlobalConfigError {
+    pub path: Box<Path>,
+    pub source: GlobalConfigErrorKind,
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error(transparent)]
+pub enum GlobalConfigErrorKind {
+    Io(#[from] std::io::Error),
+    Parse(#[from] toml::de::Error),
+    #[error("Invalid config: {0}")]
+    InvalidConfig(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn user_config_overrides_system_config() {
+        let system = Path::new("src/tests/global_config/system.toml");
+        let user = Path::new("src/tests/global_config/user.toml");
+        let config = GlobalConfig::from_files(Some(system), Some(user)).unwrap();
+
+        assert_eq!(config.link_mode(), Some(LinkMode::Copy));
+        assert_eq!(config.num_workers(), Some(2));
+        assert_eq!(config.packages_timeout(), Some(600));
+        assert_eq!(config.repositories().unwrap()[0].alias, "internal");
+        assert_eq!(
+            config.mirrors()["https://packagemanager.posit.co/cran/latest"].as_str(),
+            "https://mirror.example.com/cran/latest"
+        );
+
+        let settings = config.settings(None);
+        let num_workers = settings.iter().find(|s| s.name == "num_workers").unwrap();
+        assert_eq!(num_workers.source, SettingSource::User(user.to_path_buf()));
+        let timeout = settings
+            .iter()
+            .find(|s| s.name == "packages_timeout")
+            .unwrap();
+        assert_eq!(timeout.source, SettingSource::System(system.to_path_buf()));
+        let sys_req_url = settings.iter().find(|s| s.name == "sys_req_url").unwrap();
+        assert_eq!(sys_req_url.source, SettingSource::Default);
+    }
+
+    #[test]
+    fn missing_files_are_ignored() {
+        let config =
+            GlobalConfig::from_files(Some(Path::new("src/tests/global_config/nope.toml")), None)
+                .unwrap();
+        assert_eq!(config, GlobalConfig::default());
+    }
+
+    #[test]
+    fn errors_on_invalid_link_mode() {
+        let res = GlobalConfig::from_files(
+            None,
+            Some(Path::new("src/tests/global_config/invalid_link_mode.toml")),
+        );
+        assert!(matches!(
+            res.unwrap_err().source,
+            GlobalConfigErrorKind::InvalidConfig(_)
+        ));
+    }
+}
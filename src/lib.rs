@@ -2,47 +2,65 @@ mod activate;
 mod add;
 mod cache;
 mod cancellation;
+mod checksum;
 #[cfg(feature = "cli")]
 pub mod cli;
 pub mod conda;
 mod config;
+mod config_validation;
 mod configure;
 mod context;
 mod format;
 mod fs;
 mod git;
+mod global_config;
 mod http;
 mod library;
 mod lockfile;
+mod netrc;
 mod package;
 mod project_summary;
 mod r_cmd;
 mod renv;
 mod repository;
+mod repository_archive;
 mod repository_urls;
 mod resolver;
 mod r_parser;
+mod snapshot;
 mod sync;
 mod system_info;
+mod template;
 pub mod system_req;
 mod utils;
+mod workspace;
 
 pub mod consts;
 
 pub use activate::{activate, deactivate};
-pub use add::{AddOptions, add_packages, read_and_verify_config};
+pub use add::{
+    AddOptions, add_packages, parse_package_spec, read_and_verify_config, remove_packages,
+    set_conda_env,
+};
 pub use cache::{CacheInfo, DiskCache, PackagePaths, utils::hash_string};
 pub use cancellation::Cancellation;
 pub use conda::{CondaEnvironment, CondaError, CondaManager, CondaTool};
-pub use config::{Config, ConfigDependency, Repository};
+pub use config::{
+    Config, ConfigDependency, DependencyMarkers, Repository, RepositoryAuth, ResolutionTarget,
+};
+pub use config_validation::{ConfigDiagnostic, config_json_schema, validate_config};
 pub use configure::{
     ConfigureRepositoryResponse, RepositoryAction, RepositoryMatcher, RepositoryOperation,
     RepositoryPositioning, RepositoryUpdates, execute_repository_action,
 };
-pub use context::{Context, RCommandLookup, ResolveMode};
+pub use context::{Context, RCommandLookup, ResolveMode, TargetContext};
 pub use format::format_document;
 pub use fs::is_network_fs;
 pub use git::{CommandExecutor, GitExecutor, GitRepository};
+pub use global_config::{
+    GlobalConfig, GlobalConfigError, GlobalConfigErrorKind, Setting, SettingSource,
+    default_repositories, global_config, system_config_path, user_config_path,
+};
 pub use http::{Http, HttpDownload};
 pub use library::Library;
 pub use lockfile::{Lockfile, Source};
@@ -53,6 +71,12 @@ pub use renv::RenvLock;
 pub use repository::RepositoryDatabase;
 pub use r_parser::{extract_packages_from_directory, extract_packages_from_r_code, extract_packages_from_r_file, find_r_files};
 pub use repository_urls::{get_package_file_urls, get_tarball_urls};
-pub use resolver::{Resolution, ResolvedDependency, Resolver, UnresolvedDependency};
+pub use resolver::{
+    Conflict, ConflictRequirement, Resolution, ResolutionStrategy, ResolvedDependency, Resolver,
+    UnresolvedDependency,
+};
+pub use snapshot::set_snapshot;
 pub use sync::{BuildPlan, BuildStep, LinkMode, SyncChange, SyncHandler};
 pub use system_info::{OsType, SystemInfo};
+pub use template::UrlTemplates;
+pub use workspace::{WorkspaceError, WorkspaceErrorKind, WorkspaceMember, load_workspace_members};
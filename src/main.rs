@@ -1,26 +1,28 @@
 use clap::{Parser, Subcommand};
 use std::collections::{HashMap, HashSet};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use anyhow::Result;
 use fs_err::{read_to_string, write};
 use serde_json::json;
-use toml;
-use url::Url;
 
 use anyhow::anyhow;
 use rv::cli::{
-    Context, OutputFormat, RCommandLookup, ResolveMode, SyncHelper, find_r_repositories, init,
-    init_structure, migrate_renv, resolve_dependencies, tree,
+    Context, GroupSelection, MemberSelection, OutputFormat, RCommandLookup, ResolveMode,
+    SyncHelper, find_r_repositories, init, init_structure, lock_diff, migrate_renv, read_lockfile,
+    resolve_dependencies, resolve_other_targets, tree, update_lockfile, why,
 };
 use rv::extract_packages_from_directory;
 use rv::system_req::{SysDep, SysInstallationStatus};
-use rv::{AddOptions, CondaManager, Repository, RepositoryOperation as LibRepositoryOperation};
+use rv::{AddOptions, CondaManager, RepositoryOperation as LibRepositoryOperation};
 use rv::{
-    CacheInfo, Config, ProjectSummary, RCmd, RCommandLine, RepositoryAction, RepositoryMatcher,
-    RepositoryPositioning, RepositoryUpdates, Version, activate, add_packages, deactivate,
-    execute_repository_action, read_and_verify_config, system_req,
+    CacheInfo, Config, GlobalConfig, Lockfile, ProjectSummary, RCmd, RCommandLine,
+    RepositoryAction, RepositoryMatcher, RepositoryPositioning, RepositoryUpdates,
+    ResolutionStrategy, Version, activate, add_packages, deactivate, default_repositories,
+    execute_repository_action, global_config, parse_package_spec, read_and_verify_config,
+    remove_packages, set_conda_env, set_snapshot, system_req,
 };
+use rv::{config_json_schema, validate_config};
 
 /// rv, the R package manager
 #[derive(Parser)]
@@ -81,9 +83,29 @@ pub enum Command {
         #[clap(long)]
         /// Auto-create conda environment if it doesn't exist
         auto_create: bool,
+        /// How to pick the versions, defaults to the strategy the lockfile was resolved with
+        #[clap(long)]
+        resolution: Option<ResolutionStrategy>,
+        /// Ignore the package versions published after that day, eg `2025-03-01`
+        #[clap(long)]
+        exclude_newer: Option<String>,
+        /// Error instead of syncing if the lockfile is not up to date with the config
+        #[clap(long, conflicts_with = "frozen")]
+        locked: bool,
+        /// Install exactly what the lockfile lists, without checking it against the config
+        #[clap(
+            long,
+            conflicts_with_all = ["resolution", "exclude_newer", "members", "all_members"]
+        )]
+        frozen: bool,
+        #[clap(flatten)]
+        groups: GroupSelection,
+        #[clap(flatten)]
+        members: MemberSelection,
     },
     /// Add packages to the project and sync
     Add {
+        /// Packages to add, optionally with a version requirement (eg `dplyr@1.1.4` or `dplyr>=1.1`)
         #[clap(value_parser, required = true)]
         packages: Vec<String>,
         #[clap(long)]
@@ -95,19 +117,97 @@ pub enum Command {
         #[clap(flatten)]
         add_options: AddOptions,
     },
+    /// Remove packages from the project and sync
+    Remove {
+        #[clap(value_parser, required = true)]
+        packages: Vec<String>,
+        #[clap(long)]
+        /// Do not make any changes, only report what would happen if those packages were removed
+        dry_run: bool,
+        #[clap(long)]
+        /// Remove packages from config file, but do not sync. No effect if --dry-run is used
+        no_sync: bool,
+    },
     /// Upgrade packages to the latest versions available
     Upgrade {
+        /// Only upgrade those packages, keeping the others at their locked version
+        #[clap(value_parser)]
+        packages: Vec<String>,
+        #[clap(long, requires = "packages")]
+        /// Also upgrade the packages the given ones depend on
+        with_deps: bool,
         #[clap(long)]
         dry_run: bool,
+        #[clap(long)]
+        /// Move the Posit Package Manager repositories to that snapshot date, eg `2024-06-01`,
+        /// before upgrading
+        snapshot: Option<String>,
+    },
+    /// Resolve the dependencies and write the lockfile, without installing anything or needing R
+    #[clap(args_conflicts_with_subcommands = true)]
+    Lock {
+        #[clap(subcommand)]
+        subcommand: Option<LockSubcommand>,
+        /// Ignore the lockfile and resolve the latest versions available
+        #[clap(short, long)]
+        upgrade: bool,
+        /// Specify a R version different from the one in the config.
+        /// The command will not error even if this R version is not found
+        #[clap(long)]
+        r_version: Option<Version>,
+        /// How to pick the versions, defaults to the strategy the lockfile was resolved with
+        #[clap(long)]
+        resolution: Option<ResolutionStrategy>,
+        /// Ignore the package versions published after that day, eg `2025-03-01`
+        #[clap(long)]
+        exclude_newer: Option<String>,
     },
     /// Dry run of what sync would do
     Plan {
         #[clap(short, long)]
         upgrade: bool,
+        /// Only upgrade that package, keeping the others at their locked version. Can be repeated
+        #[clap(
+            long = "upgrade-package",
+            value_name = "PACKAGE",
+            conflicts_with = "upgrade"
+        )]
+        upgrade_packages: Vec<String>,
+        #[clap(long, requires = "upgrade_packages")]
+        /// Also upgrade the packages the ones given with `--upgrade-package` depend on
+        with_deps: bool,
         /// Specify a R version different from the one in the config.
         /// The command will not error even if this R version is not found
         #[clap(long)]
         r_version: Option<Version>,
+        /// How to pick the versions, defaults to the strategy the lockfile was resolved with
+        #[clap(long)]
+        resolution: Option<ResolutionStrategy>,
+        /// Ignore the package versions published after that day, eg `2025-03-01`
+        #[clap(long)]
+        exclude_newer: Option<String>,
+        /// Exit with an error if the lockfile is not up to date with the config
+        #[clap(
+            long,
+            conflicts_with_all = ["upgrade", "upgrade_packages", "r_version", "frozen"]
+        )]
+        locked: bool,
+        /// Plan from exactly what the lockfile lists, without checking it against the config
+        #[clap(
+            long,
+            conflicts_with_all = [
+                "upgrade",
+                "upgrade_packages",
+                "r_version",
+                "resolution",
+                "exclude_newer",
+                "members",
+                "all_members"
+            ]
+        )]
+        frozen: bool,
+        #[clap(flatten)]
+        members: MemberSelection,
     },
     /// Provide a summary about the project status
     Summary {
@@ -121,6 +221,11 @@ pub enum Command {
         #[command(subcommand)]
         subcommand: ConfigureSubcommand,
     },
+    /// Inspect the global rv configuration
+    Config {
+        #[command(subcommand)]
+        subcommand: ConfigSubcommand,
+    },
     /// Formats the toml configuration file while preserving comments and spacing
     Fmt {
         // add a --check flag to check formatting without changing the file
@@ -143,6 +248,20 @@ pub enum Command {
         /// Specify a R version different from the one in the config.
         /// The command will not error even if this R version is not found
         r_version: Option<Version>,
+        #[clap(flatten)]
+        members: MemberSelection,
+    },
+    /// Explains why a package is part of the project: every path from the dependencies of the
+    /// rproject.toml down to that package, with the kind and version requirement of each edge
+    Why {
+        /// The package to explain
+        package: String,
+        #[clap(long)]
+        /// Specify a R version different from the one in the config.
+        /// The command will not error even if this R version is not found
+        r_version: Option<Version>,
+        #[clap(flatten)]
+        members: MemberSelection,
     },
     /// Returns the path for the library for the current project/system in UNIX format, even
     /// on Windows.
@@ -197,6 +316,24 @@ pub enum Command {
     Deactivate,
 }
 
+#[derive(Debug, Subcommand)]
+pub enum LockSubcommand {
+    /// Shows the packages added, removed or changed between two versions of the lockfile
+    Diff {
+        /// The lockfile to compare from, either a path or a git revision. Defaults to `HEAD`
+        old: Option<String>,
+        /// The lockfile to compare to, either a path or a git revision. Defaults to the lockfile
+        /// of the project
+        new: Option<String>,
+        /// Output a markdown table, eg to comment on a pull request
+        #[clap(long, conflicts_with = "json")]
+        markdown: bool,
+    },
+    /// Merges both sides of the git merge conflicts of the lockfile and resolves the
+    /// dependencies again to get a valid lockfile
+    ResolveConflicts,
+}
+
 #[derive(Debug, Subcommand)]
 pub enum ConfigureSubcommand {
     /// Configure project repositories
@@ -206,6 +343,17 @@ pub enum ConfigureSubcommand {
     },
 }
 
+#[derive(Debug, Subcommand)]
+pub enum ConfigSubcommand {
+    /// Shows the effective settings and where each of them comes from: the default value,
+    /// the system or user config files, the project config or an env var
+    Show,
+    /// Checks the config file and reports every problem with its location in the file
+    Validate,
+    /// Prints the JSON Schema of the config file, to get completion and validation in editors
+    Schema,
+}
+
 #[derive(Debug, Subcommand)]
 pub enum RepositoryOperation {
     /// Add a new repository
@@ -405,22 +553,14 @@ fn try_main() -> Result<()> {
                 }
             };
 
-            // Default to PPM repositories unless --no-repositories is specified
+            // Default to the repositories of the global config or PPM unless --no-repositories
+            // is specified
             let repositories = if no_repositories {
                 Vec::new()
+            } else if let Some(repos) = global_config().repositories() {
+                repos.to_vec()
             } else {
-                vec![
-                    Repository::new(
-                        "posit".to_string(),
-                        Url::parse("https://packagemanager.posit.co/cran/latest").unwrap(),
-                        false,
-                    ),
-                    Repository::new(
-                        "bioc".to_string(),
-                        Url::parse("https://packagemanager.posit.co/bioc/latest").unwrap(),
-                        false,
-                    ),
-                ]
+                default_repositories()
             };
 
             // Extract packages from R files
@@ -527,6 +667,12 @@ fn try_main() -> Result<()> {
             save_install_logs_in,
             condaenv,
             auto_create,
+            resolution,
+            exclude_newer,
+            locked,
+            frozen,
+            groups,
+            members,
         } => {
             // Simplified: Handle conda environment for sync
             if let Some(ref env_name) = condaenv {
@@ -559,25 +705,34 @@ fn try_main() -> Result<()> {
                 }
 
                 // Update config file to persist conda_env setting
-                let mut config = rv::Config::from_file(&cli.config_file).map_err(|e| {
-                    eprintln!("Error loading config: {}", e);
-                    anyhow!("Failed to load config: {e}")
-                })?;
-                config.set_conda_env(env_name.clone());
-                let config_content = toml::to_string_pretty(&config)
-                    .map_err(|e| anyhow!("Failed to serialize config: {e}"))?;
-                fs_err::write(&cli.config_file, config_content)
+                let mut doc = read_and_verify_config(&cli.config_file)?;
+                set_conda_env(&mut doc, env_name);
+                write(&cli.config_file, doc.to_string())
                     .map_err(|e| anyhow!("Failed to write config: {e}"))?;
             }
 
             // Create Context (will automatically use conda_env from config if set)
             let mut context = Context::new(&cli.config_file, RCommandLookup::Strict)
                 .map_err(|e| anyhow!("{e}"))?;
+            groups.validate(&context)?;
+            members.select(&context)?;
+            if let Some(strategy) = resolution {
+                context.set_resolution_strategy(strategy);
+            }
+            if let Some(date) = exclude_newer {
+                context
+                    .set_exclude_newer(date)
+                    .map_err(|e| anyhow!("{e}"))?;
+            }
 
             if !log_enabled {
                 context.show_progress_bar();
             }
-            let resolve_mode = ResolveMode::Default;
+            let resolve_mode = if frozen {
+                ResolveMode::Frozen
+            } else {
+                ResolveMode::Default
+            };
             context
                 .load_for_resolve_mode(resolve_mode)
                 .map_err(|e| anyhow!("{e}"))?;
@@ -585,6 +740,9 @@ fn try_main() -> Result<()> {
                 dry_run: false,
                 output_format: Some(output_format),
                 save_install_logs_in,
+                groups,
+                members,
+                locked,
                 ..Default::default()
             }
             .run(&context, resolve_mode)?;
@@ -620,6 +778,20 @@ fn try_main() -> Result<()> {
                 }
             }
 
+            // Validate the version requirements, which can only be used for repository packages
+            for package in &packages {
+                let (_, version) = parse_package_spec(package).map_err(|e| anyhow!("{e}"))?;
+                if version.is_some()
+                    && (add_options.git.is_some()
+                        || add_options.path.is_some()
+                        || add_options.url.is_some())
+                {
+                    return Err(anyhow::anyhow!(
+                        "A version requirement cannot be used with --git, --path or --url"
+                    ));
+                }
+            }
+
             // Load config to verify structure is valid
             let mut doc = read_and_verify_config(&cli.config_file)?;
             let config = Config::from_file(&cli.config_file)?;
@@ -677,14 +849,46 @@ fn try_main() -> Result<()> {
             }
             .run(&context, resolve_mode)?;
         }
-        Command::Upgrade { dry_run } => {
+        Command::Remove {
+            packages,
+            dry_run,
+            no_sync,
+        } => {
+            let mut doc = read_and_verify_config(&cli.config_file)?;
+            let missing = remove_packages(&mut doc, &packages);
+            if !missing.is_empty() {
+                return Err(anyhow::anyhow!(
+                    "Package(s) not found in the project dependencies: {}",
+                    missing.join(", ")
+                ));
+            }
+            // write the update if not dry run
+            if !dry_run {
+                write(&cli.config_file, doc.to_string())?;
+            }
+            // if no sync, exit early
+            if no_sync {
+                if output_format.is_json() {
+                    // Nothing to output for JSON format here since we didn't sync anything
+                    println!("{{}}");
+                } else {
+                    println!("Packages successfully removed");
+                }
+                return Ok(());
+            }
             let mut context = Context::new(&cli.config_file, RCommandLookup::Strict)
                 .map_err(|e| anyhow!("{e}"))?;
 
             if !log_enabled {
                 context.show_progress_bar();
             }
-            let resolve_mode = ResolveMode::FullUpgrade;
+            // if dry run, the config won't have been edited to reflect the removal so must be updated
+            if dry_run {
+                context.config = doc.to_string().parse::<Config>()?;
+            }
+            // Packages no longer reachable from the config are dropped from the lockfile when it
+            // is rebuilt from the resolution and unlinked from the library by the sync
+            let resolve_mode = ResolveMode::Default;
             context
                 .load_for_resolve_mode(resolve_mode)
                 .map_err(|e| anyhow!("{e}"))?;
@@ -695,14 +899,214 @@ fn try_main() -> Result<()> {
             }
             .run(&context, resolve_mode)?;
         }
-        Command::Plan { upgrade, r_version } => {
+        Command::Upgrade {
+            packages,
+            with_deps,
+            dry_run,
+            snapshot,
+        } => {
+            let doc = if let Some(date) = &snapshot {
+                let mut doc = read_and_verify_config(&cli.config_file)?;
+                set_snapshot(&mut doc, date).map_err(|e| anyhow!("{e}"))?;
+                if !dry_run {
+                    write(&cli.config_file, doc.to_string())?;
+                }
+                Some(doc)
+            } else {
+                None
+            };
+            let mut context = Context::new(&cli.config_file, RCommandLookup::Strict)
+                .map_err(|e| anyhow!("{e}"))?;
+
+            if !log_enabled {
+                context.show_progress_bar();
+            }
+            // if dry run, the config won't have been edited to the new snapshot so must be updated
+            if dry_run && let Some(doc) = doc {
+                context.config = doc.to_string().parse::<Config>()?;
+            }
+            let resolve_mode = if packages.is_empty() {
+                ResolveMode::FullUpgrade
+            } else {
+                context
+                    .set_upgrade_packages(&packages, with_deps)
+                    .map_err(|e| anyhow!("{e}"))?;
+                ResolveMode::PartialUpgrade
+            };
+            context
+                .load_for_resolve_mode(resolve_mode)
+                .map_err(|e| anyhow!("{e}"))?;
+            SyncHelper {
+                dry_run,
+                output_format: Some(output_format),
+                ..Default::default()
+            }
+            .run(&context, resolve_mode)?;
+        }
+        Command::Lock {
+            subcommand: Some(LockSubcommand::Diff { old, new, markdown }),
+            ..
+        } => {
+            let config = Config::from_file(&cli.config_file).map_err(|e| anyhow!("{e}"))?;
+            let lockfile_name = config.lockfile_name();
+            let project_dir = cli
+                .config_file
+                .parent()
+                .filter(|p| !p.as_os_str().is_empty())
+                .unwrap_or(Path::new("."));
+            let old = read_lockfile(project_dir, lockfile_name, old.as_deref().unwrap_or("HEAD"))
+                .map_err(|e| anyhow!("{e}"))?;
+            let new = match new {
+                Some(n) => read_lockfile(project_dir, lockfile_name, &n),
+                None => {
+                    let path = project_dir.join(lockfile_name);
+                    if path.exists() {
+                        read_lockfile(project_dir, lockfile_name, &path.to_string_lossy())
+                    } else {
+                        Ok(None)
+                    }
+                }
+            }
+            .map_err(|e| anyhow!("{e}"))?;
+
+            let diff = lock_diff(old.as_ref(), new.as_ref());
+            if output_format.is_json() {
+                println!("{}", serde_json::to_string_pretty(&diff)?);
+            } else if markdown {
+                print!("{}", diff.to_markdown());
+            } else {
+                diff.print();
+            }
+        }
+        Command::Lock {
+            subcommand: subcommand @ (None | Some(LockSubcommand::ResolveConflicts)),
+            upgrade,
+            r_version,
+            resolution,
+            exclude_newer,
+        } => {
+            let mut merged = None;
+            if subcommand.is_some() {
+                // The resolution below starts from the merged lockfile and fixes what doesn't
+                // match the config anymore. It is only written once that succeeded.
+                let config = Config::from_file(&cli.config_file).map_err(|e| anyhow!("{e}"))?;
+                let lockfile_path = cli
+                    .config_file
+                    .parent()
+                    .unwrap()
+                    .join(config.lockfile_name());
+                if config.use_lockfile() && lockfile_path.exists() {
+                    let content = read_to_string(&lockfile_path)?;
+                    merged = Lockfile::from_conflicted(&content)?;
+                }
+            }
+            let merged_conflicts = merged.is_some();
+            let resolve_mode = if upgrade {
+                ResolveMode::FullUpgrade
+            } else {
+                ResolveMode::Default
+            };
+            let lookup = r_version
+                .map(RCommandLookup::Soft)
+                .unwrap_or(RCommandLookup::Skip);
+            let mut context = match merged {
+                Some(lockfile) => Context::new_with_lockfile(&cli.config_file, lookup, lockfile),
+                None => Context::new(&cli.config_file, lookup),
+            }
+            .map_err(|e| anyhow!("{e}"))?;
+            if !context.config.use_lockfile() {
+                return Err(anyhow!(
+                    "The lockfile is disabled by `use_lockfile = false` in the config file"
+                ));
+            }
+            if let Some(strategy) = resolution {
+                context.set_resolution_strategy(strategy);
+            }
+            if let Some(date) = exclude_newer {
+                context
+                    .set_exclude_newer(date)
+                    .map_err(|e| anyhow!("{e}"))?;
+            }
+
+            if !log_enabled {
+                context.show_progress_bar();
+            }
+            match resolve_mode {
+                ResolveMode::Default => context.load_databases_if_needed(),
+                ResolveMode::FullUpgrade | ResolveMode::PartialUpgrade => context.load_databases(),
+                ResolveMode::Frozen => unreachable!("The lockfile is always resolved"),
+            }
+            .map_err(|e| anyhow!("{e}"))?;
+            let resolution = resolve_dependencies(&context, resolve_mode, true, &output_format);
+            let other_resolutions =
+                resolve_other_targets(&context, resolve_mode, true, &output_format);
+            let mut updated = update_lockfile(&context, &resolution, &other_resolutions)?;
+            if merged_conflicts && !updated {
+                // The merged lockfile already matches the config but the file on disk still has
+                // the conflict markers
+                if let Some(lockfile) = context
+                    .universal_lockfile
+                    .as_ref()
+                    .or(context.lockfile.as_ref())
+                {
+                    lockfile.save(context.lockfile_path())?;
+                }
+                updated = true;
+            }
+
+            let lockfile_name = context.config.lockfile_name();
+            if output_format.is_json() {
+                println!(
+                    "{}",
+                    serde_json::to_string_pretty(&json!({
+                        "lockfile": lockfile_name,
+                        "updated": updated,
+                        "packages": resolution.found.len(),
+                    }))
+                    .expect("valid json")
+                );
+            } else if updated {
+                println!("Lockfile {lockfile_name} updated");
+            } else {
+                println!("Lockfile {lockfile_name} is up to date");
+            }
+        }
+        Command::Plan {
+            upgrade,
+            upgrade_packages,
+            with_deps,
+            r_version,
+            resolution,
+            exclude_newer,
+            locked,
+            frozen,
+            members,
+        } => {
             let upgrade = if upgrade || r_version.is_some() {
                 ResolveMode::FullUpgrade
+            } else if !upgrade_packages.is_empty() {
+                ResolveMode::PartialUpgrade
+            } else if frozen {
+                ResolveMode::Frozen
             } else {
                 ResolveMode::Default
             };
             let mut context =
                 Context::new(&cli.config_file, r_version.into()).map_err(|e| anyhow!("{e}"))?;
+            members.select(&context)?;
+            if let Some(strategy) = resolution {
+                context.set_resolution_strategy(strategy);
+            }
+            if let Some(date) = exclude_newer {
+                context
+                    .set_exclude_newer(date)
+                    .map_err(|e| anyhow!("{e}"))?;
+            }
+            if upgrade == ResolveMode::PartialUpgrade {
+                context
+                    .set_upgrade_packages(&upgrade_packages, with_deps)
+                    .map_err(|e| anyhow!("{e}"))?;
+            }
 
             if !log_enabled {
                 context.show_progress_bar();
@@ -713,6 +1117,8 @@ fn try_main() -> Result<()> {
             SyncHelper {
                 dry_run: true,
                 output_format: Some(output_format),
+                members,
+                locked,
                 ..Default::default()
             }
             .run(&context, upgrade)?;
@@ -721,11 +1127,12 @@ fn try_main() -> Result<()> {
             let mut context =
                 Context::new(&cli.config_file, r_version.into()).map_err(|e| anyhow!("{e}"))?;
             context.load_databases().map_err(|e| anyhow!("{e}"))?;
-            context.load_system_requirements();
+            context.load_system_requirements()?;
             if !log_enabled {
                 context.show_progress_bar();
             }
-            let resolved = resolve_dependencies(&context, ResolveMode::Default, true).found;
+            let resolved =
+                resolve_dependencies(&context, ResolveMode::Default, true, &output_format).found;
             let project_sys_deps: HashSet<_> = resolved
                 .iter()
                 .flat_map(|x| context.system_dependencies.get(x.name.as_ref()))
@@ -781,20 +1188,38 @@ fn try_main() -> Result<()> {
             depth,
             hide_system_deps,
             r_version,
+            members,
         } => {
             let mut context =
                 Context::new(&cli.config_file, r_version.into()).map_err(|e| anyhow!("{e}"))?;
+            members.select(&context)?;
             context
                 .load_databases_if_needed()
                 .map_err(|e| anyhow!("{e}"))?;
             if !hide_system_deps {
-                context.load_system_requirements();
+                context.load_system_requirements()?;
             }
             if !log_enabled {
                 context.show_progress_bar();
             }
-            let resolution = resolve_dependencies(&context, ResolveMode::Default, false);
-            let tree = tree(&context, &resolution.found, &resolution.failed);
+            let resolution =
+                resolve_dependencies(&context, ResolveMode::Default, false, &output_format);
+            let top_level_deps: Vec<_> = members
+                .select(&context)?
+                .into_iter()
+                .flat_map(|member| {
+                    member
+                        .map(|m| &m.config)
+                        .unwrap_or(&context.config)
+                        .dependencies()
+                })
+                .collect();
+            let tree = tree(
+                &context,
+                &top_level_deps,
+                &resolution.found,
+                &resolution.failed,
+            );
 
             if output_format.is_json() {
                 println!(
@@ -805,6 +1230,50 @@ fn try_main() -> Result<()> {
                 tree.print(depth, !hide_system_deps);
             }
         }
+        Command::Why {
+            package,
+            r_version,
+            members,
+        } => {
+            let mut context =
+                Context::new(&cli.config_file, r_version.into()).map_err(|e| anyhow!("{e}"))?;
+            // Cloned since loading the databases below needs the context to be mutable
+            let selected: Vec<_> = members
+                .select(&context)?
+                .into_iter()
+                .map(|m| m.cloned())
+                .collect();
+            context
+                .load_databases_if_needed()
+                .map_err(|e| anyhow!("{e}"))?;
+            if !log_enabled {
+                context.show_progress_bar();
+            }
+            let resolution =
+                resolve_dependencies(&context, ResolveMode::Default, false, &output_format);
+            let top_level_deps: Vec<_> = selected
+                .iter()
+                .flat_map(|member| {
+                    member
+                        .as_ref()
+                        .map(|m| &m.config)
+                        .unwrap_or(&context.config)
+                        .dependencies()
+                })
+                .collect();
+            let Some(why) = why(&context, &top_level_deps, &resolution.found, &package) else {
+                return Err(anyhow!("`{package}` is not a dependency of the project"));
+            };
+
+            if output_format.is_json() {
+                println!(
+                    "{}",
+                    serde_json::to_string_pretty(&why).expect("valid json")
+                );
+            } else {
+                why.print();
+            }
+        }
         Command::Library => {
             let context =
                 Context::new(&cli.config_file, RCommandLookup::Skip).map_err(|e| anyhow!("{e}"))?;
@@ -838,7 +1307,7 @@ fn try_main() -> Result<()> {
             let info = CacheInfo::new(
                 &context.config,
                 &context.cache,
-                resolve_dependencies(&context, ResolveMode::Default, true).found,
+                resolve_dependencies(&context, ResolveMode::Default, true, &output_format).found,
             );
             if output_format.is_json() {
                 let mut info_json = serde_json::to_value(&info).expect("valid json");
@@ -920,9 +1389,10 @@ fn try_main() -> Result<()> {
             context
                 .load_databases_if_needed()
                 .map_err(|e| anyhow!("{e}"))?;
-            context.load_system_requirements();
+            context.load_system_requirements()?;
 
-            let resolved = resolve_dependencies(&context, ResolveMode::Default, false).found;
+            let resolved =
+                resolve_dependencies(&context, ResolveMode::Default, false, &output_format).found;
             let project_sys_deps: HashSet<_> = resolved
                 .iter()
                 .flat_map(|x| context.system_dependencies.get(x.name.as_ref()))
@@ -986,6 +1456,60 @@ fn try_main() -> Result<()> {
             }
         }
 
+        Command::Config { subcommand } => match subcommand {
+            ConfigSubcommand::Show => {
+                let global_config = GlobalConfig::load()?;
+                let project = if cli.config_file.exists() {
+                    Some(Config::from_file(&cli.config_file)?)
+                } else {
+                    None
+                };
+                let settings = global_config.settings(project.as_ref());
+
+                if output_format.is_json() {
+                    println!(
+                        "{}",
+                        serde_json::to_string_pretty(&settings).expect("valid json")
+                    );
+                } else {
+                    for setting in settings {
+                        println!("{} = {} ({})", setting.name, setting.value, setting.source);
+                    }
+                }
+            }
+            ConfigSubcommand::Validate => {
+                let content = read_to_string(&cli.config_file)?;
+                let diagnostics = validate_config(&content);
+                if output_format.is_json() {
+                    let diagnostics: Vec<_> =
+                        diagnostics.iter().map(|d| d.to_json(&content)).collect();
+                    println!(
+                        "{}",
+                        serde_json::to_string_pretty(&diagnostics).expect("valid json")
+                    );
+                } else if diagnostics.is_empty() {
+                    println!("{} is valid", cli.config_file.display());
+                } else {
+                    for d in &diagnostics {
+                        eprintln!("{}", d.render(&cli.config_file, &content));
+                    }
+                    eprintln!(
+                        "Found {} problem(s) in {}",
+                        diagnostics.len(),
+                        cli.config_file.display()
+                    );
+                }
+                if !diagnostics.is_empty() {
+                    ::std::process::exit(1);
+                }
+            }
+            ConfigSubcommand::Schema => {
+                println!(
+                    "{}",
+                    serde_json::to_string_pretty(&config_json_schema()).expect("valid json")
+                );
+            }
+        },
         Command::Configure { subcommand } => {
             match subcommand {
                 ConfigureSubcommand::Repository { operation } => {
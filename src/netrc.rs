@@ -0,0 +1,101 @@
+//! Minimal support for .netrc files, to find the credentials of a repository host.
+//! The file used is the one in the `NETRC` env var or `~/.netrc` (`~/_netrc` on Windows).
+
+use std::path::PathBuf;
+
+const NETRC_ENV_VAR_NAME: &str = "NETRC";
+
+fn netrc_path() -> Option<PathBuf> {
+    if let Ok(p) = std::env::var(NETRC_ENV_VAR_NAME) {
+        return Some(PathBuf::from(p));
+    }
+    let filename = if cfg!(windows) { "_netrc" } else { ".netrc" };
+    etcetera::home_dir().ok().map(|h| h.join(filename))
+}
+
+/// The login and password for that host in the .netrc file, if there is one
+pub(crate) fn netrc_credentials(host: &str) -> Option<(String, String)> {
+    let path = netrc_path()?;
+    let content = fs_err::read_to_string(path).ok()?;
+    find_credentials(&content, host)
+}
+
+/// Finds the credentials of the `machine` matching the host, or the `default` ones
+fn find_credentials(content: &str, host: &str) -> Option<(String, String)> {
+    let mut tokens = content
+        .lines()
+        // Comments are not part of the format but are commonly supported
+        .filter(|l| !l.trim_start().starts_with('#'))
+        .flat_map(|l| l.split_whitespace());
+
+    // Whether the current machine entry is the one we want
+    let mut in_entry = false;
+    let mut login = None;
+    let mut password = None;
+
+    while let Some(token) = tokens.next() {
+        match token {
+            "machine" | "default" => {
+                if in_entry {
+                    break;
+                }
+                in_entry = if token == "machine" {
+                    tokens.next() == Some(host)
+                } else {
+                    true
+                };
+            }
+            "login" => {
+                let value = tokens.next();
+                if in_entry {
+                    login = value;
+                }
+            }
+            "password" => {
+                let value = tokens.next();
+                if in_entry {
+                    password = value;
+                }
+            }
+            "account" | "macdef" => {
+                tokens.next();
+            }
+            _ => (),
+        }
+    }
+
+    Some((login?.to_string(), password?.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_find_credentials() {
+        let content = r#"
+# internal mirror
+machine ppm.example.com login alice password s3cret
+machine other.example.com
+    login bob
+    password hunter2
+default login anonymous password guest
+"#;
+        assert_eq!(
+            find_credentials(content, "ppm.example.com"),
+            Some(("alice".to_string(), "s3cret".to_string()))
+        );
+        assert_eq!(
+            find_credentials(content, "other.example.com"),
+            Some(("bob".to_string(), "hunter2".to_string()))
+        );
+        assert_eq!(
+            find_credentials(content, "cran.r-project.org"),
+            Some(("anonymous".to_string(), "guest".to_string()))
+        );
+        assert_eq!(
+            find_credentials("machine a.com login a password b", "b.com"),
+            None
+        );
+    }
+}
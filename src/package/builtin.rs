@@ -1,6 +1,6 @@
-use crate::RCmd;
 use crate::consts::{BASE_PACKAGES, RECOMMENDED_PACKAGES};
-use crate::package::{Package, parse_description_file_in_folder};
+use crate::package::{Package, parse_description_file, parse_description_file_in_folder};
+use crate::{RCmd, Version};
 use fs_err as fs;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
@@ -59,3 +59,123 @@ pub fn get_builtin_versions_from_library(r_cmd: &impl RCmd) -> std::io::Result<B
         }
     }
 }
+
+/// The dependencies of the recommended packages that are not base packages, the same for all the
+/// R versions below
+const RECOMMENDED_DEPENDENCIES: &[(&str, &str)] = &[
+    ("class", "Imports: MASS"),
+    ("Matrix", "Imports: lattice"),
+    ("mgcv", "Depends: nlme (>= 3.1-64)\nImports: Matrix"),
+    ("nlme", "Imports: lattice"),
+    ("survival", "Imports: Matrix"),
+];
+
+/// The versions of the recommended packages shipped with the first release of each R minor
+/// version, in the order of `RECOMMENDED_PACKAGES`
+const BUNDLED_RECOMMENDED_VERSIONS: &[(&str, [&str; 15])] = &[
+    (
+        "4.0",
+        [
+            "1.3-24", "7.3-16", "2.1.0", "0.2-16", "0.8-78", "2.23-16", "0.20-41", "7.3-51.5",
+            "1.2-18", "1.8-31", "3.1-147", "7.3-13", "4.1-15", "7.3-11", "3.1-11",
+        ],
+    ),
+    (
+        "4.1",
+        [
+            "1.3-28", "7.3-19", "2.1.2", "0.2-18", "0.8-81", "2.23-20", "0.20-44", "7.3-54",
+            "1.3-3", "1.8-35", "3.1-152", "7.3-16", "4.1-15", "7.3-14", "3.2-11",
+        ],
+    ),
+    (
+        "4.2",
+        [
+            "1.3-28", "7.3-20", "2.1.3", "0.2-18", "0.8-82", "2.23-20", "0.20-45", "7.3-56",
+            "1.4-1", "1.8-40", "3.1-157", "7.3-17", "4.1.16", "7.3-15", "3.3-1",
+        ],
+    ),
+    (
+        "4.3",
+        [
+            "1.3-28.1", "7.3-21", "2.1.4", "0.2-19", "0.8-84", "2.23-20", "0.21-8", "7.3-58.4",
+            "1.5-4", "1.8-42", "3.1-162", "7.3-18", "4.1.19", "7.3-16", "3.5-5",
+        ],
+    ),
+    (
+        "4.4",
+        [
+            "1.3-30", "7.3-22", "2.1.6", "0.2-20", "0.8-86", "2.23-22", "0.22-6", "7.3-60.2",
+            "1.7-0", "1.9-1", "3.1-164", "7.3-19", "4.1.23", "7.3-17", "3.5-8",
+        ],
+    ),
+    (
+        "4.5",
+        [
+            "1.3-31", "7.3-23", "2.1.8.1", "0.2-20", "0.8-90", "2.23-26", "0.22-6", "7.3-65",
+            "1.7-3", "1.9-1", "3.1-168", "7.3-20", "4.1.24", "7.3-18", "3.8-3",
+        ],
+    ),
+];
+
+/// The builtin packages of that R version from a table bundled in rv, used when R is not
+/// installed. The versions of the recommended packages are the ones of the first release of
+/// that R minor version so they might be older than the ones of a patch release.
+pub fn get_bundled_builtin_packages(r_version: &Version) -> Option<BuiltinPackages> {
+    let [major, minor] = r_version.major_minor();
+    let minor_version = format!("{major}.{minor}");
+    let (_, versions) = BUNDLED_RECOMMENDED_VERSIONS
+        .iter()
+        .find(|(v, _)| *v == minor_version)?;
+
+    let recommended = RECOMMENDED_PACKAGES
+        .iter()
+        .zip(versions)
+        .map(|(name, version)| {
+            let dependencies = RECOMMENDED_DEPENDENCIES
+                .iter()
+                .find(|(n, _)| n == name)
+                .map(|(_, deps)| *deps)
+                .unwrap_or_default();
+            (*name, *version, dependencies)
+        });
+    let base = BASE_PACKAGES
+        .iter()
+        .map(|name| (*name, r_version.original.as_str(), ""));
+
+    let mut builtins = BuiltinPackages::default();
+    for (name, version, dependencies) in base.chain(recommended) {
+        let description = format!("Package: {name}\nVersion: {version}\n{dependencies}\n");
+        let package = parse_description_file(&description).expect("valid bundled DESCRIPTION");
+        builtins.packages.insert(name.to_string(), package);
+    }
+    Some(builtins)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn can_get_bundled_builtin_packages() {
+        let builtins = get_bundled_builtin_packages(&Version::from_str("4.4.1").unwrap()).unwrap();
+        assert_eq!(
+            builtins.packages.len(),
+            BASE_PACKAGES.len() + RECOMMENDED_PACKAGES.len()
+        );
+        let mgcv = &builtins.packages["mgcv"];
+        assert_eq!(mgcv.version.original, "1.9-1");
+        let deps: Vec<_> = mgcv
+            .dependencies_to_install(false)
+            .direct
+            .iter()
+            .map(|d| (d.name(), d.version_requirement().map(|r| r.to_string())))
+            .collect();
+        assert_eq!(
+            deps,
+            vec![("nlme", Some("(>= 3.1-64)".to_string())), ("Matrix", None)]
+        );
+        assert_eq!(builtins.packages["stats"].version.original, "4.4.1");
+        assert!(get_bundled_builtin_packages(&Version::from_str("3.6").unwrap()).is_none());
+    }
+}
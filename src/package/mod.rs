@@ -11,7 +11,9 @@ mod remotes;
 mod version;
 
 use crate::{consts::BASE_PACKAGES, git::url::GitUrl};
-pub use builtin::{BuiltinPackages, get_builtin_versions_from_library};
+pub use builtin::{
+    BuiltinPackages, get_builtin_versions_from_library, get_bundled_builtin_packages,
+};
 pub use description::{parse_description_file, parse_description_file_in_folder, parse_version};
 pub use parser::parse_package_file;
 pub use remotes::PackageRemote;
@@ -100,6 +102,10 @@ pub struct Package {
     // The built field only exists when a package is a binary
     // https://rstudio.github.io/r-manuals/r-ints/Package-Structure.html
     pub(crate) built: Option<String>,
+    /// The day the package was published, as `YYYY-MM-DD`, if the PACKAGES file or DESCRIPTION
+    /// lists it
+    #[serde(default)]
+    pub(crate) published: Option<String>,
 }
 
 #[derive(Debug, Default, PartialEq, Clone)]
@@ -2,6 +2,7 @@
 
 use crate::package::remotes::parse_remote;
 use crate::package::{Dependency, Package};
+use crate::snapshot::is_valid_snapshot_date;
 use crate::{Version, VersionRequirement};
 use regex::Regex;
 use std::collections::HashMap;
@@ -9,9 +10,17 @@ use std::str::FromStr;
 use std::sync::LazyLock;
 
 static PACKAGE_KEY_VAL_RE: LazyLock<Regex> =
-    LazyLock::new(|| Regex::new(r"(?m)^(?P<key>\w+):(?P<value>.*(?:\n\s+.*)*)").unwrap());
+    LazyLock::new(|| Regex::new(r"(?m)^(?P<key>[\w/]+):(?P<value>.*(?:\n\s+.*)*)").unwrap());
 static ANY_SPACE_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\s+").unwrap());
 
+/// The day part of a timestamp like `2023-11-17 16:50:02 UTC`
+fn parse_day(value: &str) -> Option<String> {
+    value
+        .get(..10)
+        .filter(|day| is_valid_snapshot_date(day))
+        .map(|day| day.to_string())
+}
+
 fn parse_dependencies(content: &str) -> Vec<Dependency> {
     let mut res = Vec::new();
 
@@ -96,6 +105,13 @@ pub fn parse_package_file(content: &str) -> HashMap<String, Vec<Package>> {
                     }
                 }
                 "Built" => package.built = Some(value.to_string()),
+                // The publication date is only set by CRAN, `Packaged` is the closest we have otherwise
+                "Date/Publication" => package.published = parse_day(value),
+                "Packaged" => {
+                    if package.published.is_none() {
+                        package.published = parse_day(value);
+                    }
+                }
                 // Posit uses that, maybe we can parse it?
                 "SystemRequirements" => continue,
                 _ => continue,
@@ -215,6 +231,30 @@ NeedsCompilation: no
         assert_eq!(packages.len(), 1);
     }
 
+    #[test]
+    fn can_parse_publication_date() {
+        let content = r#"
+Package: dplyr
+Version: 1.1.4
+Packaged: 2023-11-16 21:48:56 UTC; hadley
+Date/Publication: 2023-11-17 16:50:02 UTC
+
+Package: gsm
+Version: 2.2.0
+Packaged: 2024-10-30 18:44:31 UTC; runner
+
+Package: cli
+Version: 3.6.3
+    "#;
+        let packages = parse_package_file(content);
+        assert_eq!(
+            packages["dplyr"][0].published.as_deref(),
+            Some("2023-11-17")
+        );
+        assert_eq!(packages["gsm"][0].published.as_deref(), Some("2024-10-30"));
+        assert_eq!(packages["cli"][0].published, None);
+    }
+
     #[test]
     fn works_on_gsm() {
         let mut content =
@@ -37,7 +37,7 @@ impl FromStr for Operator {
             "<" => Ok(Self::Lower),
             ">=" => Ok(Self::GreaterOrEqual),
             "<=" => Ok(Self::LowerOrEqual),
-            _ => todo!("Handle error: {s}"),
+            _ => Err(()),
         }
     }
 }
@@ -182,6 +182,30 @@ impl VersionRequirement {
     pub fn new(version: Version, op: Operator) -> Self {
         Self { version, op }
     }
+
+    /// Parses a comma separated list of requirements as written in rproject.toml,
+    /// eg `>= 1.1.0, < 1.2` or `== 3.5.1`
+    pub fn parse_constraints(s: &str) -> Result<Vec<Self>, String> {
+        let mut out = Vec::new();
+
+        for constraint in s.split(',') {
+            let constraint = constraint.trim();
+            let version_start = constraint
+                .find(|c: char| !matches!(c, '<' | '>' | '='))
+                .unwrap_or(constraint.len());
+            let (op, version) = constraint.split_at(version_start);
+            if op.is_empty() {
+                return Err(format!("missing operator in '{constraint}'"));
+            }
+            let op = Operator::from_str(op)
+                .map_err(|_| format!("invalid operator '{op}' in '{constraint}'"))?;
+            let version = Version::from_str(version.trim())
+                .map_err(|e| format!("invalid version in '{constraint}': {e}"))?;
+            out.push(Self::new(version, op));
+        }
+
+        Ok(out)
+    }
 }
 
 impl FromStr for VersionRequirement {
@@ -297,6 +321,24 @@ mod tests {
         );
     }
 
+    #[test]
+    fn can_parse_version_constraints() {
+        let reqs = VersionRequirement::parse_constraints(">= 1.1.0, < 1.2").unwrap();
+        assert_eq!(
+            reqs.iter().map(|r| r.to_string()).collect::<Vec<_>>(),
+            vec!["(>= 1.1.0)", "(< 1.2)"]
+        );
+        let reqs = VersionRequirement::parse_constraints("==3.5.1").unwrap();
+        assert_eq!(reqs[0].to_string(), "(== 3.5.1)");
+
+        for input in ["1.0.0", "=> 1.0", ">= ", ">= 1.0,", "~= 1.0"] {
+            assert!(
+                VersionRequirement::parse_constraints(input).is_err(),
+                "{input} should fail"
+            );
+        }
+    }
+
     #[test]
     fn can_compare_versions() {
         assert!(Version::from_str("1.0").unwrap() == Version::from_str("1.0.0").unwrap());
@@ -7,6 +7,7 @@ use std::{
 use serde::Serialize;
 
 use crate::fs::is_network_fs;
+use crate::http::redact_url;
 use crate::sync::LinkMode;
 use crate::{
     Context, DiskCache, Library, Lockfile, Repository, RepositoryDatabase, ResolvedDependency,
@@ -29,7 +30,7 @@ pub struct ProjectSummary<'a> {
     cache_root: &'a PathBuf,
     network_fs: bool,
     link_mode: &'static str,
-    remote_info: RemoteInfo<'a>,
+    remote_info: RemoteInfo,
     sys_deps: Vec<SysDep>,
     max_workers: usize,
 }
@@ -134,17 +135,18 @@ impl fmt::Display for ProjectSummary<'_> {
 }
 
 #[derive(Debug, Clone, Serialize)]
-struct RepoInfo<'a> {
-    url: &'a str,
+struct RepoInfo {
+    /// Without the credentials it might contain
+    url: String,
     binary_count: usize,
     source_count: usize,
 }
 
 // TODO: Expand with git + url information
 #[derive(Debug, Clone, Serialize)]
-struct RemoteInfo<'a> {
+struct RemoteInfo {
     linux_distro_name: Option<LinuxBinaryDistroName>,
-    repositories: HashMap<String, RepoInfo<'a>>,
+    repositories: HashMap<String, RepoInfo>,
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -156,10 +158,10 @@ enum LinuxBinaryDistroName {
     },
 }
 
-impl<'a> RemoteInfo<'a> {
+impl RemoteInfo {
     fn new(
-        repos: &'a [Repository],
-        repo_dbs: &'a [(RepositoryDatabase, bool)],
+        repos: &[Repository],
+        repo_dbs: &[(RepositoryDatabase, bool)],
         r_version: &[u32; 2],
         system_info: &SystemInfo,
     ) -> Self {
@@ -171,7 +173,7 @@ impl<'a> RemoteInfo<'a> {
             repositories.insert(
                 id,
                 RepoInfo {
-                    url: repo_db.url.as_str(),
+                    url: redact_url(&repo_db.url),
                     binary_count,
                     source_count,
                 },
@@ -197,7 +199,7 @@ impl<'a> RemoteInfo<'a> {
     }
 }
 
-impl fmt::Display for RemoteInfo<'_> {
+impl fmt::Display for RemoteInfo {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         if let Some(linux_distro_name) = &self.linux_distro_name {
             match linux_distro_name {
@@ -549,7 +551,7 @@ fn is_binary_package(
         .and_then(|(db, _)| {
             db.find_package(
                 &resolved_dep.name,
-                ver_req.as_ref(),
+                ver_req.as_ref().as_slice(),
                 r_version,
                 resolved_dep.force_source,
             )
@@ -568,9 +570,9 @@ fn get_dep_id(dep: &ResolvedDependency, repos: &[Repository]) -> String {
     match &dep.source {
         Source::Repository { repository } => get_repository_alias(repository.as_str(), repos),
         Source::RUniverse { repository, .. } => get_repository_alias(repository.as_str(), repos),
-        Source::Git { git, .. } => git.to_string(),
+        Source::Git { git, .. } => redact_url(git.url()),
         Source::Local { path, .. } => path.to_string_lossy().to_string(),
-        Source::Url { url, .. } => url.to_string(),
+        Source::Url { url, .. } => redact_url(url.as_str()),
         Source::Builtin { .. } => "builtin".to_string(),
     }
 }
@@ -580,5 +582,5 @@ fn get_repository_alias(r: &str, repos: &[Repository]) -> String {
         .iter()
         .find(|repo| repo.url() == r)
         .map(|repo| repo.alias.to_string())
-        .unwrap_or(r.to_string())
+        .unwrap_or_else(|| redact_url(r))
 }
@@ -232,7 +232,7 @@ fn resolve_repository<'a>(
         && repo_db
             .find_package(
                 &pkg_info.package,
-                Some(&version_requirement),
+                &[&version_requirement],
                 r_version,
                 **force_source,
             )
@@ -241,6 +241,18 @@ fn resolve_repository<'a>(
         return Ok(Source::Repository(repo));
     };
 
+    // The exact version might only be in the archive of one of the repositories, in which case
+    // we will resolve it from there
+    let archived_in = repo_pairs
+        .iter()
+        .find(|(_, repo_db, _)| {
+            repo_db
+                .find_archived_versions(&pkg_info.package, &[&version_requirement])
+                .next()
+                .is_some()
+        })
+        .map(|(repo, _, _)| *repo);
+
     // if a repository is not found in its specified repository, look in the rest of the repositories
     // sacrificing one additional iteration step of re-looking up in preferred repository for less complexity
     if let Some((found_pkg, repo)) =
@@ -248,12 +260,14 @@ fn resolve_repository<'a>(
             .into_iter()
             .find_map(|(repo, repo_db, force_source)| {
                 let (pkg, _) =
-                    repo_db.find_package(&pkg_info.package, None, r_version, *force_source)?;
+                    repo_db.find_package(&pkg_info.package, &[], r_version, *force_source)?;
                 Some((pkg, repo))
             })
     {
         if found_pkg.version == pkg_info.version {
             Ok(Source::Repository(repo))
+        } else if let Some(repo) = archived_in {
+            Ok(Source::Repository(repo))
         } else {
             Err(format!(
                 "Package version ({}) not found in repositories. Found version {} in {}",
@@ -261,6 +275,8 @@ fn resolve_repository<'a>(
             )
             .into())
         }
+    } else if let Some(repo) = archived_in {
+        Ok(Source::Repository(repo))
     } else {
         Err("Package not found in repositories".into())
     }
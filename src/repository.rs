@@ -6,6 +6,7 @@ use crate::consts::RECOMMENDED_PACKAGES;
 use crate::git::url::GitUrl;
 use crate::package::{Dependency, Package, PackageType, deserialize_version, parse_package_file};
 use crate::package::{Version, VersionRequirement, parse_remote};
+use crate::repository_archive::{TarballIndex, parse_archive_index, parse_current_index};
 
 #[derive(Debug, Default, PartialEq, Clone, Serialize, Deserialize)]
 pub struct RepositoryDatabase {
@@ -16,6 +17,24 @@ pub struct RepositoryDatabase {
     // for both binary and source
     // But each major.minor R version will get different binary package database
     pub(crate) binary_packages: HashMap<[u32; 2], HashMap<String, Vec<Package>>>,
+    /// The versions of each package only available in the `Archive` folder of the repository,
+    /// sorted from oldest to newest. We only know their version, not their dependencies.
+    #[serde(default)]
+    pub(crate) archived_packages: HashMap<String, Vec<Version>>,
+    /// The day each version was published, as `YYYY-MM-DD`, from the `mtime` of the tarballs
+    /// in the archive and current indices. Used when the PACKAGES file doesn't have it.
+    #[serde(default)]
+    pub(crate) publication_days: HashMap<String, Vec<(Version, String)>>,
+}
+
+/// How to pick a version among the ones satisfying the requirements
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub(crate) struct VersionSelection<'a> {
+    /// Pick the lowest version rather than the highest one
+    pub lowest: bool,
+    /// Ignore the versions published after that day, as `YYYY-MM-DD`.
+    /// Versions we don't know the publication day of are kept.
+    pub exclude_newer: Option<&'a str>,
 }
 
 impl RepositoryDatabase {
@@ -48,6 +67,59 @@ impl RepositoryDatabase {
         self.binary_packages.insert(r_version, packages);
     }
 
+    /// Parses the `src/contrib/Meta/archive.rds` file of the repository.
+    /// An invalid file is only logged: we can still resolve from the current packages.
+    pub fn parse_archive(&mut self, content: &[u8]) {
+        match parse_archive_index(content) {
+            Ok(archived) => {
+                self.archived_packages = archived
+                    .iter()
+                    .map(|(name, versions)| {
+                        (
+                            name.clone(),
+                            versions.iter().map(|(v, _)| v.clone()).collect(),
+                        )
+                    })
+                    .collect();
+                self.add_publication_days(archived);
+            }
+            Err(e) => log::warn!("Failed to parse the archive index of {}: {e}", self.url),
+        }
+    }
+
+    /// Parses the `src/contrib/Meta/current.rds` file of the repository, only used to know when
+    /// the current versions were published.
+    pub fn parse_current(&mut self, content: &[u8]) {
+        match parse_current_index(content) {
+            Ok(current) => self.add_publication_days(current),
+            Err(e) => log::warn!("Failed to parse the current index of {}: {e}", self.url),
+        }
+    }
+
+    fn add_publication_days(&mut self, index: TarballIndex) {
+        for (name, versions) in index {
+            let days = self.publication_days.entry(name).or_default();
+            days.extend(versions.into_iter().filter_map(|(v, d)| Some((v, d?))));
+            days.sort_by(|a, b| a.0.cmp(&b.0));
+        }
+    }
+
+    /// The day that version of the package was published, if we know it
+    pub(crate) fn published_on(&self, name: &str, version: &Version) -> Option<&str> {
+        self.publication_days
+            .get(name)?
+            .iter()
+            .find(|(v, _)| v == version)
+            .map(|(_, d)| d.as_str())
+    }
+
+    fn published<'a>(&'a self, package: &'a Package) -> Option<&'a str> {
+        package
+            .published
+            .as_deref()
+            .or_else(|| self.published_on(&package.name, &package.version))
+    }
+
     pub fn parse_runiverse_api(&mut self, content: &str) {
         self.source_packages = parse_runiverse_api_file(content)
             .into_iter()
@@ -59,30 +131,57 @@ impl RepositoryDatabase {
     pub(crate) fn find_package<'a>(
         &'a self,
         name: &str,
-        version_requirement: Option<&VersionRequirement>,
+        version_requirements: &[&VersionRequirement],
+        r_version: &Version,
+        force_source: bool,
+    ) -> Option<(&'a Package, PackageType)> {
+        self.find_package_with(
+            name,
+            version_requirements,
+            r_version,
+            force_source,
+            VersionSelection::default(),
+        )
+    }
+
+    /// Same as `find_package` but picks the version according to `selection`. When picking the
+    /// lowest version, the binary is still preferred unless a lower version is only available
+    /// as source.
+    pub(crate) fn find_package_with<'a>(
+        &'a self,
+        name: &str,
+        version_requirements: &[&VersionRequirement],
         r_version: &Version,
         force_source: bool,
+        selection: VersionSelection,
     ) -> Option<(&'a Package, PackageType)> {
+        let VersionSelection {
+            lowest,
+            exclude_newer,
+        } = selection;
         let find_package = |db: &'a HashMap<String, Vec<Package>>| -> Option<&'a Package> {
             // If we find multiple packages matching the requirement, we grab the one with the
             // highest R requirement matching the provided R version.
             // The list of packages is in the same order as in the PACKAGE file so we start
             // from the end since latter entries have priority
             db.get(name).and_then(|packages| {
+                let candidates = packages.iter().rev().filter(|p| {
+                    p.works_with_r_version(r_version)
+                        && exclude_newer
+                            .is_none_or(|cutoff| self.published(p).is_none_or(|day| day <= cutoff))
+                        && version_requirements
+                            .iter()
+                            .all(|req| req.is_satisfied(&p.version))
+                });
+                if lowest {
+                    // `min_by` keeps the first one found for a given version
+                    return candidates.min_by(|a, b| a.version.cmp(&b.version));
+                }
+
                 let mut max_r_version = None;
                 let mut found = None;
 
-                for p in packages.iter().rev() {
-                    if !p.works_with_r_version(r_version) {
-                        continue;
-                    }
-
-                    if let Some(req) = version_requirement
-                        && !req.is_satisfied(&p.version)
-                    {
-                        continue;
-                    }
-
+                for p in candidates {
                     match (max_r_version, p.r_requirement.as_ref()) {
                         (Some(_), None) => (),
                         (None, Some(v)) => {
@@ -103,14 +202,81 @@ impl RepositoryDatabase {
             })
         };
 
+        let source = || find_package(&self.source_packages).map(|p| (p, PackageType::Source));
         if !force_source
             && let Some(db) = self.binary_packages.get(&r_version.major_minor())
             && let Some(package) = find_package(db)
         {
+            if lowest && let Some(lower) = source().filter(|(p, _)| p.version < package.version) {
+                return Some(lower);
+            }
             return Some((package, PackageType::Binary));
         }
 
-        find_package(&self.source_packages).map(|p| (p, PackageType::Source))
+        source()
+    }
+
+    /// The MD5 of the source tarball of that exact version, as listed in the PACKAGES file
+    pub(crate) fn source_md5sum(&self, name: &str, version: &Version) -> Option<&str> {
+        self.source_packages
+            .get(name)?
+            .iter()
+            .find(|p| &p.version == version && !p.md5_sum.is_empty())
+            .map(|p| p.md5_sum.as_str())
+    }
+
+    /// The MD5 of the binary tarball of that exact version for that R version, as listed in the
+    /// PACKAGES file of the binaries
+    pub(crate) fn binary_md5sum(
+        &self,
+        name: &str,
+        version: &Version,
+        r_version: &[u32; 2],
+    ) -> Option<&str> {
+        self.binary_packages
+            .get(r_version)?
+            .get(name)?
+            .iter()
+            .find(|p| &p.version == version && !p.md5_sum.is_empty())
+            .map(|p| p.md5_sum.as_str())
+    }
+
+    /// The archived versions of a package matching all the requirements, newest first.
+    /// Versions still listed in the current PACKAGES file are skipped.
+    pub(crate) fn find_archived_versions<'a>(
+        &'a self,
+        name: &'a str,
+        version_requirements: &'a [&VersionRequirement],
+    ) -> impl Iterator<Item = &'a Version> {
+        let current = self.source_packages.get(name);
+        self.archived_packages
+            .get(name)
+            .into_iter()
+            .flat_map(|versions| versions.iter().rev())
+            .filter(move |v| {
+                version_requirements.iter().all(|req| req.is_satisfied(v))
+                    && !current.is_some_and(|pkgs| pkgs.iter().any(|p| &p.version == *v))
+            })
+    }
+
+    /// Every version of a package the repository has: the current sources, the binaries for that
+    /// R version and the archived ones. The same version can be listed several times.
+    pub(crate) fn all_versions<'a>(
+        &'a self,
+        name: &'a str,
+        r_version: &[u32; 2],
+    ) -> impl Iterator<Item = &'a Version> {
+        let binaries = self
+            .binary_packages
+            .get(r_version)
+            .and_then(|db| db.get(name));
+        self.source_packages
+            .get(name)
+            .into_iter()
+            .chain(binaries)
+            .flatten()
+            .map(|p| &p.version)
+            .chain(self.archived_packages.get(name).into_iter().flatten())
     }
 
     pub(crate) fn get_binary_count(&self, r_version: &[u32; 2]) -> usize {
@@ -243,6 +409,7 @@ impl From<RUniversePackage> for Package {
             remote_sha: Some(pkg.remote_sha),
             remote_subdir: pkg.remote_subdir,
             built: None,
+            published: None,
         }
     }
 }
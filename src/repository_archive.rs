@@ -0,0 +1,580 @@
+//! Index of the versions of packages moved to the `src/contrib/Archive` folder of CRAN-like
+//! repositories, read from `src/contrib/Meta/archive.rds`, and of the current tarballs, read from
+//! `src/contrib/Meta/current.rds`.
+//!
+//! The archive index is the R serialization of a named list, one `data.frame` per package, whose
+//! row names are the paths of the archived tarballs, eg `dplyr/dplyr_1.0.10.tar.gz`. The current
+//! index is a single `data.frame` whose row names are the tarballs, eg `dplyr_1.1.4.tar.gz`.
+//! Both have the `file.info` columns of the tarballs, the `mtime` one telling when they were
+//! published.
+//! Only the subset of the R serialization format used by those files is supported.
+
+use std::collections::HashMap;
+use std::io::Read;
+use std::str::FromStr;
+
+use flate2::read::GzDecoder;
+
+use crate::package::Version;
+
+const NILSXP: u8 = 0;
+const SYMSXP: u8 = 1;
+const LISTSXP: u8 = 2;
+const CLOSXP: u8 = 3;
+const ENVSXP: u8 = 4;
+const PROMSXP: u8 = 5;
+const LANGSXP: u8 = 6;
+const SPECIALSXP: u8 = 7;
+const BUILTINSXP: u8 = 8;
+const CHARSXP: u8 = 9;
+const LGLSXP: u8 = 10;
+const INTSXP: u8 = 13;
+const REALSXP: u8 = 14;
+const CPLXSXP: u8 = 15;
+const STRSXP: u8 = 16;
+const DOTSXP: u8 = 17;
+const VECSXP: u8 = 19;
+const EXPRSXP: u8 = 20;
+const RAWSXP: u8 = 24;
+const S4SXP: u8 = 25;
+const ALTREP_SXP: u8 = 238;
+const ATTRLISTSXP: u8 = 239;
+const ATTRLANGSXP: u8 = 240;
+const BASEENV_SXP: u8 = 241;
+const EMPTYENV_SXP: u8 = 242;
+const PERSISTSXP: u8 = 247;
+const PACKAGESXP: u8 = 248;
+const NAMESPACESXP: u8 = 249;
+const BASENAMESPACE_SXP: u8 = 250;
+const MISSINGARG_SXP: u8 = 251;
+const UNBOUNDVALUE_SXP: u8 = 252;
+const GLOBALENV_SXP: u8 = 253;
+const NILVALUE_SXP: u8 = 254;
+const REFSXP: u8 = 255;
+
+const HAS_ATTR: u32 = 1 << 9;
+const HAS_TAG: u32 = 1 << 10;
+
+/// How deeply objects can be nested before we give up, rather than overflowing the stack on
+/// a corrupted file. The indices we read only have a few levels.
+const MAX_DEPTH: usize = 256;
+
+/// How large a decompressed index can be before we give up, rather than filling the memory with
+/// a gzip bomb. The indices of CRAN are a few MB, far below it.
+const MAX_DECOMPRESSED_SIZE: u64 = 512 * 1024 * 1024;
+
+/// The parts of an R object we care about
+#[derive(Debug, Clone, PartialEq, Default)]
+enum RObject {
+    #[default]
+    Null,
+    Symbol(String),
+    Char(Option<String>),
+    Strings(Vec<Option<String>>),
+    Reals(Vec<f64>),
+    List(Vec<RValue>),
+    /// Pairlists, with their tags
+    Pairs(Vec<(Option<String>, RObject)>),
+    /// Everything else: numbers, environments, functions...
+    Other,
+}
+
+/// An R object along with its attributes
+#[derive(Debug, Clone, PartialEq, Default)]
+struct RValue {
+    object: RObject,
+    attributes: Vec<(Option<String>, RObject)>,
+}
+
+impl RValue {
+    fn new(object: RObject) -> Self {
+        Self {
+            object,
+            attributes: Vec::new(),
+        }
+    }
+
+    fn attribute(&self, name: &str) -> Option<&RObject> {
+        self.attributes
+            .iter()
+            .find(|(tag, _)| tag.as_deref() == Some(name))
+            .map(|(_, value)| value)
+    }
+}
+
+/// Reads the XDR serialization format written by `saveRDS`
+struct RdsReader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+    /// Symbols and environments can be referenced by their index later on
+    references: Vec<RObject>,
+    /// How many objects we are currently nested in
+    depth: usize,
+}
+
+impl<'a> RdsReader<'a> {
+    fn new(bytes: &'a [u8]) -> Result<Self, String> {
+        let Some(bytes) = bytes.strip_prefix(b"X\n") else {
+            return Err("only the XDR format is supported".to_string());
+        };
+        let mut reader = Self {
+            bytes,
+            pos: 0,
+            references: Vec::new(),
+            depth: 0,
+        };
+        let version = reader.read_int()?;
+        // The R version that wrote it and the minimal R version needed to read it
+        reader.read_int()?;
+        reader.read_int()?;
+        match version {
+            2 => (),
+            3 => {
+                let encoding_length = reader.read_length()?;
+                reader.read_bytes(encoding_length)?;
+            }
+            _ => return Err(format!("unsupported serialization version {version}")),
+        }
+        Ok(reader)
+    }
+
+    fn read_bytes(&mut self, len: usize) -> Result<&'a [u8], String> {
+        let end = self
+            .pos
+            .checked_add(len)
+            .filter(|end| *end <= self.bytes.len())
+            .ok_or_else(|| "unexpected end of data".to_string())?;
+        let out = &self.bytes[self.pos..end];
+        self.pos = end;
+        Ok(out)
+    }
+
+    /// Reads `len` elements of `size` bytes each
+    fn read_elements(&mut self, len: usize, size: usize) -> Result<&'a [u8], String> {
+        let total = len
+            .checked_mul(size)
+            .ok_or_else(|| format!("invalid length {len}"))?;
+        self.read_bytes(total)
+    }
+
+    /// The capacity to reserve for `len` items: every item takes at least 4 bytes so a length
+    /// larger than what is left in the file can only come from a corrupted file
+    fn capacity_for(&self, len: usize) -> usize {
+        len.min((self.bytes.len() - self.pos) / 4)
+    }
+
+    fn read_int(&mut self) -> Result<i32, String> {
+        let bytes = self.read_bytes(4)?;
+        Ok(i32::from_be_bytes(bytes.try_into().expect("4 bytes")))
+    }
+
+    fn read_length(&mut self) -> Result<usize, String> {
+        let len = self.read_int()?;
+        if len == -1 {
+            let upper = self.read_int()? as u32 as u64;
+            let lower = self.read_int()? as u32 as u64;
+            let len = (upper << 32) | lower;
+            return usize::try_from(len).map_err(|_| format!("invalid length {len}"));
+        }
+        usize::try_from(len).map_err(|_| format!("invalid length {len}"))
+    }
+
+    fn read_tag(&mut self, flags: u32) -> Result<Option<String>, String> {
+        if flags & HAS_TAG == 0 {
+            return Ok(None);
+        }
+        Ok(match self.read_item()?.object {
+            RObject::Symbol(s) => Some(s),
+            _ => None,
+        })
+    }
+
+    fn read_attributes(&mut self, flags: u32) -> Result<Vec<(Option<String>, RObject)>, String> {
+        if flags & HAS_ATTR == 0 {
+            return Ok(Vec::new());
+        }
+        Ok(match self.read_item()?.object {
+            RObject::Pairs(pairs) => pairs,
+            _ => Vec::new(),
+        })
+    }
+
+    fn read_item(&mut self) -> Result<RValue, String> {
+        if self.depth >= MAX_DEPTH {
+            return Err("objects are nested too deeply".to_string());
+        }
+        self.depth += 1;
+        let out = self.read_object();
+        self.depth -= 1;
+        out
+    }
+
+    fn read_object(&mut self) -> Result<RValue, String> {
+        let flags = self.read_int()? as u32;
+        let kind = (flags & 0xFF) as u8;
+
+        let object = match kind {
+            NILVALUE_SXP | NILSXP => RObject::Null,
+            EMPTYENV_SXP | BASEENV_SXP | GLOBALENV_SXP | UNBOUNDVALUE_SXP | MISSINGARG_SXP
+            | BASENAMESPACE_SXP => RObject::Other,
+            REFSXP => {
+                let index = match flags >> 8 {
+                    0 => self.read_int()? as usize,
+                    i => i as usize,
+                };
+                return self
+                    .references
+                    .get(index.wrapping_sub(1))
+                    .cloned()
+                    .map(RValue::new)
+                    .ok_or_else(|| format!("unknown reference {index}"));
+            }
+            PERSISTSXP | NAMESPACESXP | PACKAGESXP => {
+                // Those are a STRSXP without its own flags
+                self.read_int()?;
+                let len = self.read_length()?;
+                for _ in 0..len {
+                    self.read_item()?;
+                }
+                self.references.push(RObject::Other);
+                return Ok(RValue::new(RObject::Other));
+            }
+            SYMSXP => {
+                let name = match self.read_item()?.object {
+                    RObject::Char(s) => s.unwrap_or_default(),
+                    _ => String::new(),
+                };
+                self.references.push(RObject::Symbol(name.clone()));
+                RObject::Symbol(name)
+            }
+            ENVSXP => {
+                self.references.push(RObject::Other);
+                // locked, then the enclosing env, frame, hash table and attributes
+                self.read_int()?;
+                for _ in 0..4 {
+                    self.read_item()?;
+                }
+                return Ok(RValue::new(RObject::Other));
+            }
+            LISTSXP | LANGSXP | CLOSXP | PROMSXP | DOTSXP | ATTRLISTSXP | ATTRLANGSXP => {
+                // Pairlists are read iteratively, the CDR being the next element
+                let mut pairs = Vec::new();
+                let mut flags = flags;
+                loop {
+                    // The attributes of the pairlist itself are not needed
+                    self.read_attributes(flags)?;
+                    let tag = self.read_tag(flags)?;
+                    pairs.push((tag, self.read_item()?.object));
+
+                    let next = self.read_int()? as u32;
+                    let next_kind = (next & 0xFF) as u8;
+                    if matches!(
+                        next_kind,
+                        LISTSXP | LANGSXP | CLOSXP | PROMSXP | DOTSXP | ATTRLISTSXP | ATTRLANGSXP
+                    ) {
+                        flags = next;
+                        continue;
+                    }
+                    // Not a pairlist: rewind and read it as a normal item
+                    self.pos -= 4;
+                    self.read_item()?;
+                    break;
+                }
+                return Ok(RValue::new(RObject::Pairs(pairs)));
+            }
+            SPECIALSXP | BUILTINSXP => {
+                let len = self.read_length()?;
+                self.read_bytes(len)?;
+                RObject::Other
+            }
+            CHARSXP => {
+                let len = self.read_int()?;
+                if len == -1 {
+                    RObject::Char(None)
+                } else {
+                    let len = usize::try_from(len).map_err(|_| format!("invalid length {len}"))?;
+                    let bytes = self.read_bytes(len)?;
+                    RObject::Char(Some(String::from_utf8_lossy(bytes).into_owned()))
+                }
+            }
+            LGLSXP | INTSXP => {
+                let len = self.read_length()?;
+                self.read_elements(len, 4)?;
+                RObject::Other
+            }
+            REALSXP => {
+                let len = self.read_length()?;
+                let bytes = self.read_elements(len, 8)?;
+                RObject::Reals(
+                    bytes
+                        .chunks_exact(8)
+                        .map(|b| f64::from_be_bytes(b.try_into().expect("8 bytes")))
+                        .collect(),
+                )
+            }
+            CPLXSXP => {
+                let len = self.read_length()?;
+                self.read_elements(len, 16)?;
+                RObject::Other
+            }
+            RAWSXP => {
+                let len = self.read_length()?;
+                self.read_bytes(len)?;
+                RObject::Other
+            }
+            STRSXP => {
+                let len = self.read_length()?;
+                let mut strings = Vec::with_capacity(self.capacity_for(len));
+                for _ in 0..len {
+                    strings.push(match self.read_item()?.object {
+                        RObject::Char(s) => s,
+                        _ => None,
+                    });
+                }
+                RObject::Strings(strings)
+            }
+            VECSXP | EXPRSXP => {
+                let len = self.read_length()?;
+                let mut elems = Vec::with_capacity(self.capacity_for(len));
+                for _ in 0..len {
+                    elems.push(self.read_item()?);
+                }
+                RObject::List(elems)
+            }
+            S4SXP => RObject::Other,
+            ALTREP_SXP => {
+                // The class info, the state and the attributes
+                self.read_item()?;
+                self.read_item()?;
+                self.read_item()?;
+                return Ok(RValue::new(RObject::Other));
+            }
+            _ => return Err(format!("unsupported R object type {kind}")),
+        };
+
+        let attributes = self.read_attributes(flags)?;
+        Ok(RValue { object, attributes })
+    }
+}
+
+fn gunzip(content: &[u8], max_size: u64) -> Result<Vec<u8>, String> {
+    let mut out = Vec::new();
+    // One more byte than allowed to know whether we stopped because of the limit
+    GzDecoder::new(content)
+        .take(max_size + 1)
+        .read_to_end(&mut out)
+        .map_err(|e| e.to_string())?;
+    if out.len() as u64 > max_size {
+        return Err(format!("decompressed file is larger than {max_size} bytes"));
+    }
+    Ok(out)
+}
+
+fn read_rds(content: &[u8]) -> Result<RValue, String> {
+    // saveRDS compresses with gzip by default
+    let bytes = if content.starts_with(&[0x1f, 0x8b]) {
+        gunzip(content, MAX_DECOMPRESSED_SIZE)?
+    } else {
+        content.to_vec()
+    };
+    RdsReader::new(&bytes)?.read_item()
+}
+
+/// The versions of each package along with the day their tarball was published, as
+/// `YYYY-MM-DD`, sorted from oldest to newest
+pub(crate) type TarballIndex = HashMap<String, Vec<(Version, Option<String>)>>;
+
+/// The `YYYY-MM-DD` day of a UNIX timestamp, in UTC
+fn day_from_timestamp(timestamp: f64) -> Option<String> {
+    // NA is a NaN
+    if !timestamp.is_finite() {
+        return None;
+    }
+    // http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let z = (timestamp / 86400.0).floor() as i64 + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    Some(format!("{year:04}-{month:02}-{day:02}"))
+}
+
+/// Adds the tarballs listed in the row names of that `data.frame` to the index.
+/// Tarballs not following the `<name>_<version>.tar.gz` format are ignored.
+fn add_tarballs(df: &RValue, in_folder: bool, out: &mut TarballIndex) {
+    let Some(RObject::Strings(tarballs)) = df.attribute("row.names") else {
+        return;
+    };
+    let mtimes = match (&df.object, df.attribute("names")) {
+        (RObject::List(columns), Some(RObject::Strings(names))) => names
+            .iter()
+            .position(|n| n.as_deref() == Some("mtime"))
+            .and_then(|i| match &columns.get(i)?.object {
+                RObject::Reals(mtimes) => Some(mtimes.as_slice()),
+                _ => None,
+            }),
+        _ => None,
+    };
+
+    for (i, tarball) in tarballs.iter().enumerate() {
+        let Some(tarball) = tarball else {
+            continue;
+        };
+        let file = if in_folder {
+            match tarball.split_once('/') {
+                Some((_, file)) => file,
+                None => continue,
+            }
+        } else {
+            tarball.as_str()
+        };
+        let Some((name, version)) = file.strip_suffix(".tar.gz").and_then(|f| f.split_once('_'))
+        else {
+            continue;
+        };
+        if in_folder && !tarball.starts_with(&format!("{name}/")) {
+            continue;
+        }
+        if let Ok(version) = Version::from_str(version) {
+            let published = mtimes
+                .and_then(|m| m.get(i))
+                .and_then(|t| day_from_timestamp(*t));
+            out.entry(name.to_string())
+                .or_default()
+                .push((version, published));
+        }
+    }
+}
+
+fn sort_index(index: &mut TarballIndex) {
+    for versions in index.values_mut() {
+        versions.sort_by(|a, b| a.0.cmp(&b.0));
+        versions.dedup_by(|a, b| a.0 == b.0);
+    }
+}
+
+/// Parses the `archive.rds` of a repository into the archived versions of each package.
+pub(crate) fn parse_archive_index(content: &[u8]) -> Result<TarballIndex, String> {
+    let root = read_rds(content)?;
+    let RObject::List(packages) = root.object else {
+        return Err("expected a list of packages".to_string());
+    };
+
+    let mut out = HashMap::with_capacity(packages.len());
+    for package in &packages {
+        add_tarballs(package, true, &mut out);
+    }
+    sort_index(&mut out);
+    Ok(out)
+}
+
+/// Parses the `current.rds` of a repository into the versions of each package in the PACKAGES
+/// file, which doesn't list when they were published.
+pub(crate) fn parse_current_index(content: &[u8]) -> Result<TarballIndex, String> {
+    let root = read_rds(content)?;
+    if !matches!(root.object, RObject::List(_)) {
+        return Err("expected a data.frame of tarballs".to_string());
+    }
+
+    let mut out = HashMap::new();
+    add_tarballs(&root, false, &mut out);
+    sort_index(&mut out);
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_parse_archive_index() {
+        let content = std::fs::read("src/tests/archives/repo1.rds").unwrap();
+        let index = parse_archive_index(&content).unwrap();
+        let versions = |name: &str| -> Vec<_> {
+            index[name]
+                .iter()
+                .map(|(v, _)| v.original.clone())
+                .collect()
+        };
+
+        assert_eq!(index.len(), 3);
+        assert_eq!(versions("dplyr"), vec!["0.1", "1.0.10", "1.1.3"]);
+        assert_eq!(versions("rv.git.pkgA"), vec!["0.0.3", "0.0.4"]);
+        // Files that are not a package tarball are skipped
+        assert_eq!(versions("zzlite"), vec!["0.9"]);
+        assert_eq!(index["dplyr"][1].1.as_deref(), Some("2022-09-01"));
+    }
+
+    #[test]
+    fn can_parse_current_index() {
+        let content = std::fs::read("src/tests/archives/repo1_current.rds").unwrap();
+        let index = parse_current_index(&content).unwrap();
+
+        assert_eq!(index.len(), 2);
+        assert_eq!(index["rv.git.pkgA"][0].0.original, "0.0.5");
+        assert_eq!(index["rv.git.pkgA"][0].1.as_deref(), Some("2024-06-01"));
+    }
+
+    #[test]
+    fn can_get_day_from_timestamp() {
+        assert_eq!(day_from_timestamp(0.0).as_deref(), Some("1970-01-01"));
+        assert_eq!(day_from_timestamp(1.7e9).as_deref(), Some("2023-11-14"));
+        // Leap day
+        assert_eq!(
+            day_from_timestamp(951782400.0).as_deref(),
+            Some("2000-02-29")
+        );
+        assert_eq!(day_from_timestamp(f64::NAN), None);
+    }
+
+    #[test]
+    fn errors_on_invalid_archive_index() {
+        assert!(parse_archive_index(b"Package: dplyr").is_err());
+    }
+
+    #[test]
+    fn errors_on_corrupted_archive_index() {
+        let mut content = Vec::new();
+        GzDecoder::new(&std::fs::read("src/tests/archives/repo1.rds").unwrap()[..])
+            .read_to_end(&mut content)
+            .unwrap();
+        assert!(parse_archive_index(&content[..content.len() / 2]).is_err());
+
+        let with_items = |items: &[i32]| {
+            let mut out = b"X\n".to_vec();
+            for i in [2, 0, 0].iter().chain(items) {
+                out.extend(i.to_be_bytes());
+            }
+            out
+        };
+        // Lengths larger than the file, or overflowing once multiplied by the element size
+        assert!(parse_archive_index(&with_items(&[STRSXP as i32, i32::MAX])).is_err());
+        assert!(parse_archive_index(&with_items(&[VECSXP as i32, -1, i32::MAX, -1])).is_err());
+        assert!(parse_archive_index(&with_items(&[REALSXP as i32, -1, i32::MAX, -1])).is_err());
+        assert!(parse_archive_index(&with_items(&[CHARSXP as i32, -5])).is_err());
+        // Lists nested deeper than the stack allows
+        let nested: Vec<_> = (0..100_000).flat_map(|_| [VECSXP as i32, 1]).collect();
+        assert_eq!(
+            parse_archive_index(&with_items(&nested)),
+            Err("objects are nested too deeply".to_string())
+        );
+    }
+
+    #[test]
+    fn stops_decompressing_at_the_size_limit() {
+        let content = std::fs::read("src/tests/archives/repo1.rds").unwrap();
+        let size = gunzip(&content, MAX_DECOMPRESSED_SIZE).unwrap().len() as u64;
+        assert_eq!(gunzip(&content, size).unwrap().len() as u64, size);
+        assert_eq!(
+            gunzip(&content, size - 1),
+            Err(format!(
+                "decompressed file is larger than {} bytes",
+                size - 1
+            ))
+        );
+    }
+}
@@ -73,11 +73,33 @@ fn get_source_path(url: &Url, file_path: &[&str]) -> Url {
 }
 
 // Archived packages under the format <base url>/src/contrib/Archive/<pkg name>/<pkg name>_<pkg version>.tar.gz
-fn get_archive_tarball_path(url: &Url, name: &str, version: &str) -> Url {
+pub(crate) fn get_archive_tarball_path(url: &Url, name: &str, version: &str) -> Url {
     let file_name = format!("{name}_{version}.tar.gz");
     get_source_path(url, &["Archive", name, &file_name])
 }
 
+/// Whether the repository can publish the `src/contrib/Meta` indexes. R-Universe doesn't and the
+/// `__linux__` URLs of Posit Package Manager only serve binaries.
+fn has_meta_indexes(url: &Url) -> bool {
+    let is_r_universe = url
+        .host_str()
+        .is_some_and(|host| host.ends_with("r-universe.dev"));
+    let is_linux_binaries = url
+        .path_segments()
+        .is_some_and(|mut segments| segments.any(|s| s == "__linux__"));
+    !is_r_universe && !is_linux_binaries
+}
+
+/// The index of archived packages, only published by CRAN-like repositories
+pub(crate) fn get_archive_index_url(url: &Url) -> Option<Url> {
+    has_meta_indexes(url).then(|| get_source_path(url, &["Meta", "archive.rds"]))
+}
+
+/// The index of the current source tarballs, only published by CRAN-like repositories
+pub(crate) fn get_current_index_url(url: &Url) -> Option<Url> {
+    has_meta_indexes(url).then(|| get_source_path(url, &["Meta", "current.rds"]))
+}
+
 /// # Get the path to the binary version of the file provided, when available.
 ///
 /// ## Given a CRAN-type repository URL, the location of the file wanted depends on the operating system.
@@ -427,4 +449,21 @@ mod tests {
         );
         assert_eq!(source_url.as_str(), ref_url);
     }
+
+    #[test]
+    fn meta_indexes_only_for_cran_like_urls() {
+        let index = get_archive_index_url(&PPM_URL).unwrap();
+        assert_eq!(
+            index.as_str(),
+            format!("{}/src/contrib/Meta/archive.rds", PPM_URL.as_str())
+        );
+        for url in [
+            "https://a2-ai.r-universe.dev",
+            "https://packagemanager.posit.co/cran/__linux__/jammy/latest",
+        ] {
+            let url = Url::parse(url).unwrap();
+            assert_eq!(get_archive_index_url(&url), None);
+            assert_eq!(get_current_index_url(&url), None);
+        }
+    }
 }
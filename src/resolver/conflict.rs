@@ -0,0 +1,224 @@
+//! Explanations of why some version requirements can't be satisfied together, in the style of
+//! PubGrub error reports: first why the requirements on a package are incompatible, then why the
+//! packages having them are required.
+
+use serde::Serialize;
+use std::fmt;
+
+/// The name used as `required_by` for the version requirements coming from the rproject.toml
+pub(crate) const CONFIG_REQUIRER: &str = "rproject.toml";
+
+/// How many of the newest versions of a package are listed in an explanation
+const MAX_LISTED_VERSIONS: usize = 5;
+
+/// A version requirement taking part in a conflict
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ConflictRequirement {
+    /// The package having that requirement, `None` if it comes from the rproject.toml
+    pub required_by: Option<String>,
+    pub required_by_version: Option<String>,
+    pub requirement: String,
+    /// How the package having that requirement is required: a dependency of the rproject.toml
+    /// first, up to that package. Empty for the rproject.toml
+    pub required_through: Vec<String>,
+}
+
+impl ConflictRequirement {
+    fn requirer(&self) -> Option<String> {
+        self.required_by
+            .as_ref()
+            .map(|name| match &self.required_by_version {
+                Some(version) => format!("{name} {version}"),
+                None => name.to_string(),
+            })
+    }
+
+    fn describe(&self, package: &str) -> String {
+        match self.requirer() {
+            Some(requirer) => format!("{requirer} depends on {package} {}", self.requirement),
+            None => format!("you require {package} {}", self.requirement),
+        }
+    }
+
+    /// Why the package having that requirement is installed at all
+    fn reason(&self) -> Option<String> {
+        let (top, rest) = self.required_through.split_first()?;
+        let mut out = format!("you require {top}");
+        for name in rest {
+            out.push_str(&format!(", which depends on {name}"));
+        }
+        Some(out)
+    }
+}
+
+/// Why no version of a package satisfies all the requirements on it
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Conflict {
+    pub package: String,
+    /// The versions of the package the repositories have, or that were resolved from another
+    /// source like git, oldest first
+    pub available_versions: Vec<String>,
+    pub requirements: Vec<ConflictRequirement>,
+    /// The explanation shown to users
+    pub explanation: String,
+}
+
+/// Joins the elements like a sentence: `a, b and c`
+fn enumerate(elems: &[String]) -> String {
+    match elems {
+        [] => String::new(),
+        [single] => single.clone(),
+        [rest @ .., last] => format!("{} and {last}", rest.join(", ")),
+    }
+}
+
+impl Conflict {
+    pub(crate) fn new(
+        package: String,
+        available_versions: Vec<String>,
+        requirements: Vec<ConflictRequirement>,
+    ) -> Self {
+        let mut conflict = Self {
+            package,
+            available_versions,
+            requirements,
+            explanation: String::new(),
+        };
+        conflict.explanation = conflict.explain();
+        conflict
+    }
+
+    fn explain(&self) -> String {
+        let package = &self.package;
+        let mut causes: Vec<_> = self
+            .requirements
+            .iter()
+            .map(|r| r.describe(package))
+            .collect();
+        causes.push(match self.available_versions.as_slice() {
+            [] => format!("{package} was not found in any repository"),
+            [version] => format!("only {package} {version} is available"),
+            versions if versions.len() > MAX_LISTED_VERSIONS => {
+                let (older, newest) = versions.split_at(versions.len() - MAX_LISTED_VERSIONS);
+                format!(
+                    "only {package} {} and {} older versions are available",
+                    newest.join(", "),
+                    older.len()
+                )
+            }
+            versions => format!("only {package} {} are available", versions.join(", ")),
+        });
+
+        let requirers: Vec<_> = self
+            .requirements
+            .iter()
+            .filter_map(|r| r.requirer())
+            .collect();
+        let from_config = self.requirements.iter().any(|r| r.required_by.is_none());
+        let incompatibility = match (requirers.as_slice(), from_config) {
+            ([], _) => return format!("Because {}, version solving failed.", enumerate(&causes)),
+            ([requirer], false) => format!("{requirer} can't be installed"),
+            ([requirer], true) => format!("{requirer} is incompatible with your rproject.toml"),
+            (requirers, false) => format!("{} are incompatible", enumerate(requirers)),
+            (requirers, true) => format!(
+                "{} are incompatible with your rproject.toml",
+                enumerate(requirers)
+            ),
+        };
+
+        // Top level dependencies are grouped in a single `you require a and b`
+        let mut top_level = Vec::new();
+        let mut reasons = Vec::new();
+        for req in &self.requirements {
+            match req.required_through.as_slice() {
+                [] => (),
+                [name] => {
+                    if !top_level.contains(name) {
+                        top_level.push(name.clone());
+                    }
+                }
+                _ => {
+                    let reason = req.reason().expect("non empty path");
+                    if !reasons.contains(&reason) {
+                        reasons.push(reason);
+                    }
+                }
+            }
+        }
+        if !top_level.is_empty() {
+            reasons.insert(0, format!("you require {}", enumerate(&top_level)));
+        }
+        if reasons.is_empty() {
+            return format!(
+                "Because {}, {incompatibility} and version solving failed.",
+                enumerate(&causes)
+            );
+        }
+        format!(
+            "Because {}, {incompatibility}.\nAnd because {}, version solving failed.",
+            enumerate(&causes),
+            enumerate(&reasons)
+        )
+    }
+}
+
+impl fmt::Display for Conflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.explanation)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn explains_transitive_conflicts() {
+        let conflict = Conflict::new(
+            "B".to_string(),
+            vec!["1.3".to_string(), "1.6".to_string()],
+            vec![
+                ConflictRequirement {
+                    required_by: Some("A".to_string()),
+                    required_by_version: Some("2.0".to_string()),
+                    requirement: "(>= 1.5)".to_string(),
+                    required_through: vec!["A".to_string()],
+                },
+                ConflictRequirement {
+                    required_by: Some("C".to_string()),
+                    required_by_version: Some("1.0".to_string()),
+                    requirement: "(< 1.4)".to_string(),
+                    required_through: vec!["D".to_string(), "C".to_string()],
+                },
+            ],
+        );
+        assert_eq!(
+            conflict.to_string(),
+            "Because A 2.0 depends on B (>= 1.5), C 1.0 depends on B (< 1.4) and only B 1.3, 1.6 are available, A 2.0 and C 1.0 are incompatible.\nAnd because you require A and you require D, which depends on C, version solving failed."
+        );
+    }
+
+    #[test]
+    fn lists_newest_available_versions_only() {
+        let requirement = |required_by: &str| ConflictRequirement {
+            required_by: Some(required_by.to_string()),
+            required_by_version: Some("1.0".to_string()),
+            requirement: "(>= 9.0)".to_string(),
+            required_through: vec![required_by.to_string()],
+        };
+        let versions: Vec<_> = (1..=7).map(|v| format!("{v}.0")).collect();
+        let conflict = Conflict::new("B".to_string(), versions, vec![requirement("A")]);
+        assert!(
+            conflict
+                .to_string()
+                .contains("only B 3.0, 4.0, 5.0, 6.0, 7.0 and 2 older versions are available")
+        );
+
+        let conflict = Conflict::new("B".to_string(), Vec::new(), vec![requirement("A")]);
+        assert!(
+            conflict
+                .to_string()
+                .contains("B was not found in any repository")
+        );
+    }
+}
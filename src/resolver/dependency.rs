@@ -7,6 +7,7 @@ use std::str::FromStr;
 use url::Url;
 
 use crate::cache::InstallationStatus;
+use crate::config::{DependencyMarkers, MAIN_GROUP};
 use crate::lockfile::{LockedPackage, Source};
 use crate::package::{Dependency, InstallationDependencies, Package, PackageRemote, PackageType};
 use crate::resolver::QueueItem;
@@ -39,6 +40,24 @@ pub struct ResolvedDependency<'d> {
     /// { name = "dplyr", dependencies_only = true } in your rproject.toml
     /// in which case we want to keep track of it but not write it anywhere
     pub(crate) ignored: bool,
+    /// Whether that package is excluded in the config. Excluded packages are ignored and their
+    /// own dependencies are not resolved
+    pub(crate) excluded: bool,
+    /// Whether the version or source of that package is forced by `[overrides]`
+    pub(crate) overridden: bool,
+    /// Whether that package is redirected to another source by `[patch]`
+    pub(crate) patched: bool,
+    /// The dependency groups requiring that package, see `Resolution::assign_groups`
+    pub(crate) groups: Vec<String>,
+    /// The markers of the config dependency, if that package is one
+    pub(crate) markers: DependencyMarkers,
+    /// The MD5 of the source tarball, for repository packages
+    pub(crate) md5sum: Option<String>,
+    /// The SHA-256 of the source tarball, for repository packages downloaded before
+    pub(crate) sha256: Option<String>,
+    /// The MD5 of the binary tarball for the current system, as listed by the repository. Not
+    /// written to the lockfile since binaries differ on each system
+    pub(crate) binary_md5sum: Option<String>,
 }
 
 impl<'d> ResolvedDependency<'d> {
@@ -91,7 +110,15 @@ impl<'d> ResolvedDependency<'d> {
             from_remote: false,
             local_resolved_path: None,
             env_vars: HashMap::new(),
-            ignored: false,
+            ignored: package.excluded,
+            excluded: package.excluded,
+            overridden: package.overridden,
+            patched: package.patched,
+            groups: Vec::new(),
+            markers: DependencyMarkers::default(),
+            md5sum: package.md5sum.clone(),
+            sha256: package.sha256.clone(),
+            binary_md5sum: None,
         }
     }
 
@@ -135,6 +162,14 @@ impl<'d> ResolvedDependency<'d> {
             local_resolved_path: None,
             env_vars: HashMap::new(),
             ignored: false,
+            excluded: false,
+            overridden: false,
+            patched: false,
+            groups: Vec::new(),
+            markers: DependencyMarkers::default(),
+            md5sum: None,
+            sha256: None,
+            binary_md5sum: None,
         };
 
         (res, deps)
@@ -142,6 +177,26 @@ impl<'d> ResolvedDependency<'d> {
 
     /// If we find the package to be a git repo, we will read the DESCRIPTION file during resolution
     /// This means the data will not outlive this struct and needs to be owned
+    /// Archived packages are only available as source and we get their DESCRIPTION from the
+    /// tarball so they are owned like git packages
+    pub fn from_archived_package<'p>(
+        package: &'p Package,
+        repo_url: &Url,
+        install_suggests: bool,
+        installation_status: InstallationStatus,
+    ) -> (Self, InstallationDependencies<'p>) {
+        let (mut res, deps) = Self::from_git_package(
+            package,
+            Source::Repository {
+                repository: repo_url.clone(),
+            },
+            install_suggests,
+            installation_status,
+        );
+        res.remotes = HashMap::new();
+        (res, deps)
+    }
+
     pub fn from_git_package(
         package: &Package,
         source: Source,
@@ -171,6 +226,14 @@ impl<'d> ResolvedDependency<'d> {
             local_resolved_path: None,
             env_vars: HashMap::new(),
             ignored: false,
+            excluded: false,
+            overridden: false,
+            patched: false,
+            groups: Vec::new(),
+            markers: DependencyMarkers::default(),
+            md5sum: None,
+            sha256: None,
+            binary_md5sum: None,
         };
 
         (res, deps)
@@ -205,6 +268,14 @@ impl<'d> ResolvedDependency<'d> {
             local_resolved_path: Some(local_resolved_path),
             env_vars: HashMap::new(),
             ignored: false,
+            excluded: false,
+            overridden: false,
+            patched: false,
+            groups: Vec::new(),
+            markers: DependencyMarkers::default(),
+            md5sum: None,
+            sha256: None,
+            binary_md5sum: None,
         };
 
         (res, deps)
@@ -238,6 +309,14 @@ impl<'d> ResolvedDependency<'d> {
             local_resolved_path: None,
             env_vars: HashMap::new(),
             ignored: false,
+            excluded: false,
+            overridden: false,
+            patched: false,
+            groups: Vec::new(),
+            markers: DependencyMarkers::default(),
+            md5sum: None,
+            sha256: None,
+            binary_md5sum: None,
         };
 
         (res, deps)
@@ -266,6 +345,14 @@ impl<'d> ResolvedDependency<'d> {
             local_resolved_path: None,
             env_vars: HashMap::new(),
             ignored: false,
+            excluded: false,
+            overridden: false,
+            patched: false,
+            groups: Vec::new(),
+            markers: DependencyMarkers::default(),
+            md5sum: None,
+            sha256: None,
+            binary_md5sum: None,
         };
 
         (res, deps)
@@ -282,7 +369,7 @@ impl fmt::Debug for ResolvedDependency<'_> {
         vars.sort();
         write!(
             f,
-            "{}={} ({:?}, type={}, path='{}', from_lockfile={}, from_remote={}, env_vars=[{}]{})",
+            "{}={} ({:?}, type={}, path='{}', from_lockfile={}, from_remote={}, env_vars=[{}]{}{}{}{}{})",
             self.name,
             self.version.original,
             self.source,
@@ -291,7 +378,26 @@ impl fmt::Debug for ResolvedDependency<'_> {
             self.from_lockfile,
             self.from_remote,
             vars.join(", "),
-            if self.ignored { ", ignored" } else { "" },
+            if self.excluded {
+                ", excluded"
+            } else if self.ignored {
+                ", ignored"
+            } else {
+                ""
+            },
+            if self.overridden { ", overridden" } else { "" },
+            if self.patched { ", patched" } else { "" },
+            // Only show groups when there is something else than the main group
+            if self.groups.is_empty() || self.groups == [MAIN_GROUP] {
+                String::new()
+            } else {
+                format!(", groups=[{}]", self.groups.join(", "))
+            },
+            if self.markers.is_empty() {
+                String::new()
+            } else {
+                format!(", markers=[{}]", self.markers)
+            },
         )
     }
 }
@@ -1,13 +1,17 @@
 use crate::VersionRequirement;
-use crate::{CommandExecutor, ConfigDependency, DiskCache, Lockfile, RepositoryDatabase, Version};
+use crate::{
+    CommandExecutor, ConfigDependency, DiskCache, Lockfile, RepositoryDatabase, SystemInfo, Version,
+};
 
 use fs_err as fs;
+use serde::{Deserialize, Serialize};
 use std::borrow::Cow;
 use std::collections::{HashMap, HashSet, VecDeque};
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
 use url::Url;
 
+mod conflict;
 mod dependency;
 mod result;
 mod sat;
@@ -21,10 +25,37 @@ use crate::package::{
     Package, PackageRemote, PackageType, is_binary_package, parse_description_file,
     parse_description_file_in_folder,
 };
+use crate::repository::VersionSelection;
+use crate::repository_urls::get_archive_tarball_path;
 use crate::utils::create_spinner;
+pub use conflict::{Conflict, ConflictRequirement};
 pub use dependency::{ResolvedDependency, UnresolvedDependency};
 pub use result::Resolution;
 
+/// Which version to pick when several of them satisfy the requirements on a package
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+#[serde(rename_all = "kebab-case")]
+pub enum ResolutionStrategy {
+    /// The highest version available
+    #[default]
+    Highest,
+    /// The lowest version satisfying the requirements, for every package
+    Lowest,
+    /// The lowest version for the dependencies listed in the config, the highest for the others
+    LowestDirect,
+}
+
+impl ResolutionStrategy {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Highest => "highest",
+            Self::Lowest => "lowest",
+            Self::LowestDirect => "lowest-direct",
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Default)]
 pub(crate) struct QueueItem<'d> {
     name: Cow<'d, str>,
@@ -48,6 +79,23 @@ impl<'d> QueueItem<'d> {
         })
     }
 
+    /// The version requirements from the config, on top of `version_requirement`
+    fn config_version_requirements(&self) -> Vec<VersionRequirement> {
+        self.dep
+            .map(|d| d.version_requirements())
+            .unwrap_or_default()
+    }
+
+    fn is_version_satisfied(&self, version: &Version) -> bool {
+        self.version_requirement
+            .as_ref()
+            .is_none_or(|req| req.is_satisfied(version))
+            && self
+                .config_version_requirements()
+                .iter()
+                .all(|req| req.is_satisfied(version))
+    }
+
     fn name_and_parent_only(name: Cow<'d, str>, parent: Cow<'d, str>) -> Self {
         Self {
             name,
@@ -55,6 +103,26 @@ impl<'d> QueueItem<'d> {
             ..Default::default()
         }
     }
+
+    /// Replaces what the item requires by an override or a patch: the version requirements and
+    /// remotes coming from the parent package are dropped
+    fn with_dependency(
+        self,
+        dep: &'d ConfigDependency,
+        matching_in_lockfile: Option<bool>,
+    ) -> Self {
+        Self {
+            name: self.name,
+            dep: Some(dep),
+            version_requirement: None,
+            install_suggestions: dep.install_suggestions(),
+            force_source: dep.force_source(),
+            parent: self.parent,
+            remote: None,
+            local_path: dep.local_path(),
+            matching_in_lockfile,
+        }
+    }
 }
 
 // Macro to go around borrow errors we would get with a normal fn
@@ -99,6 +167,8 @@ pub struct Resolver<'d> {
     /// We might not have loaded the databases but we still want their urls
     repo_urls: HashSet<&'d str>,
     r_version: &'d Version,
+    /// The dependencies whose markers don't match that system are skipped
+    system_info: &'d SystemInfo,
     /// The base + recommended package versions for the R version we are using
     builtin_packages: &'d HashMap<String, Package>,
     /// Env vars from the config
@@ -108,14 +178,22 @@ pub struct Resolver<'d> {
     lockfile: Option<&'d Lockfile>,
     /// Progress bar is only shown for git dependencies
     show_progress_bar: bool,
+    strategy: ResolutionStrategy,
+    /// Versions published after that day, as `YYYY-MM-DD`, are ignored
+    exclude_newer: Option<&'d str>,
+    /// When upgrading only some packages, the ones to look up in the repositories even if they
+    /// are in the lockfile. The other packages of the lockfile need to keep their version.
+    upgrade_packages: Option<&'d HashSet<String>>,
 }
 
 impl<'d> Resolver<'d> {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         project_dir: impl AsRef<Path>,
         repositories: &'d [(RepositoryDatabase, bool)],
         repo_urls: HashSet<&'d str>,
         r_version: &'d Version,
+        system_info: &'d SystemInfo,
         builtin_packages: &'d HashMap<String, Package>,
         lockfile: Option<&'d Lockfile>,
         packages_env_vars: &'d HashMap<String, HashMap<String, String>>,
@@ -125,10 +203,14 @@ impl<'d> Resolver<'d> {
             repositories,
             repo_urls,
             r_version,
+            system_info,
             lockfile,
             builtin_packages,
             packages_env_vars,
             show_progress_bar: false,
+            strategy: ResolutionStrategy::default(),
+            exclude_newer: None,
+            upgrade_packages: None,
         }
     }
 
@@ -136,6 +218,48 @@ impl<'d> Resolver<'d> {
         self.show_progress_bar = true;
     }
 
+    pub fn set_resolution_strategy(&mut self, strategy: ResolutionStrategy) {
+        self.strategy = strategy;
+    }
+
+    pub fn set_exclude_newer(&mut self, exclude_newer: Option<&'d str>) {
+        self.exclude_newer = exclude_newer;
+    }
+
+    pub fn set_upgrade_packages(&mut self, packages: &'d HashSet<String>) {
+        self.upgrade_packages = Some(packages);
+    }
+
+    fn is_upgraded(&self, name: &str) -> bool {
+        self.upgrade_packages.is_some_and(|p| p.contains(name))
+    }
+
+    /// When upgrading only some packages, the error if that item requires a version of a locked
+    /// package other than the one in the lockfile
+    fn locked_version_conflict(&self, item: &QueueItem<'d>) -> Option<String> {
+        if self.upgrade_packages.is_none()
+            || self.is_upgraded(&item.name)
+            || item.matching_in_lockfile == Some(false)
+        {
+            return None;
+        }
+        let package = self.lockfile?.get_package(&item.name, item.dep)?;
+        if package.source.could_have_changed()
+            || item.is_version_satisfied(&Version::from_str(&package.version).ok()?)
+        {
+            return None;
+        }
+        Some(format!(
+            "locked to version {}, upgrade it as well or use `--with-deps`",
+            package.version
+        ))
+    }
+
+    fn is_too_new(&self, published: Option<&str>) -> bool {
+        self.exclude_newer
+            .is_some_and(|cutoff| published.is_some_and(|day| day > cutoff))
+    }
+
     fn local_lookup(
         &self,
         item: &QueueItem<'d>,
@@ -197,6 +321,10 @@ impl<'d> Resolver<'d> {
             return None;
         }
 
+        if self.is_upgraded(&item.name) {
+            return None;
+        }
+
         if let Some(package) = self
             .lockfile
             .and_then(|l| l.get_package(&item.name, item.dep))
@@ -207,16 +335,30 @@ impl<'d> Resolver<'d> {
                 return None;
             }
 
-            if let Some(req) = &item.version_requirement
-                && !req.is_satisfied(&Version::from_str(&package.version).unwrap())
-            {
+            if !item.is_version_satisfied(&Version::from_str(&package.version).unwrap()) {
                 return None;
             }
 
             let installation_status =
                 cache.get_installation_status(&item.name, &package.version, &package.source);
-            let resolved_dep =
+            let mut resolved_dep =
                 ResolvedDependency::from_locked_package(package, installation_status);
+            // The databases are only there if the lockfile was not enough to resolve everything
+            if let Source::Repository { repository } = &package.source
+                && resolved_dep.kind == PackageType::Binary
+                && let Some((repo, _)) = self
+                    .repositories
+                    .iter()
+                    .find(|(r, _)| Url::parse(&r.url).is_ok_and(|u| &u == repository))
+            {
+                resolved_dep.binary_md5sum = repo
+                    .binary_md5sum(
+                        &package.name,
+                        &resolved_dep.version,
+                        &self.r_version.major_minor(),
+                    )
+                    .map(|s| s.to_string());
+            }
 
             let items = package
                 .dependencies
@@ -240,8 +382,16 @@ impl<'d> Resolver<'d> {
         &self,
         item: &QueueItem<'d>,
         cache: &'d DiskCache,
+        lowest: bool,
     ) -> Option<(ResolvedDependency<'d>, Vec<QueueItem<'d>>)> {
         let repository = item.dep.as_ref().and_then(|c| c.r_repository());
+        let config_version_requirements = item.config_version_requirements();
+        let version_requirements: Vec<_> = item
+            .version_requirement
+            .as_deref()
+            .into_iter()
+            .chain(&config_version_requirements)
+            .collect();
 
         for (repo, repo_source_only) in self.repositories {
             if let Some(r) = repository
@@ -255,11 +405,16 @@ impl<'d> Resolver<'d> {
                 *repo_source_only
             };
 
-            if let Some((package, package_type)) = repo.find_package(
+            let selection = VersionSelection {
+                lowest,
+                exclude_newer: self.exclude_newer,
+            };
+            if let Some((package, package_type)) = repo.find_package_with(
                 item.name.as_ref(),
-                item.version_requirement.as_deref(),
+                &version_requirements,
                 self.r_version,
                 force_source,
+                selection,
             ) {
                 let mut status = cache.get_installation_status(
                     &package.name,
@@ -275,7 +430,7 @@ impl<'d> Resolver<'d> {
                     status = status.mark_as_binary_unavailable();
                 }
 
-                let (resolved_dep, deps) = ResolvedDependency::from_package_repository(
+                let (mut resolved_dep, deps) = ResolvedDependency::from_package_repository(
                     package,
                     &Url::parse(&repo.url).unwrap(),
                     package_type,
@@ -283,6 +438,101 @@ impl<'d> Resolver<'d> {
                     force_source,
                     status,
                 );
+                if resolved_dep.source.is_repo() {
+                    resolved_dep.md5sum = repo
+                        .source_md5sum(&package.name, &package.version)
+                        .map(|s| s.to_string());
+                    resolved_dep.sha256 = cache.get_source_sha256(
+                        &package.name,
+                        &package.version.original,
+                        &resolved_dep.source,
+                    );
+                    if package_type == PackageType::Binary {
+                        resolved_dep.binary_md5sum = repo
+                            .binary_md5sum(
+                                &package.name,
+                                &package.version,
+                                &self.r_version.major_minor(),
+                            )
+                            .map(|s| s.to_string());
+                    }
+                }
+                return Some(prepare_deps!(resolved_dep, deps, item.matching_in_lockfile));
+            }
+        }
+
+        None
+    }
+
+    /// Looks for a version matching the requirements in the archives of the repositories, when
+    /// no current version does. We only know the versions of archived packages so the tarball is
+    /// downloaded to read its DESCRIPTION. Archived packages are always installed from source.
+    /// The newest matching version is picked, or the oldest one if `lowest` is set.
+    fn archive_lookup(
+        &self,
+        item: &QueueItem<'d>,
+        cache: &'d DiskCache,
+        http_downloader: &'d impl HttpDownload,
+        lowest: bool,
+    ) -> Option<(ResolvedDependency<'d>, Vec<QueueItem<'d>>)> {
+        let repository = item.dep.as_ref().and_then(|c| c.r_repository());
+        let config_version_requirements = item.config_version_requirements();
+        let version_requirements: Vec<_> = item
+            .version_requirement
+            .as_deref()
+            .into_iter()
+            .chain(&config_version_requirements)
+            .collect();
+
+        // Later repositories have priority
+        for (repo, _) in self.repositories.iter().rev() {
+            if let Some(r) = repository
+                && repo.url != r
+            {
+                continue;
+            }
+            let repo_url = Url::parse(&repo.url).unwrap();
+
+            let mut versions: Vec<_> = repo
+                .find_archived_versions(item.name.as_ref(), &version_requirements)
+                .filter(|v| !self.is_too_new(repo.published_on(&item.name, v)))
+                .collect();
+            if lowest {
+                versions.reverse();
+            }
+            for version in versions {
+                let url = get_archive_tarball_path(&repo_url, &item.name, &version.original);
+                let package =
+                    match self.archived_description(&item.name, &url, cache, http_downloader) {
+                        Ok(p) => p,
+                        Err(e) => {
+                            log::debug!("Could not get the DESCRIPTION file from {url}: {e}");
+                            continue;
+                        }
+                    };
+                if package.name != item.name || &package.version != version {
+                    log::debug!("{url} doesn't contain {} {}", item.name, version.original);
+                    continue;
+                }
+                if !package.works_with_r_version(self.r_version)
+                    || self.is_too_new(package.published.as_deref())
+                {
+                    continue;
+                }
+
+                let status = cache.get_installation_status(
+                    &package.name,
+                    &package.version.original,
+                    &Source::Repository {
+                        repository: repo_url.clone(),
+                    },
+                );
+                let (resolved_dep, deps) = ResolvedDependency::from_archived_package(
+                    &package,
+                    &repo_url,
+                    item.install_suggestions,
+                    status,
+                );
                 return Some(prepare_deps!(resolved_dep, deps, item.matching_in_lockfile));
             }
         }
@@ -290,6 +540,26 @@ impl<'d> Resolver<'d> {
         None
     }
 
+    /// The DESCRIPTION of an archived tarball, downloading it only if it's not in the cache already
+    fn archived_description(
+        &self,
+        name: &str,
+        url: &Url,
+        cache: &'d DiskCache,
+        http_downloader: &'d impl HttpDownload,
+    ) -> Result<Package, Box<dyn std::error::Error>> {
+        let out_path = cache.get_url_download_path(url);
+        // The tarball contains a folder named after the package
+        for folder in [out_path.join(name), out_path.clone()] {
+            if let Ok(package) = parse_description_file_in_folder(&folder) {
+                return Ok(package);
+            }
+        }
+
+        let (dir, _) = http_downloader.download_and_untar(url, &out_path, false, None)?;
+        parse_description_file_in_folder(dir.unwrap_or(out_path))
+    }
+
     fn git_lookup(
         &self,
         item: &QueueItem<'d>,
@@ -407,19 +677,13 @@ impl<'d> Resolver<'d> {
         item: &QueueItem<'d>,
     ) -> Option<(ResolvedDependency<'d>, Vec<QueueItem<'d>>)> {
         if let Some(package) = self.builtin_packages.get(item.name.as_ref()) {
-            if let Some(ref req) = item.version_requirement {
-                if req.is_satisfied(&package.version) {
-                    let (resolved_dep, deps) =
-                        ResolvedDependency::from_builtin_package(package, item.install_suggestions);
-                    Some(prepare_deps!(resolved_dep, deps, item.matching_in_lockfile))
-                } else {
-                    None
-                }
-            } else {
-                // if there's no version requirement, we are fine with what's builtin
+            // if there's no version requirement, we are fine with what's builtin
+            if item.is_version_satisfied(&package.version) {
                 let (resolved_dep, deps) =
                     ResolvedDependency::from_builtin_package(package, item.install_suggestions);
                 Some(prepare_deps!(resolved_dep, deps, item.matching_in_lockfile))
+            } else {
+                None
             }
         } else {
             None
@@ -427,15 +691,45 @@ impl<'d> Resolver<'d> {
     }
 
     /// Tries to find all dependencies from the repos, as well as their installation status
+    #[allow(clippy::too_many_arguments)]
     pub fn resolve(
         &self,
-        dependencies: &'d [ConfigDependency],
+        dependencies: &[&'d ConfigDependency],
         prefer_repositories_for: &'d [String],
+        exclude: &'d [String],
+        overrides: &'d [ConfigDependency],
+        patches: &'d [ConfigDependency],
         cache: &'d DiskCache,
         git_exec: &'d (impl CommandExecutor + Clone + 'static),
         http_download: &'d impl HttpDownload,
     ) -> Resolution<'d> {
         let mut result = Resolution::default();
+        // Dependencies restricted to other platforms or R versions are ignored entirely
+        let dependencies: Vec<_> = dependencies
+            .iter()
+            .copied()
+            .filter(|d| {
+                let matches = d.matches_target(self.system_info, self.r_version);
+                if !matches {
+                    log::debug!("Skipping {} ({}) on this target", d.name(), d.markers());
+                }
+                matches
+            })
+            .collect();
+        // Overrides take precedence over what any package, or the config itself, asks for
+        let overrides: HashMap<_, _> = overrides
+            .iter()
+            .filter(|o| o.matches_target(self.system_info, self.r_version))
+            .map(|o| (o.name(), o))
+            .collect();
+        // Patches only change where a package comes from, the version requirements on them are
+        // checked once everything is resolved
+        let patches: HashMap<_, _> = patches
+            .iter()
+            .filter(|p| p.matches_target(self.system_info, self.r_version))
+            .filter(|p| !overrides.contains_key(p.name()))
+            .map(|p| (p.name(), p))
+            .collect();
         let mut processed: HashMap<String, HashSet<Option<Cow<'d, VersionRequirement>>>> =
             HashMap::with_capacity(dependencies.len() * 10);
         // Top level dependencies can require specific repos.
@@ -443,34 +737,101 @@ impl<'d> Resolver<'d> {
         // packages
         let repo_required: HashSet<_> = dependencies
             .iter()
+            .copied()
+            .chain(overrides.values().copied())
             .filter(|d| d.r_repository().is_some())
             .map(|d| d.name())
             .collect();
+        // Packages excluded in the config or by any dependency are resolved but their own
+        // dependencies are not and they are never installed
+        let excluded: HashSet<_> = exclude
+            .iter()
+            .chain(dependencies.iter().flat_map(|d| d.exclude()))
+            .map(|s| s.as_str())
+            .collect();
         let dependencies_only: HashSet<_> = dependencies
             .iter()
+            .copied()
             .filter(|d| d.dependencies_only())
             .map(|d| d.name())
             .collect();
+        let direct: HashSet<_> = dependencies.iter().map(|d| d.name()).collect();
+        let prefers_lowest = |name: &str| match self.strategy {
+            ResolutionStrategy::Highest => false,
+            ResolutionStrategy::Lowest => true,
+            ResolutionStrategy::LowestDirect => direct.contains(name),
+        };
+
+        // Version requirements set in the config are also given to the SAT solver so they
+        // are enforced even if a dependency of another package requires another version
+        let config_requirements: Vec<_> = dependencies
+            .iter()
+            .copied()
+            .filter(|d| !overrides.contains_key(d.name()))
+            .chain(overrides.values().copied())
+            .flat_map(|d| {
+                d.version_requirements()
+                    .into_iter()
+                    .map(move |req| (d.name(), req))
+            })
+            .collect();
 
+        // We get one queue item per version requirement set in the config
         let mut queue: VecDeque<_> = dependencies
             .iter()
-            .map(|d| QueueItem {
-                name: Cow::Borrowed(d.name()),
-                dep: Some(d),
-                version_requirement: None,
-                install_suggestions: d.install_suggestions(),
-                force_source: d.force_source(),
-                parent: None,
-                remote: None,
-                local_path: d.local_path(),
-                matching_in_lockfile: self.lockfile.and_then(|l| {
-                    l.get_package(d.name(), Some(d))
-                        .map(|p| p.is_matching(d, &self.repo_urls))
-                }),
+            .copied()
+            .flat_map(|d| {
+                let version_requirements = d.version_requirements();
+                let version_requirements = if version_requirements.is_empty() {
+                    vec![None]
+                } else {
+                    version_requirements
+                        .into_iter()
+                        .map(|req| Some(Cow::Owned(req)))
+                        .collect()
+                };
+                version_requirements
+                    .into_iter()
+                    .map(move |version_requirement| QueueItem {
+                        name: Cow::Borrowed(d.name()),
+                        dep: Some(d),
+                        version_requirement,
+                        install_suggestions: d.install_suggestions(),
+                        force_source: d.force_source(),
+                        parent: None,
+                        remote: None,
+                        local_path: d.local_path(),
+                        matching_in_lockfile: self.lockfile.and_then(|l| {
+                            l.get_package(d.name(), Some(d))
+                                .map(|p| p.is_matching(d, &self.repo_urls))
+                        }),
+                    })
             })
             .collect();
 
         while let Some(item) = queue.pop_front() {
+            if item
+                .parent
+                .as_ref()
+                .is_some_and(|p| excluded.contains(p.as_ref()))
+            {
+                continue;
+            }
+
+            let item = match overrides
+                .get(item.name.as_ref())
+                .or_else(|| patches.get(item.name.as_ref()))
+            {
+                Some(d) => {
+                    let matching_in_lockfile = self.lockfile.and_then(|l| {
+                        l.get_package(d.name(), Some(d))
+                            .map(|p| p.is_matching(d, &self.repo_urls))
+                    });
+                    item.with_dependency(d, matching_in_lockfile)
+                }
+                None => item,
+            };
+
             if let Some(ver_reqs) = processed.get(item.name.as_ref()) {
                 // If we have already found that dependency and it has a forced repo, skip it
                 if repo_required.contains(item.name.as_ref()) {
@@ -516,8 +877,15 @@ impl<'d> Resolver<'d> {
                 continue;
             }
 
-            // Look at lockfile
-            if let Some((resolved_dep, items)) = self.lockfile_lookup(&item, cache) {
+            // Look at lockfile, unless the package was excluded, overridden or patched when locked
+            // and isn't anymore
+            if let Some((resolved_dep, items)) =
+                self.lockfile_lookup(&item, cache).filter(|(d, _)| {
+                    (!d.excluded || excluded.contains(d.name.as_ref()))
+                        && (!d.overridden || overrides.contains_key(d.name.as_ref()))
+                        && (!d.patched || patches.contains_key(d.name.as_ref()))
+                })
+            {
                 processed
                     .entry(resolved_dep.name.to_string())
                     .or_default()
@@ -534,6 +902,13 @@ impl<'d> Resolver<'d> {
                 .or_default()
                 .insert(item.version_requirement.clone());
 
+            if let Some(error) = self.locked_version_conflict(&item) {
+                result
+                    .failed
+                    .push(UnresolvedDependency::from_item(&item).with_error(error));
+                continue;
+            }
+
             // But first, we check if the item has a remote and use that instead
             // We will keep the remote result around _if_ the item has a version requirement and is in
             // override list so we can check in the repo before pushing the remote version
@@ -607,7 +982,15 @@ impl<'d> Resolver<'d> {
                     if item.version_requirement.is_none() && result.found_in_repo(&item.name) {
                         continue;
                     }
-                    if let Some((resolved_dep, items)) = self.repositories_lookup(&item, cache) {
+                    // The archived versions are older than the current ones
+                    let found = if prefers_lowest(&item.name) {
+                        self.archive_lookup(&item, cache, http_download, true)
+                            .or_else(|| self.repositories_lookup(&item, cache, true))
+                    } else {
+                        self.repositories_lookup(&item, cache, false)
+                            .or_else(|| self.archive_lookup(&item, cache, http_download, false))
+                    };
+                    if let Some((resolved_dep, items)) = found {
                         result.add_found(resolved_dep);
                         queue.extend(items);
                     } else {
@@ -680,14 +1063,35 @@ impl<'d> Resolver<'d> {
         for name in dependencies_only {
             result.ignore(name);
         }
+        result.exclude(&excluded);
 
         for dep in result.found.iter_mut() {
             if let Some(args) = self.packages_env_vars.get(dep.name.as_ref()) {
                 dep.env_vars = args.iter().map(|(k, v)| (k.as_str(), v.as_str())).collect();
             }
+            if let Some(o) = overrides.get(dep.name.as_ref()) {
+                dep.overridden = true;
+                dep.markers = o.markers();
+            } else if let Some(p) = patches.get(dep.name.as_ref()) {
+                dep.patched = true;
+                dep.markers = p.markers();
+            } else if let Some(d) = dependencies.iter().find(|d| d.name() == dep.name) {
+                dep.markers = d.markers();
+            }
         }
 
-        result.finalize();
+        result.check_patched_requirements();
+        result.finalize(&config_requirements, prefers_lowest);
+        if !result.req_failures.is_empty() {
+            let r_version = self.r_version.major_minor();
+            result.explain_conflicts(dependencies.iter().map(|d| d.name()), |name| {
+                self.repositories
+                    .iter()
+                    .flat_map(|(db, _)| db.all_versions(name, &r_version))
+                    .cloned()
+                    .collect()
+            });
+        }
         result
     }
 }
@@ -760,6 +1164,10 @@ mod tests {
         source: Option<String>,
         binary: Option<String>,
         force_source: bool,
+        /// The name of an archive.rds file in src/tests/archives
+        archive: Option<String>,
+        /// The name of a current.rds file in src/tests/archives
+        current: Option<String>,
     }
 
     #[derive(Debug, Deserialize)]
@@ -787,6 +1195,13 @@ mod tests {
                     repo.binary_packages
                         .insert(r_version.major_minor(), dbs[&p].clone());
                 }
+
+                if let Some(p) = r.archive {
+                    repo.parse_archive(&fs::read(format!("src/tests/archives/{p}.rds")).unwrap());
+                }
+                if let Some(p) = r.current {
+                    repo.parse_current(&fs::read(format!("src/tests/archives/{p}.rds")).unwrap());
+                }
                 res.push((repo, r.force_source));
             }
             res
@@ -837,6 +1252,20 @@ mod tests {
         )
         .unwrap();
 
+        // And for an archived package
+        let url = get_archive_tarball_path(
+            &Url::parse("http://repo1/").unwrap(),
+            "rv.git.pkgA",
+            "0.0.4",
+        );
+        let url_path = cache.get_url_download_path(&url);
+        fs::create_dir_all(&url_path).unwrap();
+        fs::copy(
+            "src/tests/descriptions/rv.git.pkgA.DESCRIPTION",
+            url_path.join(DESCRIPTION_FILENAME),
+        )
+        .unwrap();
+
         // Add a custom package that has downloaded a binary but didn't compile it
         let paths = cache.get_package_paths(
             &Source::Repository {
@@ -890,23 +1319,30 @@ mod tests {
             };
             builtin_packages.insert("MASS".to_string(), mass);
 
-            let resolver = Resolver::new(
+            let mut resolver = Resolver::new(
                 Path::new("."),
                 &repositories,
                 repositories.iter().map(|(x, _)| x.url.as_str()).collect(),
                 &r_version,
+                &cache.system_info,
                 &builtin_packages,
                 Some(&lockfile),
                 config.packages_env_vars(),
             );
+            resolver.set_resolution_strategy(lockfile.resolution_strategy());
+            resolver.set_exclude_newer(config.exclude_newer());
 
-            let resolution = resolver.resolve(
-                config.dependencies(),
+            let mut resolution = resolver.resolve(
+                &config.all_dependencies(),
                 config.prefer_repositories_for(),
+                config.exclude(),
+                config.overrides(),
+                config.patches(),
                 &cache,
                 &FakeGit {},
                 &FakeHttp {},
             );
+            resolution.assign_groups(config.dependency_groups());
             // let new_lockfile = Lockfile::from_resolved(&r_version.major_minor(), resolution.found.clone());
             // println!("{}", new_lockfile.as_toml_string());
             let mut out = String::new();
@@ -943,8 +1379,138 @@ mod tests {
                     out.push('\n');
                 }
             }
+            if !resolution.conflicts.is_empty() {
+                out.push_str("--- conflicts --- \n");
+                for conflict in resolution.conflicts {
+                    out.push_str(&conflict.to_string());
+                    out.push('\n');
+                }
+            }
+            if !resolution.excluded_requirements.is_empty() {
+                out.push_str("--- excluded requirements --- \n");
+                for (excluded, required_by) in resolution.excluded_requirements {
+                    out.push_str(&format!("{excluded} required by {required_by}\n"));
+                }
+            }
+            if !resolution.patch_failures.is_empty() {
+                out.push_str("--- patch requirement failures --- \n");
+                for failure in resolution.patch_failures {
+                    out.push_str(&failure.to_string());
+                    out.push('\n');
+                }
+            }
             // Output has been compared with pkgr for the same PACKAGE file
             insta::assert_snapshot!(p.file_name().unwrap().to_string_lossy().to_string(), out);
         }
     }
+
+    #[test]
+    fn upgrading_some_packages_keeps_the_others_locked() {
+        let config = Config::from_str(
+            r#"
+[project]
+name = "test"
+r_version = "4.4"
+repositories = []
+dependencies = ["A", "C"]
+"#,
+        )
+        .unwrap();
+        let mut repo = RepositoryDatabase::new("http://cran/");
+        repo.parse_source(
+            r#"
+Package: A
+Version: 2.0.0
+Imports: B (>= 2.0.0)
+
+Package: B
+Version: 2.0.0
+
+Package: C
+Version: 1.1.0
+"#,
+        );
+        let repositories = vec![(repo, false)];
+        let lockfile = Lockfile::from_str(
+            r#"
+version = 2
+r_version = "4.4"
+
+[[packages]]
+name = "A"
+version = "1.0.0"
+source = { repository = "http://cran/" }
+force_source = false
+dependencies = ["B"]
+
+[[packages]]
+name = "B"
+version = "1.0.0"
+source = { repository = "http://cran/" }
+force_source = false
+dependencies = []
+
+[[packages]]
+name = "C"
+version = "1.0.0"
+source = { repository = "http://cran/" }
+force_source = false
+dependencies = []
+"#,
+        )
+        .unwrap();
+        let r_version = config.r_version().clone();
+        let (_cache_dir, cache) = setup_cache(&r_version);
+        let builtin_packages = HashMap::new();
+
+        let resolve = |upgrade: &[&str]| {
+            let upgrade_packages: HashSet<_> = upgrade.iter().map(|p| p.to_string()).collect();
+            let mut resolver = Resolver::new(
+                Path::new("."),
+                &repositories,
+                repositories.iter().map(|(x, _)| x.url.as_str()).collect(),
+                &r_version,
+                &cache.system_info,
+                &builtin_packages,
+                Some(&lockfile),
+                config.packages_env_vars(),
+            );
+            resolver.set_upgrade_packages(&upgrade_packages);
+            let resolution = resolver.resolve(
+                &config.all_dependencies(),
+                config.prefer_repositories_for(),
+                config.exclude(),
+                config.overrides(),
+                config.patches(),
+                &cache,
+                &FakeGit {},
+                &FakeHttp {},
+            );
+            let mut found: Vec<_> = resolution
+                .found
+                .iter()
+                .map(|d| format!("{}={}", d.name, d.version.original))
+                .collect();
+            found.sort();
+            let failed: Vec<_> = resolution.failed.iter().map(|d| d.to_string()).collect();
+            (found, failed)
+        };
+
+        let (found, failed) = resolve(&["C"]);
+        assert_eq!(found, vec!["A=1.0.0", "B=1.0.0", "C=1.1.0"]);
+        assert!(failed.is_empty());
+
+        // A 2.0.0 needs a version of B that is not the locked one
+        let (_, failed) = resolve(&["A"]);
+        assert_eq!(
+            failed,
+            vec![
+                "B (>= 2.0.0)  [required by: A]: locked to version 1.0.0, upgrade it as well or use `--with-deps`"
+            ]
+        );
+
+        let (found, failed) = resolve(&["A", "B"]);
+        assert_eq!(found, vec!["A=2.0.0", "B=2.0.0", "C=1.0.0"]);
+        assert!(failed.is_empty());
+    }
 }
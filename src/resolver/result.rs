@@ -1,6 +1,10 @@
+use crate::config::MAIN_GROUP;
+use crate::resolver::conflict::{CONFIG_REQUIRER, Conflict, ConflictRequirement};
 use crate::resolver::sat::DependencySolver;
-use crate::{ResolvedDependency, UnresolvedDependency};
-use std::collections::{HashMap, HashSet};
+use crate::{
+    ConfigDependency, ResolvedDependency, UnresolvedDependency, Version, VersionRequirement,
+};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::fmt;
 
 #[derive(Debug, Clone, PartialEq)]
@@ -15,11 +19,38 @@ impl fmt::Display for RequirementFailure {
     }
 }
 
+/// A version requirement that the patched version of a package doesn't satisfy
+#[derive(Debug, Clone, PartialEq)]
+pub struct PatchRequirementFailure {
+    pub package: String,
+    pub version: String,
+    pub required_by: String,
+    pub version_req: String,
+}
+
+impl fmt::Display for PatchRequirementFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "`{}` is patched to version {} but `{}` requires {} {}",
+            self.package, self.version, self.required_by, self.package, self.version_req
+        )
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Default)]
 pub struct Resolution<'d> {
     pub found: Vec<ResolvedDependency<'d>>,
     pub failed: Vec<UnresolvedDependency<'d>>,
     pub req_failures: HashMap<String, Vec<RequirementFailure>>,
+    /// The excluded packages that are a hard dependency (Depends/Imports/LinkingTo) of another
+    /// package: (excluded package, required by)
+    pub excluded_requirements: Vec<(String, String)>,
+    /// The requirements on patched packages that are not satisfied by the patch. They are only
+    /// reported since the patch wins.
+    pub patch_failures: Vec<PatchRequirementFailure>,
+    /// The explanation of each requirement failure, sorted by package name
+    pub conflicts: Vec<Conflict>,
 }
 
 impl<'d> Resolution<'d> {
@@ -40,13 +71,141 @@ impl<'d> Resolution<'d> {
             .any(|d| d.source.is_repo() && d.name == name)
     }
 
+    /// The indices in `found` of the packages required by those dependencies, directly or
+    /// transitively.
+    pub(crate) fn closure<'c>(
+        &self,
+        dependencies: impl IntoIterator<Item = &'c ConfigDependency>,
+    ) -> HashSet<usize> {
+        let mut by_name: HashMap<&str, Vec<usize>> = HashMap::new();
+        for (i, dep) in self.found.iter().enumerate() {
+            by_name.entry(dep.name.as_ref()).or_default().push(i);
+        }
+
+        let mut out = HashSet::new();
+        let mut visited = HashSet::new();
+        let mut stack: Vec<&str> = dependencies.into_iter().map(|d| d.name()).collect();
+        while let Some(name) = stack.pop() {
+            if !visited.insert(name) {
+                continue;
+            }
+            for &i in by_name.get(name).into_iter().flatten() {
+                out.insert(i);
+                let pkg = &self.found[i];
+                stack.extend(pkg.dependencies.iter().map(|d| d.name()));
+                if pkg.install_suggests {
+                    stack.extend(pkg.suggests.iter().map(|d| d.name()));
+                }
+            }
+        }
+        out
+    }
+
+    /// Tags each found package with the name of the dependency groups requiring it, directly
+    /// or transitively.
+    pub(crate) fn assign_groups<'c, D>(&mut self, groups: impl IntoIterator<Item = (&'c str, D)>)
+    where
+        D: IntoIterator<Item = &'c ConfigDependency>,
+    {
+        let mut assigned = vec![Vec::new(); self.found.len()];
+        for (group, deps) in groups {
+            for i in self.closure(deps) {
+                if !assigned[i].iter().any(|g| g == group) {
+                    assigned[i].push(group.to_string());
+                }
+            }
+        }
+
+        for (dep, mut groups) in self.found.iter_mut().zip(assigned) {
+            // Should not happen but we don't want to lose packages if that's the case
+            if groups.is_empty() {
+                groups.push(MAIN_GROUP.to_string());
+            }
+            dep.groups = groups;
+        }
+    }
+
     pub(crate) fn ignore(&mut self, name: &str) {
         if let Some(dep) = self.found.iter_mut().find(|dep| dep.name == name) {
             dep.ignored = true;
         }
     }
 
-    pub fn finalize(&mut self) {
+    /// Marks the excluded packages as ignored without any dependencies. The excluded packages
+    /// that could not be found are dropped instead of failing the resolution.
+    pub(crate) fn exclude(&mut self, excluded: &HashSet<&str>) {
+        for pkg in &self.found {
+            if excluded.contains(pkg.name.as_ref()) {
+                continue;
+            }
+            for dep in &pkg.dependencies {
+                if excluded.contains(dep.name()) {
+                    self.excluded_requirements
+                        .push((dep.name().to_string(), pkg.name.to_string()));
+                }
+            }
+        }
+        self.excluded_requirements.sort();
+        self.excluded_requirements.dedup();
+
+        let not_found: HashSet<_> = self
+            .failed
+            .iter()
+            .filter(|d| excluded.contains(d.name.as_ref()))
+            .map(|d| d.name.to_string())
+            .collect();
+        self.failed.retain(|d| !excluded.contains(d.name.as_ref()));
+
+        for pkg in &mut self.found {
+            if excluded.contains(pkg.name.as_ref()) {
+                pkg.ignored = true;
+                pkg.excluded = true;
+                pkg.dependencies.clear();
+                pkg.suggests.clear();
+            } else {
+                pkg.dependencies.retain(|d| !not_found.contains(d.name()));
+                pkg.suggests.retain(|d| !not_found.contains(d.name()));
+            }
+        }
+    }
+
+    /// Finds the version requirements on patched packages that the patches don't satisfy
+    pub(crate) fn check_patched_requirements(&mut self) {
+        for pkg in &self.found {
+            let deps = pkg.dependencies.iter().chain({
+                if pkg.install_suggests {
+                    pkg.suggests.iter()
+                } else {
+                    [].iter()
+                }
+            });
+            for dep in deps {
+                let Some(req) = dep.version_requirement() else {
+                    continue;
+                };
+                let unsatisfied = self
+                    .found
+                    .iter()
+                    .find(|p| p.patched && p.name == dep.name() && !req.is_satisfied(&p.version));
+                if let Some(patched) = unsatisfied {
+                    self.patch_failures.push(PatchRequirementFailure {
+                        package: patched.name.to_string(),
+                        version: patched.version.original.clone(),
+                        required_by: pkg.name.to_string(),
+                        version_req: req.to_string(),
+                    });
+                }
+            }
+        }
+    }
+
+    /// `config_requirements` are the version requirements coming from the rproject.toml and
+    /// `prefers_lowest` tells for which packages the lowest version found should be picked
+    pub fn finalize(
+        &mut self,
+        config_requirements: &[(&str, VersionRequirement)],
+        prefers_lowest: impl Fn(&str) -> bool,
+    ) {
         // First we go through the failed dependencies to see if something that would match was found
         // (for example it can happen if someone puts a dep in a git package and specify that dep
         // directly in rproject.toml instead of remotes)
@@ -71,11 +230,21 @@ impl<'d> Resolution<'d> {
         }
 
         let mut solver = DependencySolver::default();
+        for (name, req) in config_requirements {
+            // ignored packages are not given to the solver
+            if self.found.iter().any(|p| p.name == *name && p.ignored) {
+                continue;
+            }
+            solver.add_requirement(name, req, CONFIG_REQUIRER);
+        }
         for package in &self.found {
             if package.ignored {
                 continue;
             }
             solver.add_package(&package.name, &package.version);
+            if prefers_lowest(&package.name) {
+                solver.prefer_lowest(&package.name);
+            }
 
             let deps = package.dependencies.iter().chain({
                 if package.install_suggests {
@@ -86,6 +255,15 @@ impl<'d> Resolution<'d> {
             });
 
             for dep in deps {
+                // Same for requirements on ignored packages, and on overridden or patched packages
+                // since they win over what other packages require
+                if self
+                    .found
+                    .iter()
+                    .any(|p| p.name == dep.name() && (p.ignored || p.overridden || p.patched))
+                {
+                    continue;
+                }
                 if let Some(req) = dep.version_requirement() {
                     solver.add_requirement(dep.name(), req, &package.name);
                 }
@@ -136,6 +314,99 @@ impl<'d> Resolution<'d> {
         }
     }
 
+    /// The shortest chain of dependencies from one of the top level dependencies to each
+    /// package, both included
+    fn dependency_paths<'a>(
+        &self,
+        top_level: impl IntoIterator<Item = &'a str>,
+    ) -> HashMap<&str, Vec<&str>> {
+        let mut paths: HashMap<&str, Vec<&str>> = HashMap::new();
+        let mut queue = VecDeque::new();
+        for name in top_level {
+            if let Some(pkg) = self.found.iter().find(|p| p.name == name) {
+                paths.entry(pkg.name.as_ref()).or_insert_with(|| {
+                    queue.push_back(pkg.name.as_ref());
+                    vec![pkg.name.as_ref()]
+                });
+            }
+        }
+
+        while let Some(name) = queue.pop_front() {
+            let path = paths[name].clone();
+            for pkg in self.found.iter().filter(|p| p.name == name) {
+                for dep in pkg.all_dependencies_names() {
+                    if paths.contains_key(dep) {
+                        continue;
+                    }
+                    let mut dep_path = path.clone();
+                    dep_path.push(dep);
+                    paths.insert(dep, dep_path);
+                    queue.push_back(dep);
+                }
+            }
+        }
+        paths
+    }
+
+    /// Explains the requirement failures, `top_level` being the dependencies of the rproject.toml
+    /// and `repository_versions` giving the versions of a package the repositories have
+    pub(crate) fn explain_conflicts<'a>(
+        &mut self,
+        top_level: impl IntoIterator<Item = &'a str>,
+        repository_versions: impl Fn(&str) -> Vec<Version>,
+    ) {
+        let paths = self.dependency_paths(top_level);
+        let mut conflicts = Vec::with_capacity(self.req_failures.len());
+        for (package, failures) in &self.req_failures {
+            // Packages from git, local paths or URLs are not in the repositories
+            let mut versions = repository_versions(package);
+            versions.extend(
+                self.found
+                    .iter()
+                    .filter(|p| p.name == package.as_str())
+                    .map(|p| p.version.as_ref().clone()),
+            );
+            versions.sort();
+            versions.dedup();
+            let available_versions = versions.into_iter().map(|v| v.original).collect();
+
+            let requirements = failures
+                .iter()
+                .map(|failure| {
+                    if failure.required_by == CONFIG_REQUIRER {
+                        return ConflictRequirement {
+                            required_by: None,
+                            required_by_version: None,
+                            requirement: failure.version_req.clone(),
+                            required_through: Vec::new(),
+                        };
+                    }
+                    let name = failure.required_by.as_str();
+                    ConflictRequirement {
+                        required_by: Some(name.to_string()),
+                        required_by_version: self
+                            .found
+                            .iter()
+                            .find(|p| p.name == name)
+                            .map(|p| p.version.original.clone()),
+                        requirement: failure.version_req.clone(),
+                        required_through: paths
+                            .get(name)
+                            .map(|path| path.iter().map(|n| n.to_string()).collect())
+                            .unwrap_or_default(),
+                    }
+                })
+                .collect();
+            conflicts.push(Conflict::new(
+                package.clone(),
+                available_versions,
+                requirements,
+            ));
+        }
+        conflicts.sort_by(|a, b| a.package.cmp(&b.package));
+        self.conflicts = conflicts;
+    }
+
     pub fn is_success(&self) -> bool {
         self.failed.is_empty() && self.req_failures.is_empty()
     }
@@ -1,5 +1,5 @@
 use crate::{Version, VersionRequirement};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 /// Literals in CNF formula are represented as positive or negative integers
 type Literal = i32;
@@ -28,6 +28,8 @@ pub struct PackageRequirement<'d> {
 pub(crate) struct DependencySolver<'d> {
     packages: HashMap<&'d str, Vec<Package<'d>>>,
     requirements: Vec<PackageRequirement<'d>>,
+    /// The packages for which the lowest version is tried first
+    prefer_lowest: HashSet<&'d str>,
 }
 
 impl<'d> DependencySolver<'d> {
@@ -44,6 +46,10 @@ impl<'d> DependencySolver<'d> {
         }
     }
 
+    pub fn prefer_lowest(&mut self, name: &'d str) {
+        self.prefer_lowest.insert(name);
+    }
+
     pub fn add_requirement(
         &mut self,
         package: &'d str,
@@ -289,7 +295,38 @@ impl<'d> DependencySolver<'d> {
         Some(result)
     }
 
-    fn solve_sat_iterative(&self, formula: &Formula, num_vars: i32) -> HashMap<Literal, bool> {
+    /// The variables of each version of the packages preferring their lowest version, mapped to
+    /// the variables of all the versions of that package sorted from the lowest
+    fn get_lowest_first(
+        &self,
+        pkg_version_to_var: &HashMap<(&'d str, &'d Version), Literal>,
+    ) -> HashMap<Literal, Vec<Literal>> {
+        let mut out = HashMap::new();
+        for name in &self.prefer_lowest {
+            let Some(packages) = self.packages.get(name) else {
+                continue;
+            };
+            let mut versions: Vec<_> = packages.iter().map(|p| p.version).collect();
+            versions.sort();
+            let vars: Vec<_> = versions
+                .into_iter()
+                .filter_map(|v| pkg_version_to_var.get(&(*name, v)).copied())
+                .collect();
+            for var in &vars {
+                out.insert(*var, vars.clone());
+            }
+        }
+        out
+    }
+
+    /// `lowest_first` is used to pick which version of a package to try first, see
+    /// `get_lowest_first`
+    fn solve_sat_iterative(
+        &self,
+        formula: &Formula,
+        num_vars: i32,
+        lowest_first: &HashMap<Literal, Vec<Literal>>,
+    ) -> HashMap<Literal, bool> {
         let mut assignment = HashMap::new();
         let mut decision_stack = Vec::new();
         let max_iterations = 100000;
@@ -350,6 +387,11 @@ impl<'d> DependencySolver<'d> {
             }
 
             let next_var = self.get_most_constrained_variable(formula, &assignment, num_vars);
+            let next_var = lowest_first
+                .get(&next_var)
+                .and_then(|vars| vars.iter().find(|v| !assignment.contains_key(v)))
+                .copied()
+                .unwrap_or(next_var);
 
             // If we couldn't find an unassigned variable, but not all are assigned, something's wrong
             if next_var == 0 && (assignment.len() as i32) < num_vars {
@@ -429,7 +471,7 @@ impl<'d> DependencySolver<'d> {
             // Check if still unsatisfiable
             let num_vars = self.packages.values().fold(0, |acc, pkgs| acc + pkgs.len()) as i32;
             if self
-                .solve_sat_iterative(&all_test_clauses, num_vars)
+                .solve_sat_iterative(&all_test_clauses, num_vars, &HashMap::new())
                 .is_empty()
             {
                 // Still unsatisfiable, we can remove this clause from our MUS
@@ -485,11 +527,14 @@ impl<'d> DependencySolver<'d> {
         let pkg_version_to_var = self.get_variable_mappings();
         let (clauses, clauses_to_req) = self.create_clauses(&pkg_version_to_var);
 
+        let lowest_first = self.get_lowest_first(&pkg_version_to_var);
+
         let var_to_pkg_version: HashMap<_, _> =
             pkg_version_to_var.iter().map(|(k, v)| (v, k)).collect();
         let start_time = std::time::Instant::now();
         log::debug!("Starting SAT solving");
-        let assignment = self.solve_sat_iterative(&clauses, var_to_pkg_version.len() as i32);
+        let assignment =
+            self.solve_sat_iterative(&clauses, var_to_pkg_version.len() as i32, &lowest_first);
 
         // No solution exists
         if assignment.is_empty() {
@@ -589,6 +634,26 @@ mod tests {
         assert_eq!(result["A"], &packages[1].1);
     }
 
+    #[test]
+    fn prefer_lowest_version_satisfying_reqs() {
+        let packages = vec![
+            ("A", Version::from_str("2.0.0").unwrap()),
+            ("A", Version::from_str("1.5.0").unwrap()),
+            ("A", Version::from_str("1.0.0").unwrap()),
+            ("B", Version::from_str("1.1.0").unwrap()),
+        ];
+        let requirements = vec![(
+            "A",
+            VersionRequirement::from_str("(>= 1.2.0)").unwrap(),
+            "B",
+        )];
+        let mut resolver = get_resolver(&packages, &requirements);
+        resolver.prefer_lowest("A");
+        let result = resolver.solve().unwrap();
+        assert_eq!(result.len(), 2);
+        assert_eq!(result["A"], &packages[1].1);
+    }
+
     #[test]
     fn version_req_error() {
         let packages = vec![
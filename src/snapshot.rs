@@ -0,0 +1,146 @@
+//! Snapshot dates of Posit Package Manager repositories.
+//! PPM serves every repository at a given date with URLs like `https://p3m.dev/cran/2024-06-01`,
+//! `latest` being the current state. Setting a snapshot replaces that last segment of the URL so
+//! everything, including the `__linux__/<codename>/<date>` binary URLs, uses the dated repository.
+
+use toml_edit::{DocumentMut, Item, Value};
+use url::Url;
+
+const LATEST: &str = "latest";
+
+/// Whether the string is a date in the `YYYY-MM-DD` format
+pub(crate) fn is_valid_snapshot_date(s: &str) -> bool {
+    let parts: Vec<_> = s.split('-').collect();
+    let [year, month, day] = parts.as_slice() else {
+        return false;
+    };
+    let number = |s: &str, len: usize| {
+        if s.len() == len && s.chars().all(|c| c.is_ascii_digit()) {
+            s.parse::<u32>().ok()
+        } else {
+            None
+        }
+    };
+
+    matches!(
+        (number(year, 4), number(month, 2), number(day, 2)),
+        (Some(_), Some(1..=12), Some(1..=31))
+    )
+}
+
+/// The URL of the repository at that date, if the URL is a PPM one ending with `latest` or a date
+pub(crate) fn snapshot_url(url: &Url, date: &str) -> Option<Url> {
+    let edition = url.path_segments()?.next_back()?;
+    if edition != LATEST && !is_valid_snapshot_date(edition) {
+        return None;
+    }
+
+    let mut new_url = url.clone();
+    new_url.path_segments_mut().ok()?.pop().push(date);
+    Some(new_url)
+}
+
+/// Same as `snapshot_url` but for a URL template that can contain env vars, which can't be parsed
+pub(crate) fn snapshot_template(template: &str, date: &str) -> Option<String> {
+    let (base, edition) = template.trim_end_matches('/').rsplit_once('/')?;
+    if edition != LATEST && !is_valid_snapshot_date(edition) {
+        return None;
+    }
+    Some(format!("{base}/{date}"))
+}
+
+/// The URL of the repository without its snapshot date, to compare repositories regardless
+/// of their snapshot
+pub(crate) fn without_snapshot(url: &Url) -> Url {
+    snapshot_url(url, LATEST).unwrap_or_else(|| url.clone())
+}
+
+/// Whether the URL is a repository at a given date, whose content will never change
+pub(crate) fn is_dated_snapshot(url: &str) -> bool {
+    url.trim_end_matches('/')
+        .rsplit('/')
+        .next()
+        .is_some_and(is_valid_snapshot_date)
+}
+
+/// Moves the config to a new snapshot date: the project default is set to it and the
+/// repositories having their own snapshot are updated to it.
+pub fn set_snapshot(config_doc: &mut DocumentMut, date: &str) -> Result<(), String> {
+    if !is_valid_snapshot_date(date) {
+        return Err(format!(
+            "Invalid snapshot date `{date}`, it should be like `2024-06-01`"
+        ));
+    }
+
+    let project = config_doc
+        .get_mut("project")
+        .and_then(|p| p.as_table_like_mut())
+        .ok_or("The config has no `[project]` section")?;
+    project.insert("snapshot", Item::Value(Value::from(date)));
+
+    if let Some(repositories) = project
+        .get_mut("repositories")
+        .and_then(|r| r.as_array_mut())
+    {
+        for repo in repositories.iter_mut() {
+            if let Some(repo) = repo.as_inline_table_mut()
+                && let Some(snapshot) = repo.get_mut("snapshot")
+            {
+                let decor = snapshot.decor().clone();
+                *snapshot = Value::from(date);
+                *snapshot.decor_mut() = decor;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_validate_dates() {
+        assert!(is_valid_snapshot_date("2024-06-01"));
+        assert!(!is_valid_snapshot_date("2024-6-1"));
+        assert!(!is_valid_snapshot_date("2024-13-01"));
+        assert!(!is_valid_snapshot_date("latest"));
+    }
+
+    #[test]
+    fn can_get_snapshot_urls() {
+        let url = Url::parse("https://packagemanager.posit.co/cran/latest").unwrap();
+        assert_eq!(
+            snapshot_url(&url, "2024-06-01").unwrap().as_str(),
+            "https://packagemanager.posit.co/cran/2024-06-01"
+        );
+        let url = Url::parse("https://p3m.dev/cran/2023-01-10").unwrap();
+        assert_eq!(
+            snapshot_url(&url, "2024-06-01").unwrap().as_str(),
+            "https://p3m.dev/cran/2024-06-01"
+        );
+        let url = Url::parse("https://cran.r-project.org").unwrap();
+        assert_eq!(snapshot_url(&url, "2024-06-01"), None);
+        assert_eq!(
+            snapshot_template("https://${PPM_HOST}/cran/latest", "2024-06-01").unwrap(),
+            "https://${PPM_HOST}/cran/2024-06-01"
+        );
+    }
+
+    #[test]
+    fn can_set_snapshot() {
+        let mut doc = r#"[project]
+name = "test"
+r_version = "4.4"
+repositories = [
+    { alias = "ppm", url = "https://p3m.dev/cran/latest", snapshot = "2024-01-01" }, # pinned
+    { alias = "cran", url = "https://cran.r-project.org" },
+]
+"#
+        .parse::<DocumentMut>()
+        .unwrap();
+        set_snapshot(&mut doc, "2024-06-01").unwrap();
+        insta::assert_snapshot!(doc.to_string());
+    }
+}
@@ -33,6 +33,10 @@ impl<'a> BuildPlan<'a> {
 
             let mut queue = VecDeque::from_iter(dep.dependencies.iter().map(|x| x.name()));
             while let Some(dep_name) = queue.pop_front() {
+                // Ignored packages are never installed so nothing should wait for them
+                if by_name[dep_name].ignored {
+                    continue;
+                }
                 all_deps.insert(dep_name);
                 for d in &by_name[dep_name].dependencies {
                     if !all_deps.contains(d.name()) {
@@ -147,6 +151,14 @@ mod tests {
             local_resolved_path: None,
             env_vars: HashMap::new(),
             ignored: false,
+            excluded: false,
+            overridden: false,
+            patched: false,
+            groups: Vec::new(),
+            markers: Default::default(),
+            md5sum: None,
+            sha256: None,
+            binary_md5sum: None,
         }
     }
 
@@ -202,4 +214,17 @@ mod tests {
         // Calling it again doesn't change anything
         assert_eq!(plan.get(), BuildStep::Done);
     }
+
+    #[test]
+    fn does_not_wait_for_ignored_packages() {
+        let mut excluded = get_resolved_dep("B", vec![]);
+        excluded.ignored = true;
+        let deps = vec![get_resolved_dep("A", vec!["B"]), excluded];
+
+        let mut plan = BuildPlan::new(&deps);
+        assert_eq!(plan.num_to_install(), 1);
+        assert_eq!(plan.get(), BuildStep::Install(&deps[0]));
+        plan.mark_installed("A");
+        assert_eq!(plan.get(), BuildStep::Done);
+    }
 }
@@ -1,3 +1,4 @@
+use crate::checksum::ChecksumMismatch;
 use crate::http::HttpError;
 use crate::r_cmd::InstallError;
 use crate::sync::LinkError;
@@ -31,6 +32,13 @@ pub enum SyncErrorKind {
     PackagesLoadedError(String),
     #[error("Invalid package found at `{path}`: {error}")]
     InvalidPackage { path: PathBuf, error: String },
+    #[error(
+        "Checksum mismatch for `{url}`: {error}. The tarball may have been tampered with or the repository changed it since it was listed or locked, run `rv upgrade <package>` if the change is expected."
+    )]
+    ChecksumMismatch {
+        url: String,
+        error: ChecksumMismatch,
+    },
 }
 
 impl From<InstallError> for SyncError {
@@ -5,7 +5,9 @@ use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
 use crate::conda::CondaManager;
-use crate::consts::{BASE_PACKAGES, NO_CHECK_OPEN_FILE_ENV_VAR_NAME, RECOMMENDED_PACKAGES};
+use crate::consts::{
+    BASE_PACKAGES, NO_CHECK_OPEN_FILE_ENV_VAR_NAME, RECOMMENDED_PACKAGES, STAGING_DIR_NAME,
+};
 use crate::lockfile::Source;
 use crate::package::PackageType;
 #[cfg(feature = "cli")]
@@ -17,7 +19,7 @@ use crate::sync::{LinkMode, sources};
 use crate::system_req::{self, SysInstallationStatus};
 use crate::utils::{get_max_workers, is_env_var_truthy};
 use crate::{
-    BuildPlan, BuildStep, Cancellation, Context, GitExecutor, RCmd, ResolvedDependency,
+    BuildPlan, BuildStep, Cancellation, Context, GitExecutor, Library, RCmd, ResolvedDependency,
     get_tarball_urls,
 };
 use crossbeam::{channel, thread};
@@ -83,6 +85,8 @@ fn get_all_packages_in_use(path: &Path) -> HashMap<(String, u32), HashSet<String
 #[derive(Debug)]
 pub struct SyncHandler<'a> {
     context: &'a Context,
+    /// The library to sync, the context library unless set with `set_library`
+    library: &'a Library,
     save_install_logs_in: Option<PathBuf>,
     dry_run: bool,
     show_progress_bar: bool,
@@ -94,6 +98,7 @@ impl<'a> SyncHandler<'a> {
     pub fn new(context: &'a Context, save_install_logs_in: Option<PathBuf>) -> Self {
         Self {
             context,
+            library: &context.library,
             save_install_logs_in,
             dry_run: false,
             show_progress_bar: false,
@@ -115,6 +120,15 @@ impl<'a> SyncHandler<'a> {
         self.max_workers = max_workers;
     }
 
+    /// Sync into another library than the context one, eg for a workspace member
+    pub fn set_library(&mut self, library: &'a Library) {
+        self.library = library;
+    }
+
+    fn staging_path(&self) -> PathBuf {
+        self.library.path.join(STAGING_DIR_NAME)
+    }
+
     pub fn set_uses_lockfile(&mut self, uses_lockfile: bool) {
         self.uses_lockfile = uses_lockfile;
     }
@@ -194,17 +208,19 @@ impl<'a> SyncHandler<'a> {
                             .cache
                             .get_tarball_path(&dep.name, &dep.version.original);
 
-                        let result = crate::http::download_to_file(
-                            &tarball_url.source,
-                            &tarball_path,
-                        )
-                        .or_else(|e| {
-                            log::warn!(
-                                "Failed to download source tarball from {}: {e:?}, trying archive",
-                                tarball_url.source
-                            );
-                            crate::http::download_to_file(&tarball_url.archive, &tarball_path)
-                        });
+                        let result = self
+                            .context
+                            .http
+                            .download_to_file(&tarball_url.source, &tarball_path)
+                            .or_else(|e| {
+                                log::warn!(
+                                    "Failed to download source tarball from {}: {e:?}, trying archive",
+                                    tarball_url.source
+                                );
+                                self.context
+                                    .http
+                                    .download_to_file(&tarball_url.archive, &tarball_path)
+                            });
 
                         // Send result with name for tracking
                         match result {
@@ -276,8 +292,8 @@ impl<'a> SyncHandler<'a> {
         LinkMode::link_files(
             Some(LinkMode::Copy),
             &dep.name,
-            self.context.library.path().join(dep.name.as_ref()),
-            self.context.staging_path().join(dep.name.as_ref()),
+            self.library.path().join(dep.name.as_ref()),
+            self.staging_path().join(dep.name.as_ref()),
         )?;
 
         Ok(())
@@ -294,8 +310,8 @@ impl<'a> SyncHandler<'a> {
         }
         // we want the staging to take precedence over the library, but still have
         // the library in the paths for lookup
-        let staging_path = self.context.staging_path();
-        let library_dirs = vec![&staging_path, self.context.library.path()];
+        let staging_path = self.staging_path();
+        let library_dirs = vec![&staging_path, self.library.path()];
         let configure_args = self.get_configure_args(&dep.name);
 
         match dep.source {
@@ -303,6 +319,7 @@ impl<'a> SyncHandler<'a> {
                 dep,
                 &library_dirs,
                 &self.context.cache,
+                &self.context.http,
                 r_cmd,
                 &configure_args,
                 cancellation,
@@ -358,11 +375,11 @@ impl<'a> SyncHandler<'a> {
         let mut preserved_packages: Vec<&str> = RECOMMENDED_PACKAGES.to_vec();
         preserved_packages.extend(BASE_PACKAGES.as_slice());
 
-        for name in self.context.library.packages.keys() {
+        for name in self.library.packages.keys() {
             if let Some(dep) = deps_by_name.get(name.as_str()) {
                 // If the library contains the dep, we also want it to be resolved from the lockfile, otherwise we cannot trust its source
                 // Additionally, any package in the library that is ignored, needs to be removed
-                if self.context.library.contains_package(dep) && !dep.ignored {
+                if self.library.contains_package(dep) && !dep.ignored {
                     match &dep.source {
                         Source::Repository { .. } => {
                             if !self.uses_lockfile || dep.from_lockfile {
@@ -404,7 +421,7 @@ impl<'a> SyncHandler<'a> {
         }
 
         // Lastly, remove any package that we can't really access
-        for name in &self.context.library.broken {
+        for name in &self.library.broken {
             log::warn!("Package {name} in library is broken");
             deps_to_remove.insert((name.as_str(), false));
         }
@@ -420,7 +437,7 @@ impl<'a> SyncHandler<'a> {
         // Clean up at all times, even with a dry run
         let cancellation = Arc::new(Cancellation::default());
 
-        let staging_path = self.context.staging_path();
+        let staging_path = self.staging_path();
         #[cfg(feature = "cli")]
         {
             let cancellation_clone = Arc::clone(&cancellation);
@@ -449,7 +466,7 @@ impl<'a> SyncHandler<'a> {
         if staging_path.is_dir() {
             fs::remove_dir_all(&staging_path)?;
         }
-        fs::create_dir_all(self.context.library.path())?;
+        fs::create_dir_all(self.library.path())?;
 
         let mut sync_changes = Vec::new();
 
@@ -458,7 +475,7 @@ impl<'a> SyncHandler<'a> {
         let (deps_seen, deps_to_copy, deps_to_remove) = self.compare_with_local_library(deps);
         let needs_sync = deps_seen.len() != num_deps_to_install;
         let packages_loaded = if !deps_to_remove.is_empty() {
-            get_all_packages_in_use(self.context.library.path())
+            get_all_packages_in_use(self.library.path())
         } else {
             HashMap::new()
         };
@@ -495,7 +512,7 @@ impl<'a> SyncHandler<'a> {
 
             // Only actually remove the deps if we are not going to do any other changes.
             if !needs_sync {
-                let p = self.context.library.path().join(dir_name);
+                let p = self.library.path().join(dir_name);
                 if !self.dry_run && *notify {
                     log::debug!("Removing {dir_name} from library");
                     fs::remove_dir_all(&p)?;
@@ -525,7 +542,7 @@ impl<'a> SyncHandler<'a> {
         // Then we mark the deps seen so they won't be installed into the staging dir
         for d in &deps_seen {
             // builtin packages will not be in the library
-            let in_lib = self.context.library.path().join(d);
+            let in_lib = self.library.path().join(d);
             if in_lib.is_dir() {
                 plan.mark_installed(d);
             }
@@ -808,7 +825,7 @@ impl<'a> SyncHandler<'a> {
 
             // mv new packages to the library and delete the ones that need to be removed
             for (name, notify) in deps_to_remove {
-                let p = self.context.library.path().join(name);
+                let p = self.library.path().join(name);
                 if !self.dry_run && notify {
                     log::debug!("Removing {name} from library");
                     fs::remove_dir_all(&p)?;
@@ -824,7 +841,7 @@ impl<'a> SyncHandler<'a> {
                 let path = entry.path();
                 let name = path.file_name().unwrap().to_str().unwrap().to_string();
                 if !deps_seen.contains(name.as_str()) {
-                    let out = self.context.library.path().join(&name);
+                    let out = self.library.path().join(&name);
                     if out.is_dir() {
                         fs::remove_dir_all(&out)?;
                     }
@@ -1005,7 +1022,7 @@ impl<'a> SyncHandler<'a> {
 
         log::info!("Attempting pak fallback for package: {} (git: {:?})", package_name, git_url);
 
-        let lib_path = self.context.library_path();
+        let lib_path = self.library.path();
 
         // 使用 RCommandLine 的字段来正确处理 conda 环境
         let r_cmd = &self.context.r_cmd;
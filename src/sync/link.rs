@@ -9,9 +9,9 @@ use std::env;
 use std::path::{Path, PathBuf};
 use walkdir::WalkDir;
 
+use crate::consts::LINK_MODE_ENV_VAR_NAME;
 use crate::fs::{copy_folder, is_network_fs};
-
-const LINK_ENV_NAME: &str = "RV_LINK_MODE";
+use crate::global_config::global_config;
 
 #[derive(thiserror::Error, Debug)]
 pub enum LinkError {
@@ -60,18 +60,30 @@ impl LinkMode {
         }
     }
 
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "copy" => Some(Self::Copy),
+            "clone" => Some(Self::Clone),
+            "hardlink" => Some(Self::Hardlink),
+            "symlink" => Some(Self::Symlink),
+            _ => None,
+        }
+    }
+
     /// Determine what link mode will be used for a given destination path.
-    /// This takes into account environment variables and network filesystem detection.
+    /// This takes into account environment variables, the global config and network filesystem
+    /// detection.
     pub fn effective_mode(destination: impl AsRef<Path>) -> Self {
         // Check environment variable first
-        if let Ok(val) = env::var(LINK_ENV_NAME) {
-            match val.to_lowercase().as_str() {
-                "copy" => return Self::Copy,
-                "clone" => return Self::Clone,
-                "hardlink" => return Self::Hardlink,
-                "symlink" => return Self::Symlink,
-                _ => {}
-            }
+        if let Some(mode) = env::var(LINK_MODE_ENV_VAR_NAME)
+            .ok()
+            .and_then(|val| Self::from_name(&val))
+        {
+            return mode;
+        }
+
+        if let Some(mode) = global_config().link_mode() {
+            return mode;
         }
 
         // Check if destination is on a network filesystem
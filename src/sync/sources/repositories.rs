@@ -1,25 +1,84 @@
 //! Download and install packages from repositories like CRAN, posit etc
 
 use fs_err as fs;
-use std::io::Write;
+use std::io::{Cursor, Write};
 use std::path::Path;
 use std::sync::Arc;
+use url::Url;
 
 use crate::cache::InstallationStatus;
-use crate::consts::BUILT_FROM_SOURCE_FILENAME;
-use crate::http::Http;
+use crate::checksum::verify_checksums;
+use crate::consts::{BUILT_FROM_SOURCE_FILENAME, SOURCE_SHA256_FILENAME};
+use crate::fs::untar_archive;
+use crate::http::{Http, HttpError, redact_url};
 use crate::package::PackageType;
 use crate::sync::LinkMode;
-use crate::sync::errors::SyncError;
+use crate::sync::errors::{SyncError, SyncErrorKind};
 use crate::{
     Cancellation, DiskCache, HttpDownload, RCmd, ResolvedDependency, get_tarball_urls,
     is_binary_package,
 };
 
+/// Downloads the source tarball of a package and extracts it in the given destination, after
+/// checking it against the hashes we have for it. The SHA-256 of the tarball is kept next to what
+/// was extracted so it can be written to the lockfile.
+fn download_source_tarball(
+    http: &impl HttpDownload,
+    url: &Url,
+    pkg: &ResolvedDependency,
+    destination: &Path,
+) -> Result<(), SyncError> {
+    let mut bytes = Vec::new();
+    http.download(url, &mut bytes, vec![])?;
+    let sha256 = verify_checksums(&bytes, pkg.md5sum.as_deref(), pkg.sha256.as_deref()).map_err(
+        |error| SyncError {
+            source: SyncErrorKind::ChecksumMismatch {
+                url: redact_url(url.as_str()),
+                error,
+            },
+        },
+    )?;
+    untar_archive(Cursor::new(bytes), destination, false)
+        .map_err(|e| HttpError::from_io(url.as_str(), e))?;
+    fs::write(destination.join(SOURCE_SHA256_FILENAME), sha256)?;
+    Ok(())
+}
+
+/// Downloads the binary tarball of a package and extracts it in the given destination, after
+/// checking it against the MD5 listed by the repository. That MD5 is only known if the
+/// repository databases were loaded during resolution since the lockfile doesn't have the hashes
+/// of binaries: they are different on each system.
+fn download_binary_tarball(
+    http: &impl HttpDownload,
+    url: &Url,
+    pkg: &ResolvedDependency,
+    destination: &Path,
+) -> Result<(), SyncError> {
+    let mut bytes = Vec::new();
+    http.download(url, &mut bytes, vec![])?;
+    if pkg.binary_md5sum.is_none() {
+        log::warn!(
+            "The binary of {} ({}) was not verified: no checksum is known for it",
+            pkg.name,
+            pkg.version.original
+        );
+    }
+    verify_checksums(&bytes, pkg.binary_md5sum.as_deref(), None).map_err(|error| SyncError {
+        source: SyncErrorKind::ChecksumMismatch {
+            url: redact_url(url.as_str()),
+            error,
+        },
+    })?;
+    untar_archive(Cursor::new(bytes), destination, false)
+        .map_err(|e| HttpError::from_io(url.as_str(), e))?;
+    Ok(())
+}
+
 pub(crate) fn install_package(
     pkg: &ResolvedDependency,
     library_dirs: &[&Path],
     cache: &DiskCache,
+    http: &Http,
     r_cmd: &impl RCmd,
     configure_args: &[String],
     cancellation: Arc<Cancellation>,
@@ -81,7 +140,6 @@ pub(crate) fn install_package(
 
             let tarball_url = get_tarball_urls(pkg, &cache.r_version, &cache.system_info)
                 .expect("Dependency has source Repository");
-            let http = Http {};
 
             let download_and_install_source_or_archive = || -> Result<(), SyncError> {
                 log::debug!(
@@ -90,8 +148,12 @@ pub(crate) fn install_package(
                     pkg.version.original
                 );
                 if let Err(e) =
-                    http.download_and_untar(&tarball_url.source, &pkg_paths.source, false, None)
+                    download_source_tarball(http, &tarball_url.source, pkg, &pkg_paths.source)
                 {
+                    // A tarball not matching the lockfile should not be replaced by another one
+                    if matches!(e.source, SyncErrorKind::ChecksumMismatch { .. }) {
+                        return Err(e);
+                    }
                     log::warn!(
                         "Failed to download/untar source package from {}: {e:?}, falling back to {}",
                         tarball_url.source,
@@ -102,7 +164,7 @@ pub(crate) fn install_package(
                         pkg.name,
                         pkg.version.original
                     );
-                    http.download_and_untar(&tarball_url.archive, &pkg_paths.source, false, None)?;
+                    download_source_tarball(http, &tarball_url.archive, pkg, &pkg_paths.source)?;
                 }
                 compile_package()?;
                 Ok(())
@@ -112,12 +174,16 @@ pub(crate) fn install_package(
                 download_and_install_source_or_archive()?;
             } else {
                 // If we get an error doing the binary download, fall back to source
-                if let Err(e) = http.download_and_untar(
+                if let Err(e) = download_binary_tarball(
+                    http,
                     &tarball_url.binary.clone().unwrap(),
+                    pkg,
                     &pkg_paths.binary,
-                    false,
-                    None,
                 ) {
+                    // A binary not matching what the repository lists should not be replaced
+                    if matches!(e.source, SyncErrorKind::ChecksumMismatch { .. }) {
+                        return Err(e);
+                    }
                     log::warn!(
                         "Failed to download/untar binary package from {}: {e:?}, falling back to {}",
                         tarball_url.binary.clone().unwrap(),
@@ -130,7 +196,7 @@ pub(crate) fn install_package(
                     let bin_path = pkg_paths.binary.join(pkg.name.as_ref());
                     if !is_binary_package(&bin_path, pkg.name.as_ref()).map_err(|err| {
                         SyncError {
-                            source: crate::sync::errors::SyncErrorKind::InvalidPackage {
+                            source: SyncErrorKind::InvalidPackage {
                                 path: bin_path,
                                 error: err.to_string(),
                             },
@@ -161,3 +227,85 @@ pub(crate) fn install_package(
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Lockfile;
+    use crate::checksum::md5_hex;
+    use flate2::Compression;
+    use flate2::write::GzEncoder;
+    use std::path::PathBuf;
+    use std::str::FromStr;
+
+    struct FakeHttp(Vec<u8>);
+
+    impl HttpDownload for FakeHttp {
+        fn download<W: Write>(
+            &self,
+            _: &Url,
+            writer: &mut W,
+            _: Vec<(&str, String)>,
+        ) -> Result<u64, HttpError> {
+            writer.write_all(&self.0).unwrap();
+            Ok(self.0.len() as u64)
+        }
+
+        fn download_and_untar(
+            &self,
+            _: &Url,
+            _: impl AsRef<Path>,
+            _: bool,
+            _: Option<&Path>,
+        ) -> Result<(Option<PathBuf>, String), HttpError> {
+            unreachable!()
+        }
+    }
+
+    fn tarball() -> Vec<u8> {
+        let mut builder = tar::Builder::new(GzEncoder::new(Vec::new(), Compression::default()));
+        let content = b"Package: cli\nVersion: 3.6.3\n";
+        let mut header = tar::Header::new_gnu();
+        header.set_size(content.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder
+            .append_data(&mut header, "cli/DESCRIPTION", &content[..])
+            .unwrap();
+        builder.into_inner().unwrap().finish().unwrap()
+    }
+
+    #[test]
+    fn verifies_binaries_against_repository_md5() {
+        let lockfile = Lockfile::from_str(
+            r#"version = 2
+r_version = "4.4"
+
+[[packages]]
+name = "cli"
+version = "3.6.3"
+source = { repository = "https://cran.r-project.org/" }
+force_source = false
+dependencies = []
+"#,
+        )
+        .unwrap();
+        let mut pkg = ResolvedDependency::from_locked_package(
+            &lockfile.packages()[0],
+            InstallationStatus::Absent,
+        );
+        let content = tarball();
+        let http = FakeHttp(content.clone());
+        let url = Url::parse("https://cran.r-project.org/bin/cli_3.6.3.tgz").unwrap();
+        let tmp = tempfile::tempdir().unwrap();
+
+        pkg.binary_md5sum = Some(md5_hex(b"something else"));
+        let err = download_binary_tarball(&http, &url, &pkg, tmp.path()).unwrap_err();
+        assert!(matches!(err.source, SyncErrorKind::ChecksumMismatch { .. }));
+        assert!(!tmp.path().join("cli").exists());
+
+        pkg.binary_md5sum = Some(md5_hex(&content));
+        download_binary_tarball(&http, &url, &pkg, tmp.path()).unwrap();
+        assert!(tmp.path().join("cli").join("DESCRIPTION").is_file());
+    }
+}
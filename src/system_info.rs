@@ -7,6 +7,23 @@ use os_info::{Type, Version};
 use serde::Serialize;
 use std::fmt;
 
+/// The Linux distributions we recognize, see `SystemInfo::from_os_info`
+pub(crate) const LINUX_DISTRIBUTIONS: &[&str] = &[
+    "ubuntu",
+    "fedora",
+    "arch",
+    "amazon",
+    "debian",
+    "pop",
+    "centos",
+    "almalinux",
+    "opensuse",
+    "redhat",
+    "rocky",
+    "suse",
+    "gentoo",
+];
+
 /// For R we only care about Windows, MacOS and Linux
 #[derive(Debug, PartialEq, Clone, Copy, Serialize)]
 pub enum OsType {
@@ -27,6 +44,14 @@ impl OsType {
         }
     }
 
+    /// The Linux distribution of that name, if it is one we know about
+    pub fn linux(distribution: &str) -> Option<Self> {
+        LINUX_DISTRIBUTIONS
+            .iter()
+            .find(|d| **d == distribution)
+            .map(|d| OsType::Linux(d))
+    }
+
     pub fn tarball_extension(&self) -> &'static str {
         match self {
             OsType::Windows => "zip",
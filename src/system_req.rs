@@ -1,4 +1,5 @@
-use crate::{SystemInfo, http};
+use crate::http::{HttpError, HttpErrorKind};
+use crate::{Http, SystemInfo, http};
 use std::collections::{HashMap, HashSet};
 use std::fmt;
 use std::fmt::Formatter;
@@ -9,9 +10,11 @@ use url::Url;
 use which::which;
 
 use crate::consts::{SYS_DEPS_CHECK_IN_PATH_ENV_VAR_NAME, SYS_REQ_URL_ENV_VAR_NAME};
+use crate::global_config::global_config;
 
 /// https://rserver.tradecraftclinical.com/rspm/__api__/swagger/index.html#/default/get_repos__id__sysreqs
-const SYSTEM_REQ_API_URL: &str = "https://packagemanager.posit.co/__api__/repos/cran/sysreqs";
+pub(crate) const SYSTEM_REQ_API_URL: &str =
+    "https://packagemanager.posit.co/__api__/repos/cran/sysreqs";
 /// Some tools might not be installed by the package manager
 const KNOWN_THINGS_IN_PATH: &[&str] = &[
     "rustc",
@@ -74,7 +77,12 @@ struct Response {
 }
 
 fn get_sysreq_url() -> String {
-    std::env::var(SYS_REQ_URL_ENV_VAR_NAME).unwrap_or_else(|_| SYSTEM_REQ_API_URL.to_string())
+    std::env::var(SYS_REQ_URL_ENV_VAR_NAME).unwrap_or_else(|_| {
+        global_config()
+            .sys_req_url()
+            .unwrap_or(SYSTEM_REQ_API_URL)
+            .to_string()
+    })
 }
 
 pub fn is_supported(system_info: &SystemInfo) -> bool {
@@ -94,9 +102,14 @@ pub fn is_supported(system_info: &SystemInfo) -> bool {
 }
 
 /// This should only be run on Linux
-pub fn get_system_requirements(system_info: &SystemInfo) -> HashMap<String, Vec<String>> {
-    let agent = http::get_agent();
+pub fn get_system_requirements(
+    system_info: &SystemInfo,
+    http: &Http,
+) -> Result<HashMap<String, Vec<String>>, HttpError> {
     let mut url = Url::parse(&get_sysreq_url()).unwrap();
+    // The API might be on an internal Package Manager, which requires the same settings as the
+    // repositories on it
+    let agent = http.agent_for(&url)?;
 
     {
         let mut pairs = url.query_pairs_mut();
@@ -106,23 +119,26 @@ pub fn get_system_requirements(system_info: &SystemInfo) -> HashMap<String, Vec<
         pairs.append_pair("release", version.as_str());
     }
 
-    log::debug!("Getting sysreq data from {}", url.as_str());
+    log::debug!(
+        "Getting sysreq data from {}",
+        http::redact_url(url.as_str())
+    );
 
-    let response = agent
-        .get(url.as_str())
-        .header("Accept", "application/json")
+    let mut request = agent.get(url.as_str()).header("Accept", "application/json");
+    for (key, value) in http.repository_headers(&url)? {
+        request = request.header(key, value);
+    }
+    let response = request
         .call()
-        .unwrap()
-        .body_mut()
-        .read_json::<Response>()
-        .unwrap();
+        .and_then(|mut res| res.body_mut().read_json::<Response>())
+        .map_err(|e| HttpError::new(&url, HttpErrorKind::Ureq(Box::new(e))))?;
 
     let mut out = HashMap::new();
     for package in response.requirements {
         out.insert(package.name, package.requirements.packages);
     }
 
-    out
+    Ok(out)
 }
 
 /// Extract package name from rpm query output
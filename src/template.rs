@@ -0,0 +1,106 @@
+//! Expansion of `${VAR}` and `${VAR:-default}` references to environment variables, used to keep
+//! secrets such as tokens out of the config and the lockfile.
+
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum ExpandError {
+    #[error("Environment variable `{0}` is not set")]
+    MissingVar(String),
+    #[error("Unterminated `${{` in `{0}`")]
+    Unterminated(String),
+}
+
+/// Replaces all the `${VAR}` and `${VAR:-default}` in the string by the value of the env var.
+/// A `$` not followed by `{` is kept as is.
+pub(crate) fn expand_env_vars(s: &str) -> Result<String, ExpandError> {
+    let mut out = String::with_capacity(s.len());
+    let mut rest = s;
+
+    while let Some(start) = rest.find("${") {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let Some(end) = after.find('}') else {
+            return Err(ExpandError::Unterminated(s.to_string()));
+        };
+        let reference = &after[..end];
+        let (name, default) = match reference.split_once(":-") {
+            Some((name, default)) => (name, Some(default)),
+            None => (reference, None),
+        };
+
+        match std::env::var(name) {
+            Ok(value) => out.push_str(&value),
+            Err(_) => match default {
+                Some(d) => out.push_str(d),
+                None => return Err(ExpandError::MissingVar(name.to_string())),
+            },
+        }
+        rest = &after[end + 1..];
+    }
+    out.push_str(rest);
+
+    Ok(out)
+}
+
+pub(crate) fn is_template(s: &str) -> bool {
+    s.contains("${")
+}
+
+/// The URLs that were written as templates: expanded URL -> template.
+/// The expanded URLs are used everywhere at runtime but only the templates are written to disk.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct UrlTemplates(HashMap<String, String>);
+
+impl UrlTemplates {
+    pub(crate) fn insert(&mut self, expanded: impl Into<String>, template: impl Into<String>) {
+        self.0.insert(expanded.into(), template.into());
+    }
+
+    pub(crate) fn extend(&mut self, other: &UrlTemplates) {
+        self.0
+            .extend(other.0.iter().map(|(k, v)| (k.clone(), v.clone())));
+    }
+
+    pub(crate) fn remove(&mut self, expanded: &str) -> Option<String> {
+        self.0.remove(expanded)
+    }
+
+    /// The template of that URL, or the URL itself if it wasn't a template
+    pub fn template_of<'a>(&'a self, url: &'a str) -> &'a str {
+        self.0.get(url).map(|t| t.as_str()).unwrap_or(url)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_expand_env_vars() {
+        // SAFETY: the variables are only used by this test
+        unsafe {
+            std::env::set_var("RV_TEST_TEMPLATE_TOKEN", "secret");
+            std::env::remove_var("RV_TEST_TEMPLATE_MISSING");
+        }
+
+        assert_eq!(
+            expand_env_vars("https://${RV_TEST_TEMPLATE_TOKEN}@example.com/$HOME").unwrap(),
+            "https://secret@example.com/$HOME"
+        );
+        assert_eq!(
+            expand_env_vars("https://${RV_TEST_TEMPLATE_MISSING:-ppm.example.com}/cran").unwrap(),
+            "https://ppm.example.com/cran"
+        );
+        assert_eq!(
+            expand_env_vars("https://${RV_TEST_TEMPLATE_MISSING}/cran"),
+            Err(ExpandError::MissingVar(
+                "RV_TEST_TEMPLATE_MISSING".to_string()
+            ))
+        );
+        assert!(matches!(
+            expand_env_vars("https://${RV_TEST_TEMPLATE_TOKEN/cran"),
+            Err(ExpandError::Unterminated(_))
+        ));
+    }
+}
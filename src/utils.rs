@@ -4,11 +4,13 @@ use std::time::Duration;
 use indicatif::{ProgressBar, ProgressStyle};
 
 use crate::consts::NUM_CPUS_ENV_VAR_NAME;
+use crate::global_config::global_config;
 
 pub(crate) fn get_max_workers() -> usize {
     std::env::var(NUM_CPUS_ENV_VAR_NAME)
         .ok()
         .and_then(|x| x.parse::<usize>().ok())
+        .or_else(|| global_config().num_workers())
         .unwrap_or_else(num_cpus::get)
 }
 
@@ -0,0 +1,171 @@
+//! A workspace is a root rproject.toml listing member projects in `[workspace]`.
+//! All the members are resolved together into the single lockfile of the root, but each member
+//! keeps its own library containing only what its own dependencies require.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::consts::CONFIG_FILENAME;
+use crate::snapshot::without_snapshot;
+use crate::{Config, ConfigDependency, Library, SystemInfo};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct WorkspaceMember {
+    /// The member directory, as written in the `[workspace]` section
+    pub path: String,
+    /// The absolute directory of the member
+    pub dir: PathBuf,
+    /// The member config. Its local dependencies paths are relative to the workspace root.
+    pub config: Config,
+}
+
+impl WorkspaceMember {
+    pub fn name(&self) -> &str {
+        self.config.name()
+    }
+
+    /// A member can be selected either by its directory or its project name
+    pub fn matches(&self, name: &str) -> bool {
+        let name = name.trim_end_matches('/');
+        self.path.trim_end_matches('/') == name || self.name() == name
+    }
+
+    /// The dependencies of that member across all its groups
+    pub fn all_dependencies(&self) -> Vec<&ConfigDependency> {
+        self.config.all_dependencies()
+    }
+
+    /// The library of the member, in its own directory unless it sets a custom one
+    pub fn library(&self, system_info: &SystemInfo, r_version: [u32; 2]) -> Library {
+        if let Some(p) = self.config.library() {
+            Library::new_custom(&self.dir, p)
+        } else {
+            Library::new(&self.dir, system_info, r_version)
+        }
+    }
+}
+
+/// Loads the members of the workspace defined in the config at the root
+pub fn load_workspace_members(
+    root_dir: &Path,
+    root_config: &Config,
+) -> Result<Vec<WorkspaceMember>, WorkspaceError> {
+    // The members use the snapshot of the root so the repositories are compared without it
+    let root_repositories: HashMap<_, _> = root_config
+        .repositories()
+        .iter()
+        .map(|r| (without_snapshot(&r.url), &r.url))
+        .collect();
+    let mut members = Vec::with_capacity(root_config.workspace_members().len());
+
+    for path in root_config.workspace_members() {
+        let dir = root_dir.join(path);
+        let config_file = dir.join(CONFIG_FILENAME);
+        let mut config = Config::from_file(&config_file).map_err(|e| WorkspaceError {
+            path: config_file.clone().into(),
+            source: WorkspaceErrorKind::Config(e),
+        })?;
+
+        if !config.workspace_members().is_empty() {
+            return Err(WorkspaceError {
+                path: config_file.into(),
+                source: WorkspaceErrorKind::NestedWorkspace,
+            });
+        }
+
+        // Everything is resolved from the root so the members can't bring their own repositories
+        let mut replacements = HashMap::new();
+        for repo in config.repositories() {
+            let Some(root_url) = root_repositories.get(&without_snapshot(&repo.url)) else {
+                return Err(WorkspaceError {
+                    path: config_file.into(),
+                    source: WorkspaceErrorKind::UnknownRepository(repo.url().to_string()),
+                });
+            };
+            replacements.insert(repo.url().to_string(), *root_url);
+        }
+        config.replace_repository_urls(
+            &replacements
+                .iter()
+                .map(|(url, root_url)| (url.as_str(), *root_url))
+                .collect(),
+        );
+
+        if !config.r_version().hazy_match(root_config.r_version()) {
+            log::warn!(
+                "Workspace member `{path}` uses R {} but the workspace root uses R {}, the version of the root is used.",
+                config.r_version().original,
+                root_config.r_version().original
+            );
+        }
+
+        config.rebase_local_paths(Path::new(path));
+        members.push(WorkspaceMember {
+            path: path.to_string(),
+            dir,
+            config,
+        });
+    }
+
+    Ok(members)
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("Failed to load workspace member at `{path}`")]
+#[non_exhaustive]
+pub struct WorkspaceError {
+    pub path: Box<Path>,
+    pub source: WorkspaceErrorKind,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum WorkspaceErrorKind {
+    #[error(transparent)]
+    Config(#[from] crate::config::ConfigLoadError),
+    #[error("Workspaces cannot be nested, a member cannot have a `[workspace]` section")]
+    NestedWorkspace,
+    #[error("Repository {0} is not listed in the repositories of the workspace root")]
+    UnknownRepository(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn can_load_workspace_members() {
+        let root_dir = Path::new("src/tests/workspace");
+        let config = Config::from_file(root_dir.join(CONFIG_FILENAME)).unwrap();
+        let members = load_workspace_members(root_dir, &config).unwrap();
+        assert_eq!(members.len(), 2);
+        assert!(members[0].matches("analysis/clean"));
+        assert!(members[0].matches("analysis/clean/"));
+        assert!(members[1].matches("report"));
+        assert_eq!(
+            members[1].config.dependencies()[1].local_path(),
+            Some(PathBuf::from("analysis/report/../../helpers"))
+        );
+    }
+
+    #[test]
+    fn errors_on_member_with_unknown_repository() {
+        let config = Config::from_str(
+            r#"
+[project]
+name = "root"
+r_version = "4.4"
+repositories = []
+
+[workspace]
+members = ["analysis/clean"]
+"#,
+        )
+        .unwrap();
+        let res = load_workspace_members(Path::new("src/tests/workspace"), &config);
+        assert!(matches!(
+            res.unwrap_err().source,
+            WorkspaceErrorKind::UnknownRepository(_)
+        ));
+    }
+}